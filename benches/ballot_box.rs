@@ -0,0 +1,114 @@
+//! The cost of submitting a ballot to the voting server, for ballot boxes of increasing size.
+//!
+//! The ballot box of a single election is filled progressively, and at each size the same fresh
+//! voter submits their ballot (which replaces itself after the first iteration).
+#![allow(non_snake_case)]
+
+use belenios::datatypes::questions::QuestionBuilder;
+use belenios::participants::credential_authority::CredentialAuthority;
+use belenios::participants::messages::*;
+use belenios::participants::participant_template::*;
+use belenios::participants::server_admin::ServerAdmin;
+use belenios::participants::trustee::Trustee;
+use belenios::participants::voter::Voter;
+use belenios::participants::voting_server::{self, VotingServer};
+use belenios::primitives::rng::SecureRandom;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use ring::rand::SystemRandom;
+use std::sync::{Arc, Mutex};
+
+const SIZES: [usize; 4] = [10, 100, 1000, 10000];
+
+/// Sets up an election with `num_voters` voters, returning the voting server in its voting
+/// phase along with a ballot for each voter.
+fn setup_election(
+    rng: Arc<Mutex<dyn SecureRandom>>,
+    num_voters: usize,
+) -> (VotingServer<voting_server::V4>, Vec<V3Mi>) {
+    let voters = E1MBuilder::default()
+        .voters(vec![1; num_voters])
+        .build()
+        .unwrap();
+    let (_, message_SA) = ServerAdmin::new(rng.clone()).process_message(voters.clone());
+    let (voting_server, message_VS) = VotingServer::new(rng.clone()).process_message(voters);
+    let context = E8MiBuilder::default()
+        .uuid(message_VS.uuid().clone())
+        .index(0)
+        .build()
+        .unwrap();
+    let (credential_authority, credentials) =
+        CredentialAuthority::new(rng.clone()).process_message(E3M::from((message_VS, message_SA)));
+    let credentials: Vec<E4Mi> = credentials.into();
+    let (_, message) = credential_authority.process_message(EmptyMessage);
+    let (voting_server, message) = voting_server.process_message(message);
+    message.check.unwrap();
+    let (_, trustee_key) = Trustee::new(rng.clone()).process_message(context);
+    let (voting_server, message) = voting_server.process_message(E9M::from(vec![trustee_key]));
+    message.check.unwrap();
+    let question = QuestionBuilder::default()
+        .question("Q1")
+        .answers(vec!["A", "B"])
+        .build()
+        .unwrap();
+    let election = E10MBuilder::default()
+        .questions(vec![question])
+        .version(1)
+        .description("Benchmark".to_string())
+        .name("Benchmark".to_string())
+        .administrator("Admin".to_string())
+        .credential_authority("CA".to_string())
+        .build()
+        .unwrap();
+    let (voting_server, election) = voting_server.process_message(election);
+    let (voting_server, _) = voting_server.process_message(EmptyMessage);
+    let ballots = credentials
+        .into_iter()
+        .map(|credential| {
+            let (voter, _) = Voter::new(rng.clone()).process_message(credential);
+            let (voter, _) = voter.process_message(election.clone());
+            let (_, ballot) = voter.process_message(V2Mi {
+                choices: vec![vec![true, false]],
+            });
+            ballot
+        })
+        .collect();
+    (voting_server, ballots)
+}
+
+fn bench_ballot_submission(c: &mut Criterion) {
+    let rng: Arc<Mutex<dyn SecureRandom>> = Arc::new(Mutex::new(SystemRandom::new()));
+    let (voting_server, mut ballots) = setup_election(rng, SIZES[SIZES.len() - 1] + 1);
+    let fresh = ballots.pop().unwrap();
+    let mut ballots = ballots.into_iter();
+    let mut voting_server = Some(voting_server);
+    let mut filled = 0;
+
+    let mut group = c.benchmark_group("ballot_submission");
+    for size in SIZES {
+        for ballot in ballots.by_ref().take(size - filled) {
+            let (server, message) = voting_server.take().unwrap().process_message(ballot);
+            message.check.unwrap();
+            voting_server = Some(server);
+        }
+        filled = size;
+        group.bench_with_input(BenchmarkId::from_parameter(size), &fresh, |b, fresh| {
+            b.iter_batched(
+                || fresh.clone(),
+                |ballot| {
+                    let (server, message) = voting_server.take().unwrap().process_message(ballot);
+                    message.check.unwrap();
+                    voting_server = Some(server);
+                },
+                BatchSize::SmallInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(20);
+    targets = bench_ballot_submission
+}
+criterion_main!(benches);
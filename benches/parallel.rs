@@ -0,0 +1,142 @@
+//! Compares the sequential and parallel paths (see the `parallel` feature) of the universal
+//! verification of an election, which verifies every ballot, and of the aggregation of the
+//! encrypted tally by the voting server, for elections of increasing size.
+//!
+//! Both paths run the same code, built with the `parallel` feature: the sequential one on a rayon
+//! pool of a single thread, the parallel one on a pool of a thread per core.
+#![allow(non_snake_case)]
+
+use belenios::datatypes::bulletin_board::Entry;
+use belenios::datatypes::credentials::Password;
+use belenios::datatypes::questions::QuestionBuilder;
+use belenios::participants::credential_authority::CredentialAuthority;
+use belenios::participants::messages::*;
+use belenios::participants::participant_template::*;
+use belenios::participants::server_admin::ServerAdmin;
+use belenios::participants::snapshot::Snapshot;
+use belenios::participants::trustee::Trustee;
+use belenios::participants::voter::Voter;
+use belenios::participants::voting_server::{self, VotingServer};
+use belenios::primitives::rng::SecureRandom;
+use belenios::verify::verify_election;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use ring::rand::SystemRandom;
+use std::sync::{Arc, Mutex};
+
+const SIZES: [usize; 2] = [100, 1000];
+
+/// Runs an election with `num_voters` voters up to the end of its voting phase, every voter
+/// having cast a ballot.
+fn setup_election(
+    rng: Arc<Mutex<dyn SecureRandom>>,
+    num_voters: usize,
+) -> VotingServer<voting_server::V4> {
+    let voters = E1MBuilder::default()
+        .voters(vec![1; num_voters])
+        .build()
+        .unwrap();
+    let (_, message_SA) = ServerAdmin::new(rng.clone()).process_message(voters.clone());
+    let (voting_server, message_VS) = VotingServer::new(rng.clone()).process_message(voters);
+    let context = E8MiBuilder::default()
+        .uuid(message_VS.uuid().clone())
+        .index(0)
+        .build()
+        .unwrap();
+    let (credential_authority, credentials) =
+        CredentialAuthority::new(rng.clone()).process_message(E3M::from((message_VS, message_SA)));
+    let credentials: Vec<E4Mi> = credentials.into();
+    let (_, message) = credential_authority.process_message(EmptyMessage);
+    let (voting_server, message) = voting_server.process_message(message);
+    message.check.unwrap();
+    let (_, trustee_key) = Trustee::new(rng.clone()).process_message(context);
+    let (voting_server, message) = voting_server.process_message(E9M::from(vec![trustee_key]));
+    message.check.unwrap();
+    let question = QuestionBuilder::default()
+        .question("Q1")
+        .answers(vec!["A", "B", "C"])
+        .build()
+        .unwrap();
+    let election = E10MBuilder::default()
+        .questions(vec![question])
+        .version(1)
+        .description("Benchmark".to_string())
+        .name("Benchmark".to_string())
+        .administrator("Admin".to_string())
+        .credential_authority("CA".to_string())
+        .build()
+        .unwrap();
+    let (voting_server, election) = voting_server.process_message(election);
+    let (mut voting_server, _) = voting_server.process_message(EmptyMessage);
+    for (i, credential) in credentials.into_iter().enumerate() {
+        let (voter, _) = Voter::new(rng.clone()).process_message(credential);
+        let (voter, _) = voter.process_message(election.clone());
+        let mut choices = vec![false; 3];
+        choices[i % 3] = true;
+        let (_, ballot) = voter.process_message(V2Mi {
+            choices: vec![choices],
+        });
+        let (server, message) = voting_server.process_message(ballot);
+        message.check.unwrap();
+        voting_server = server;
+    }
+    voting_server
+}
+
+fn bench_parallel(c: &mut Criterion) {
+    let rng: Arc<Mutex<dyn SecureRandom>> = Arc::new(Mutex::new(SystemRandom::new()));
+    let key = Password::gen(rng.clone());
+    let sequential = ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    let parallel = ThreadPoolBuilder::new().build().unwrap();
+    let pools: [(&str, &ThreadPool); 2] = [("sequential", &sequential), ("parallel", &parallel)];
+
+    // For each size, the voting server at the end of the voting phase, and the bulletin board
+    // once it published the encrypted tally.
+    let elections: Vec<(usize, Snapshot, Vec<Entry>)> = SIZES
+        .iter()
+        .map(|size| {
+            let voting_server = setup_election(rng.clone(), *size);
+            let snapshot = voting_server.snapshot(&key);
+            let (voting_server, _) = voting_server.process_message(EmptyMessage);
+            let entries = voting_server.bulletin_board().entries().to_vec();
+            (*size, snapshot, entries)
+        })
+        .collect();
+
+    let mut group = c.benchmark_group("verify_election");
+    for (size, _, entries) in elections.iter() {
+        for (name, pool) in pools {
+            group.bench_with_input(BenchmarkId::new(name, size), entries, |b, entries| {
+                b.iter(|| pool.install(|| assert!(verify_election(entries).is_valid())))
+            });
+        }
+    }
+    group.finish();
+
+    // As participants are not `Send`, the voting server is resumed on the pool, which is timed
+    // along with the aggregation.
+    let mut group = c.benchmark_group("encrypted_tally");
+    for (size, snapshot, _) in elections.iter() {
+        for (name, pool) in pools {
+            group.bench_with_input(BenchmarkId::new(name, size), snapshot, |b, snapshot| {
+                b.iter(|| {
+                    pool.install(|| {
+                        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+                        let voting_server =
+                            VotingServer::<voting_server::V4>::resume(rng, snapshot, &key).unwrap();
+                        let (_, encrypted_tally) = voting_server.process_message(EmptyMessage);
+                        encrypted_tally
+                    })
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group! {
+    name = benches;
+    config = Criterion::default().sample_size(10);
+    targets = bench_parallel
+}
+criterion_main!(benches);
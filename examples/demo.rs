@@ -0,0 +1,199 @@
+#![allow(non_snake_case, clippy::needless_range_loop)]
+use belenios::datatypes::questions::QuestionBuilder;
+use belenios::participants::credential_authority::CredentialAuthority;
+use belenios::participants::messages::*;
+use belenios::participants::participant_template::*;
+use belenios::participants::server_admin::ServerAdmin;
+use belenios::participants::trustee::Trustee;
+use belenios::participants::voter::Voter;
+use belenios::participants::voting_server::VotingServer;
+use ring::rand::SystemRandom;
+use std::sync::{Arc, Mutex};
+
+fn main() {
+    //
+    // ***START OF THE ELECTION SETUP PHASE***
+    //
+
+    // THE ELECTION SETUP
+    let NUM_VOTERS = 10;
+    let NUM_TRUSTEES = 9;
+    // Defining the weights of the voters (10 voters of weight 1 each).
+    let voters = vec![1; NUM_VOTERS];
+    let election = E1MBuilder::default().voters(voters).build().unwrap();
+
+    // Defining the various parties who participate in the election.
+    let rng = Arc::new(Mutex::new(SystemRandom::new()));
+    // Loading election into voting server and server administrator
+    let server_admin = ServerAdmin::new(rng.clone());
+    // Loading election into voting server
+    let voting_server = VotingServer::new(rng.clone());
+
+    let (_server_admin, message_SA_to_CA) = server_admin.process_message(election.clone());
+    let (voting_server, message_VS_to_CA) = voting_server.process_message(election);
+    let message_to_CA = E3M::from((message_VS_to_CA, message_SA_to_CA));
+    let uuid = message_to_CA.uuid().clone();
+    let credential_authority = CredentialAuthority::new(rng.clone());
+    let (credential_authority, message_to_voters) =
+        credential_authority.process_message(message_to_CA);
+
+    let message_to_voters: Vec<E4Mi> = message_to_voters.into();
+    let mut voters = Vec::new();
+    // Setting up the voters
+    for i in 0..NUM_VOTERS {
+        let new_voter = Voter::new(rng.clone());
+        let (new_voter, _) = new_voter.process_message(message_to_voters[i].clone());
+        voters.push(new_voter);
+    }
+    // Credential Authority gives the public list of weights/stuff to the Voting Server.
+    let (credential_authority, message) = credential_authority.process_message(EmptyMessage);
+    let (voting_server, message) = voting_server.process_message(message);
+    message
+        .check
+        .expect("The voting server failed the check in E7");
+    // Voting Server initializes the Trustees
+    let mut trustees = Vec::new();
+    let mut trustee_keys = Vec::new();
+
+    for i in 0..NUM_TRUSTEES {
+        let new_trustee = Trustee::new(rng.clone());
+        // The trustee binds its proofs to the election, and to its index.
+        let context = E8MiBuilder::default()
+            .uuid(uuid.clone())
+            .index(i)
+            .build()
+            .unwrap();
+        let (new_trustee, trustee_key) = new_trustee.process_message(context);
+        trustees.push(new_trustee);
+        trustee_keys.push(trustee_key);
+    }
+    let trustee_keys: E9M = trustee_keys.into();
+    let (voting_server, message) = voting_server.process_message(trustee_keys);
+    message.check.expect(
+        "If a trustee tried to fake a ZK proof, their index has been recorded in this message",
+    );
+    //
+    // DEFINING THE ELECTION
+    //
+    let quest = "Who should be IACR director in 2021?";
+    let ans = vec!["Mark Fischlin", "Nadia Heninger", "Anna Lysyanskaya"];
+    let question_one = QuestionBuilder::default()
+        .question(quest)
+        .answers(ans)
+        .build()
+        .unwrap();
+    let quest = "Which Hardness Assumption will be broken next?";
+    let ans = vec![
+        "RLWE with Small Galois Group",
+        "Small Moduli LWR",
+        "RSA will be destroye",
+    ];
+    let question_two = QuestionBuilder::default()
+        .question(quest)
+        .answers(ans)
+        .build()
+        .unwrap();
+    let description = "This is a Test Election".to_string();
+    let name = "Test Election".to_string();
+    let admin_name = "Mark Schultz's Left Hand".to_string();
+    let ca_name = "Mark Schultz's Right Hand".to_string();
+    //
+    // END DEFINING THE ELECTION
+    //
+
+    let message: E10M = E10MBuilder::default()
+        .questions(vec![question_one, question_two])
+        .version(1)
+        .description(description)
+        .name(name)
+        .administrator(admin_name)
+        .credential_authority(ca_name)
+        .build()
+        .unwrap();
+    let (voting_server, election_message) = voting_server.process_message(message);
+    // This message defines the election, transmit copies of it to voters eventually.
+    let (_credential_authority, message) =
+        credential_authority.process_message(election_message.clone());
+    message
+        .check
+        .expect("The Credential Authority and the Voting Server disagree over the public list L");
+    //
+    // *** END OF THE ELECTION SETUP PHASE ***
+    //
+
+    //
+    // *** START OF THE VOTING PHASE ***
+    //
+
+    // Transmit the election to each voter.
+    let voters: Vec<(Voter<belenios::participants::voter::V1>, EmptyMessage)> = voters
+        .into_iter()
+        .map(|v| v.process_message(election_message.clone()))
+        .collect();
+    // Create votes for voters.
+    // Each choice is a Vec<bool> denoting which options to vote for on a particular question.
+    // Associated with each voter is then a Vec<Vec<bool>>, denoting for each questino which
+    // options to vote for.
+
+    // Q1 everyone votes for the same person
+    let choice_q1: Vec<bool> = vec![false, false, true];
+    // Q2 there will be three options people are roughly split between
+    let choice_q2a = vec![true, false, false];
+    let choice_q2b = vec![false, true, false];
+    let choice_q2c = vec![false, false, true];
+
+    let voters: Vec<(Voter<belenios::participants::voter::V1>, V3Mi)> = voters
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (v, _))| {
+            let mut choices = Vec::new();
+            choices.push(choice_q1.clone());
+            if idx <= 3 {
+                choices.push(choice_q2a.clone());
+            } else if idx <= 7 {
+                choices.push(choice_q2b.clone())
+            } else {
+                choices.push(choice_q2c.clone());
+            }
+            let message = V2Mi { choices };
+            v.process_message(message)
+        })
+        .collect();
+    let mut new_voters: Vec<Voter<belenios::participants::voter::V1>> = Vec::new();
+    let mut messages = Vec::new();
+    for (voter, message) in voters.into_iter() {
+        new_voters.push(voter);
+        messages.push(message);
+    }
+    let (mut voting_server, _) = voting_server.process_message(EmptyMessage);
+    for ballot in messages.into_iter() {
+        let (temp_voting_server, message) = voting_server.process_message(ballot);
+        // Could handle one of ~3 different errors that could happen here.
+        message
+            .check
+            .expect("Cheap error handling --- will panic if any misbehavior is detected");
+        voting_server = temp_voting_server;
+    }
+    // Have the list of accepted_ballots now.
+
+    //
+    // *** START OF THE TALLY PHASE ***
+    //
+
+    // The Voting Server closes the election, and sends the encrypted tally to each trustee.
+    let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+    let partial_decryptions: Vec<T3Mi> = trustees
+        .into_iter()
+        .map(|t| t.process_message(encrypted_tally.clone()).1)
+        .collect();
+    let (voting_server, message) = voting_server.process_message(T3M::from(partial_decryptions));
+    message
+        .check
+        .expect("A trustee sent an invalid partial decryption");
+    let (_voting_server, message) = voting_server.process_message(EmptyMessage);
+    let result = message.result.expect("Failed to decrypt the tally");
+    println!("{} ballots were tallied.", message.num_tallied);
+    for (i, counts) in result.iter().enumerate() {
+        println!("Question {}: {:?}", i + 1, counts);
+    }
+}
@@ -1,44 +1,110 @@
 //! The Ballot Datatype
 
+use crate::primitives::rng::SecureRandom;
 use crate::{
     datatypes::credentials::{Credential, ExpandedCredential, Password, UUID},
     datatypes::election::Election,
     datatypes::questions::Question,
-    primitives::group::{Point, Scalar},
-    primitives::pki::{Ciphertext, EncryptionKey, EncryptionKeys},
-    primitives::zkp::{IntervalMembership, IntervalMembershipWitness, Proof, ProofSystem},
+    primitives::group::{Group, Point, Ristretto, Scalar},
+    primitives::pki::{Ciphertext, EncryptionKey, EncryptionKeys, SigningKey, SigningKeys},
+    primitives::zkp::{
+        BatchVerifier, Batchable, Disjunction, IntervalMembership, IntervalMembershipWitness,
+        Proof, ProofContext, ProofSystem,
+    },
 };
-use ring::rand::SecureRandom;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
-#[derive(Builder, Clone, Debug)]
-pub struct Ballot {
+#[derive(Builder, Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Ballot<G: Group = Ristretto> {
     pub(crate) election_uuid: UUID,
     pub(crate) election_hash: Vec<u8>,
-    pub(crate) credential: Point,
-    pub(crate) answers: Vec<Answer>,
+    pub(crate) credential: Point<G>,
+    pub(crate) answers: Vec<Answer<G>>,
+    // A Schnorr signature of `hash()` under the credential, see section 4.5 of the specification.
+    #[builder(default)]
+    pub(crate) signature: Option<Proof<G>>,
 }
 
-impl Ballot {
-    pub fn verify(
-        &self,
-        rng: Arc<Mutex<dyn SecureRandom>>,
-        pub_key: &Point,
+/// Appends a length-prefixed list of proofs to data.
+fn extend_with_proofs<G: Group>(data: &mut Vec<u8>, proofs: &[Proof<G>]) {
+    data.extend((proofs.len() as u64).to_le_bytes());
+    for pf in proofs.iter() {
+        data.extend(pf.challenge.as_bytes());
+        data.extend(pf.response.as_bytes());
+    }
+}
+
+impl<G: Group> Ballot<G> {
+    /// A hash of every field of the ballot except its signature.
+    pub(crate) fn hash(&self) -> Vec<u8> {
+        let mut data =
+            bincode::serialize(&(&self.election_uuid, &self.election_hash, &self.credential))
+                .unwrap();
+        data.extend((self.answers.len() as u64).to_le_bytes());
+        for answer in self.answers.iter() {
+            data.extend(bincode::serialize(&answer.choices).unwrap());
+            data.extend((answer.individual_proofs.len() as u64).to_le_bytes());
+            for proofs in answer.individual_proofs.iter() {
+                extend_with_proofs(&mut data, proofs);
+            }
+            extend_with_proofs(&mut data, &answer.overall_proof);
+            if let Some(blank_proof) = &answer.blank_proof {
+                extend_with_proofs(&mut data, blank_proof);
+            }
+        }
+        digest(&SHA256, &data).as_ref().into()
+    }
+    /// Signs the ballot with the secret key of its credential.
+    pub(crate) fn sign(&mut self, rng: Arc<Mutex<dyn SecureRandom>>, secret_key: Scalar<G>) {
+        let hash = self.hash();
+        let (_, pf) = SigningKeys::sign(SigningKey(secret_key), rng, &hash);
+        self.signature = Some(pf);
+    }
+    /// Checks the ballot is signed under its credential.
+    pub fn verify_signature(&self) -> bool {
+        match &self.signature {
+            Some(pf) => SigningKeys::verify(self.credential, (&self.hash(), pf.clone())),
+            None => false,
+        }
+    }
+
+    /// Checks the proofs of each answer, for the question of the same index.
+    /// The proofs are bound to the election of the ballot, which the caller compares with its own.
+    pub fn verify(&self, pub_key: &Point<G>, questions: &[Question]) -> bool {
+        Self::verify_batch(&[self], pub_key, questions)[0]
+    }
+    /// Checks the proofs of many ballots at once, with the same results as `verify` on each of
+    /// them but checking the commitments the voters claim as a single random linear combination,
+    /// and computing any others together (across threads with the `parallel` feature), see
+    /// `BatchVerifier`.
+    pub fn verify_batch(
+        ballots: &[&Ballot<G>],
+        pub_key: &Point<G>,
         questions: &[Question],
-    ) -> bool {
-        for i in 0..self.answers.len() {
-            if self.answers[i].verify(
-                rng.clone(),
-                &self.election_hash,
-                self.credential,
-                pub_key,
-                &questions[i],
-            ) != true
-            {
-                return false;
+    ) -> Vec<bool> {
+        let mut batch = BatchVerifier::new(ballots.len());
+        for (i, ballot) in ballots.iter().enumerate() {
+            if ballot.answers.len() != questions.len() {
+                batch.reject(i);
+                continue;
+            }
+            let context = ProofContext::new(&ballot.election_uuid, None);
+            for (answer, question) in ballot.answers.iter().zip(questions.iter()) {
+                answer.push_proofs(
+                    &mut batch,
+                    i,
+                    &context,
+                    &ballot.election_hash,
+                    ballot.credential,
+                    pub_key,
+                    question,
+                );
             }
         }
-        true
+        batch.verify()
     }
 }
 
@@ -47,56 +113,104 @@ impl Ballot {
 // We also have one proof of the sum being bounded, so `Vec<Proof>`.
 //
 // Note that these are comperable sizes, as the length of the vec = length of the interval.
-#[derive(Clone, Debug, Builder)]
-pub struct Answer {
-    choices: Vec<Ciphertext>,
+//
+// If the question allows blank votes, `choices` starts with an additional encryption of 1 (for a
+// blank vote) or 0, and
+// * `blank_proof` proves that either this choice, or the sum of the other choices, is 0, while
+// * `overall_proof` proves that either this choice is 1, or the sum of the others is in [min, max].
+// See section 4.11 of the specification.
+#[derive(Clone, Debug, Builder, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Answer<G: Group = Ristretto> {
+    pub(crate) choices: Vec<Ciphertext<G>>,
     // Coincidence that these are both Vec<Proofs>
-    individual_proofs: Vec<Vec<Proof>>,
-    overall_proof: Vec<Proof>,
-    // Not implementing blank_proofs at this point
-    blank_proof: Option<()>,
+    pub(crate) individual_proofs: Vec<Vec<Proof<G>>>,
+    pub(crate) overall_proof: Vec<Proof<G>>,
+    pub(crate) blank_proof: Option<Vec<Proof<G>>>,
+    // The commitments (A_j, B_j) of the individual proofs, then of the blank and overall proofs,
+    // which let verifiers check many proofs at once, see `BatchVerifier`.
+    // They are empty for answers in the JSON layout of the specification, which has no room for
+    // them, and not part of the hash signed, as wrong ones do not change whether a ballot verifies.
+    #[builder(default)]
+    pub(crate) commitments: Vec<Vec<Ciphertext<G>>>,
 }
 
-impl Answer {
+type Statements<G> = Vec<(Ciphertext<G>, Scalar<G>)>;
+
+/// The statements of the blank and overall proofs of an answer to a question allowing blank votes,
+/// where `blank` is the encryption of the blank choice and `sum` the sum of the other choices.
+fn blank_statements<G: Group>(
+    blank: Ciphertext<G>,
+    sum: Ciphertext<G>,
+    question: &Question,
+) -> (Statements<G>, Statements<G>) {
+    let blank_statements = vec![(blank, Scalar::zero()), (sum, Scalar::zero())];
+    let mut overall_statements = vec![(blank, Scalar::one())];
+    for i in question.min..=question.max {
+        overall_statements.push((sum, Scalar::from(i)));
+    }
+    (blank_statements, overall_statements)
+}
+
+impl<G: Group> Answer<G> {
     // Verifies a single Answer, to ease testing.
-    // RNG not used in verification, but I need to pass one in due to API design choices in the
-    // ZKPs.
     pub(crate) fn verify(
         &self,
-        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
         election_hash: &[u8],
-        cred: Point,
-        pub_key: &Point,
+        cred: Point<G>,
+        pub_key: &Point<G>,
         question: &Question,
     ) -> bool {
+        let mut batch = BatchVerifier::new(1);
+        self.push_proofs(
+            &mut batch,
+            0,
+            context,
+            election_hash,
+            cred,
+            pub_key,
+            question,
+        );
+        batch.verify()[0]
+    }
+    // Adds the proofs of the answer to a batch, under `group`.
+    #[allow(clippy::too_many_arguments)]
+    fn push_proofs(
+        &self,
+        batch: &mut BatchVerifier<G>,
+        group: usize,
+        context: &ProofContext,
+        election_hash: &[u8],
+        cred: Point<G>,
+        pub_key: &Point<G>,
+        question: &Question,
+    ) {
         // also need a Credential
         // and an Election Hash
         let choices = &self.choices;
         let ind_proofs = &self.individual_proofs;
-        if choices.len() != ind_proofs.len() {
-            return false;
+        let offset = question.blank as usize;
+        if choices.len() != ind_proofs.len() || choices.len() != question.answers.len() + offset {
+            return batch.reject(group);
         }
         let overall_proof = &self.overall_proof;
+        let num_proofs = choices.len() + 1 + question.blank as usize;
+        let claimed =
+            |k: usize| (self.commitments.len() == num_proofs).then(|| &self.commitments[k][..]);
         // Publicly computing S0
-        let mut S0: Vec<u8> = election_hash.clone().into();
+        let mut S0: Vec<u8> = election_hash.into();
         S0.extend(cred.as_bytes());
         // verify the individual proofs.
-        for i in 0..choices.len() {
+        for (k, (ctxt, proof)) in choices.iter().zip(ind_proofs.iter()).enumerate() {
             let finite_set = vec![Scalar::zero(), Scalar::one()];
-            let ctxt = choices[i].clone();
-            let y = pub_key.clone();
-            let pf = ind_proofs[i].clone();
-            let rng = rng.clone();
             let instance = IntervalMembership {
-                ctxt,
-                y,
-                rng,
+                ctxt: *ctxt,
+                y: *pub_key,
                 finite_set,
                 S: S0.clone(),
             };
-            if instance.verify(&pf) == false {
-                return false;
-            }
+            batch.push(group, context, &instance, proof, claimed(k));
         }
         // verify the overall proof
         // Need (summed) ctxt, finite set, and S.
@@ -106,55 +220,99 @@ impl Answer {
         for i in question.min..=question.max {
             finite_set.push(Scalar::from(i));
         }
-        for i in 0..choices.len() {
-            let (alpha, beta) = choices[i].into();
+        for choice in choices[offset..].iter() {
+            let (alpha, beta) = (*choice).into();
             alpha_sum = alpha_sum + alpha;
             beta_sum = beta_sum + beta;
         }
         let serialized = bincode::serialize(&choices).unwrap();
         let S = [S0.clone(), serialized].concat();
-        let ctxt: Ciphertext = (alpha_sum, beta_sum).into();
+        let ctxt: Ciphertext<G> = (alpha_sum, beta_sum).into();
+        if question.blank {
+            let blank_proof = match &self.blank_proof {
+                Some(pf) => pf,
+                None => return batch.reject(group),
+            };
+            let (blank_statements, overall_statements) =
+                blank_statements(choices[0], ctxt, question);
+            let instance = Disjunction {
+                statements: blank_statements,
+                y: *pub_key,
+                S: S.clone(),
+            };
+            batch.push(
+                group,
+                context,
+                &instance,
+                blank_proof,
+                claimed(choices.len()),
+            );
+            let instance = Disjunction {
+                statements: overall_statements,
+                y: *pub_key,
+                S,
+            };
+            let claimed = claimed(choices.len() + 1);
+            return batch.push(group, context, &instance, overall_proof, claimed);
+        }
+        if self.blank_proof.is_some() {
+            return batch.reject(group);
+        }
         let instance = IntervalMembership {
             ctxt,
-            y: pub_key.clone(),
-            rng: rng.clone(),
+            y: *pub_key,
             finite_set,
             S,
         };
-        instance.verify(&overall_proof)
+        batch.push(
+            group,
+            context,
+            &instance,
+            overall_proof,
+            claimed(choices.len()),
+        );
     }
 }
 
 #[derive(Builder)]
-pub(crate) struct StateNeededForAnswer {
+pub(crate) struct StateNeededForAnswer<G: Group = Ristretto> {
     choices: Vec<bool>,
     question: Question,
-    election: Election,
+    election: Election<G>,
     pass: Password,
     rng: Arc<Mutex<dyn SecureRandom>>,
 }
 
-pub(crate) fn gen_S0(election_hash: &[u8], cred: Point) -> Vec<u8> {
+pub(crate) fn gen_S0<G: Group>(election_hash: &[u8], cred: Point<G>) -> Vec<u8> {
     let S0: Vec<u8> = [election_hash, &cred.as_bytes()].concat();
     S0
 }
 
-impl From<StateNeededForAnswer> for Answer {
-    fn from(state: StateNeededForAnswer) -> Self {
+impl<G: Group> From<StateNeededForAnswer<G>> for Answer<G> {
+    fn from(state: StateNeededForAnswer<G>) -> Self {
         let rng = state.rng.clone();
-        let ms = state.choices.clone();
         let question = state.question.clone();
+        // When blank votes are allowed, an answer without any choices is a blank vote, which is
+        // recorded as an additional first choice.
+        let offset = question.blank as usize;
+        let mut ms = Vec::new();
+        if question.blank {
+            ms.push(state.choices.iter().all(|c| !c));
+        }
+        ms.extend(state.choices.iter());
         let uuid = state.election.uuid.clone();
+        let context = ProofContext::new(&uuid, None);
         let cred: Credential = (state.pass.clone(), uuid).into();
-        let expanded_cred: ExpandedCredential = cred.into();
+        let expanded_cred: ExpandedCredential<G> = cred.into();
         let pub_key = expanded_cred.public_key;
         // Generating encryptions + randomness
-        let mut ctxts: Vec<Ciphertext> = Vec::new();
-        let mut rs: Vec<Scalar> = Vec::new();
+        let mut ctxts: Vec<Ciphertext<G>> = Vec::new();
+        let mut rs: Vec<Scalar<G>> = Vec::new();
         let mut individual_pfs = Vec::new();
-        for i in 0..ms.len() {
-            let pk: EncryptionKey = state.election.public_key.into();
-            let (ctxt, r) = pk.enc_leak_randomness(rng.clone(), Scalar::from(ms[i] as u128));
+        let mut commitments = Vec::new();
+        for m in ms.iter() {
+            let pk: EncryptionKey<G> = state.election.public_key.into();
+            let (ctxt, r) = pk.enc_leak_randomness(rng.clone(), Scalar::from(*m as u128));
             ctxts.push(ctxt);
             rs.push(r);
         }
@@ -163,18 +321,16 @@ impl From<StateNeededForAnswer> for Answer {
         let S0 = gen_S0(&election_hash, pub_key);
         let serialized = bincode::serialize(&ctxts).unwrap();
         let S = [S0.clone(), serialized].concat();
-        let y = state.election.public_key.clone();
+        let y = state.election.public_key;
         let finite_set = vec![Scalar::zero(), Scalar::one()];
 
         // Genrating proofs for each encryption
         for i in 0..ms.len() {
-            let rng = rng.clone();
             let ctxt = ctxts[i];
             let r = rs[i];
             let instance = IntervalMembership {
                 ctxt,
                 y,
-                rng,
                 finite_set: finite_set.clone(),
                 S: S0.clone(),
             };
@@ -182,16 +338,17 @@ impl From<StateNeededForAnswer> for Answer {
                 r,
                 i: (ms[i] as usize),
             };
-            let pf = instance.prove(&w);
+            let (pf, pf_commitments) = instance.prove_with_commitments(rng.clone(), &context, &w);
             individual_pfs.push(pf);
+            commitments.push(pf_commitments);
         }
         // Generating the overall proof that the sum of the ciphertexts is in in [min..max]
-        let mut R: Scalar = Scalar::zero();
-        let mut M: Scalar = Scalar::zero();
+        let mut R: Scalar<G> = Scalar::zero();
+        let mut M: Scalar<G> = Scalar::zero();
         let mut idx: u128 = 0;
         let mut alpha_sum = Point::identity();
         let mut beta_sum = Point::identity();
-        for i in 0..ms.len() {
+        for i in offset..ms.len() {
             R = R + rs[i];
             idx += ms[i] as u128;
             M = M + Scalar::from(ms[i] as u128);
@@ -199,16 +356,59 @@ impl From<StateNeededForAnswer> for Answer {
             alpha_sum = alpha_sum + alpha;
             beta_sum = beta_sum + beta;
         }
+        let ctxt = (alpha_sum, beta_sum).into();
+        if question.blank {
+            let (blank_statements, overall_statements) =
+                blank_statements(ctxts[0], ctxt, &question);
+            // For a blank vote, the sum is 0 and the blank choice is 1, and otherwise the blank
+            // choice is 0 and the sum is in [min, max].
+            let (blank_w, overall_w) = if ms[0] {
+                (
+                    IntervalMembershipWitness { r: R, i: 1 },
+                    IntervalMembershipWitness { r: rs[0], i: 0 },
+                )
+            } else {
+                (
+                    IntervalMembershipWitness { r: rs[0], i: 0 },
+                    IntervalMembershipWitness {
+                        r: R,
+                        i: (idx - question.min) as usize + 1,
+                    },
+                )
+            };
+            let instance = Disjunction {
+                statements: blank_statements,
+                y,
+                S: S.clone(),
+            };
+            let (blank_proof, blank_commitments) =
+                instance.prove_with_commitments(rng.clone(), &context, &blank_w);
+            commitments.push(blank_commitments);
+            let instance = Disjunction {
+                statements: overall_statements,
+                y,
+                S,
+            };
+            let (overall_proof, overall_commitments) =
+                instance.prove_with_commitments(rng.clone(), &context, &overall_w);
+            commitments.push(overall_commitments);
+            return AnswerBuilder::default()
+                .choices(ctxts)
+                .individual_proofs(individual_pfs)
+                .overall_proof(overall_proof)
+                .blank_proof(Some(blank_proof))
+                .commitments(commitments)
+                .build()
+                .unwrap();
+        }
         let mut finite_set = Vec::new();
         for i in question.min..=question.max {
             let M = Scalar::from(i);
             finite_set.push(M);
         }
-        let ctxt = (alpha_sum, beta_sum).into();
         let instance = IntervalMembership {
             ctxt,
             y,
-            rng: rng.clone(),
             finite_set,
             S,
         };
@@ -216,12 +416,15 @@ impl From<StateNeededForAnswer> for Answer {
             r: R,
             i: (idx - question.min) as usize,
         };
-        let overall_proof = instance.prove(&w);
+        let (overall_proof, overall_commitments) =
+            instance.prove_with_commitments(rng.clone(), &context, &w);
+        commitments.push(overall_commitments);
         AnswerBuilder::default()
             .choices(ctxts)
             .individual_proofs(individual_pfs)
             .overall_proof(overall_proof)
             .blank_proof(None)
+            .commitments(commitments)
             .build()
             .unwrap()
     }
@@ -254,12 +457,115 @@ mod tests {
             .unwrap();
         let answer: Answer = state.into();
         let expanded_cred: ExpandedCredential = cred.into();
-        assert!(answer.verify(
-            rng.clone(),
-            &election.fingerprint(),
-            expanded_cred.public_key,
-            &election.public_key,
-            &questions[0],
-        ));
+        let verify = |context: &ProofContext| {
+            answer.verify(
+                context,
+                &election.fingerprint(),
+                expanded_cred.public_key,
+                &election.public_key,
+                &questions[0],
+            )
+        };
+        assert!(verify(&ProofContext::new(&election.uuid, None)));
+        // The proofs do not verify for another election.
+        assert!(!verify(&ProofContext::new(&UUID::gen(rng.clone()), None)));
+    }
+
+    #[test]
+    fn test_verify_batch() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let election = crate::datatypes::election::tests::build_election();
+        let questions = election.questions.clone();
+        let ballot = |choices: Vec<bool>| -> Ballot {
+            let pass = Password::gen(rng.clone());
+            let cred: Credential = (pass.clone(), election.uuid.clone()).into();
+            let expanded_cred: ExpandedCredential = cred.into();
+            let answer: Answer = StateNeededForAnswerBuilder::default()
+                .choices(choices)
+                .question(questions[0].clone())
+                .pass(pass)
+                .election(election.clone())
+                .rng(rng.clone())
+                .build()
+                .unwrap()
+                .into();
+            BallotBuilder::default()
+                .election_uuid(election.uuid.clone())
+                .election_hash(election.fingerprint())
+                .credential(expanded_cred.public_key)
+                .answers(vec![answer])
+                .build()
+                .unwrap()
+        };
+        let valid = ballot(vec![false, true, false]);
+        assert_eq!(valid.answers[0].commitments.len(), 4);
+        // Without the commitments, e.g. from the JSON layout, or with wrong ones.
+        let mut unclaimed = ballot(vec![true, false, false]);
+        unclaimed.answers[0].commitments.clear();
+        let mut misclaimed = ballot(vec![false, false, true]);
+        misclaimed.answers[0].commitments[1] = valid.answers[0].commitments[1].clone();
+        // With a proof for an encryption of another choice.
+        let mut forged = ballot(vec![false, true, false]);
+        forged.answers[0].individual_proofs[0] = valid.answers[0].individual_proofs[0].clone();
+        forged.answers[0].commitments[0] = valid.answers[0].commitments[0].clone();
+        // With fewer answers than questions.
+        let mut empty = ballot(vec![false, true, false]);
+        empty.answers.clear();
+        let ballots = [&valid, &unclaimed, &misclaimed, &forged, &empty];
+        let results = Ballot::verify_batch(&ballots, &election.public_key, &questions);
+        assert_eq!(results, vec![true, true, true, false, false]);
+        for (ballot, result) in ballots.iter().zip(results) {
+            assert_eq!(ballot.verify(&election.public_key, &questions), result);
+        }
+    }
+
+    #[test]
+    fn test_blank_answers() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let pass = Password::gen(rng.clone());
+        let mut election = crate::datatypes::election::tests::build_election();
+        election.questions[0].blank = true;
+        let question = election.questions[0].clone();
+        let cred: Credential = (pass.clone(), election.uuid.clone()).into();
+        let expanded_cred: ExpandedCredential = cred.into();
+        let answer = |choices: Vec<bool>| -> Answer {
+            StateNeededForAnswerBuilder::default()
+                .choices(choices)
+                .question(question.clone())
+                .pass(pass.clone())
+                .election(election.clone())
+                .rng(rng.clone())
+                .build()
+                .unwrap()
+                .into()
+        };
+        let context = ProofContext::new(&election.uuid, None);
+        let verify = |answer: &Answer, question: &Question| {
+            answer.verify(
+                &context,
+                &election.fingerprint(),
+                expanded_cred.public_key,
+                &election.public_key,
+                question,
+            )
+        };
+        let blank = answer(vec![false, false, false]);
+        let vote = answer(vec![false, true, false]);
+        assert_eq!(blank.choices.len(), 4);
+        assert!(verify(&blank, &question));
+        assert!(verify(&vote, &question));
+        // Answers to blank questions are not answers to the corresponding non-blank question.
+        let mut non_blank = question.clone();
+        non_blank.blank = false;
+        assert!(!verify(&blank, &non_blank));
+        // The blank proof is mandatory.
+        let mut forged = blank.clone();
+        forged.blank_proof = None;
+        assert!(!verify(&forged, &question));
+        // Marking a vote as blank as well invalidates the blank proof.
+        let mut forged = vote.clone();
+        forged.choices[0] = blank.choices[0];
+        forged.individual_proofs[0] = blank.individual_proofs[0].clone();
+        assert!(!verify(&forged, &question));
     }
 }
@@ -0,0 +1,177 @@
+//! The ballot box of the voting server: the public list L of credentials and the ballots it
+//! accepted, at most one per credential.
+//!
+//! Both are indexed by the encoding of their credential, so that processing a ballot or a
+//! credential update takes constant time however many voters the election has.
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::datatypes::ballot::Ballot;
+use crate::primitives::group::{Group, Point, Ristretto};
+use crate::ProtocolError;
+
+pub struct BallotBox<G: Group = Ristretto> {
+    pub(crate) L: Vec<(Point<G>, u128)>,
+    pub(crate) ballots: Vec<(Ballot<G>, u128)>,
+    // The index in L of each credential.
+    credentials: HashMap<Vec<u8>, usize>,
+    // The index in `ballots` of the ballot accepted for each credential.
+    accepted: HashMap<Vec<u8>, usize>,
+}
+
+impl<G: Group> BallotBox<G> {
+    pub(crate) fn new(L: Vec<(Point<G>, u128)>) -> Self {
+        Self::from_parts(L, Vec::new())
+    }
+    fn from_parts(L: Vec<(Point<G>, u128)>, ballots: Vec<(Ballot<G>, u128)>) -> Self {
+        let mut credentials = HashMap::with_capacity(L.len());
+        for (i, (pt, _)) in L.iter().enumerate() {
+            // A credential appearing twice in L has the weight of its first occurrence.
+            credentials.entry(pt.as_bytes()).or_insert(i);
+        }
+        let accepted = ballots
+            .iter()
+            .enumerate()
+            .map(|(i, (ballot, _))| (ballot.credential.as_bytes(), i))
+            .collect();
+        BallotBox {
+            L,
+            ballots,
+            credentials,
+            accepted,
+        }
+    }
+    /// The public list L of credentials and their weights.
+    pub fn voters(&self) -> &[(Point<G>, u128)] {
+        &self.L
+    }
+    /// The accepted ballots along with the weight of their credential, in no particular order.
+    pub fn ballots(&self) -> &[(Ballot<G>, u128)] {
+        &self.ballots
+    }
+    /// The weight of a credential, if it is in L.
+    pub fn weight(&self, credential: &Point<G>) -> Option<u128> {
+        let i = self.credentials.get(&credential.as_bytes())?;
+        Some(self.L[*i].1)
+    }
+    /// Whether a ballot was already accepted for a credential.
+    pub fn has_ballot(&self, credential: &Point<G>) -> bool {
+        self.accepted.contains_key(&credential.as_bytes())
+    }
+    /// Accepts a ballot, returning the ballot it replaces for the same credential, if any.
+    pub(crate) fn insert(&mut self, ballot: Ballot<G>, weight: u128) -> Option<Ballot<G>> {
+        let key = ballot.credential.as_bytes();
+        match self.accepted.get(&key) {
+            Some(i) => {
+                let (replaced, _) = std::mem::replace(&mut self.ballots[*i], (ballot, weight));
+                Some(replaced)
+            }
+            None => {
+                self.accepted.insert(key, self.ballots.len());
+                self.ballots.push((ballot, weight));
+                None
+            }
+        }
+    }
+    /// Removes the ballot accepted for a credential, if any.
+    pub(crate) fn remove(&mut self, credential: &Point<G>) -> Option<Ballot<G>> {
+        let i = self.accepted.remove(&credential.as_bytes())?;
+        let (ballot, _) = self.ballots.swap_remove(i);
+        // The last ballot took the place of the removed one.
+        if let Some((moved, _)) = self.ballots.get(i) {
+            self.accepted.insert(moved.credential.as_bytes(), i);
+        }
+        Some(ballot)
+    }
+    /// Replaces a credential of L, keeping its weight.
+    pub(crate) fn update_credential(
+        &mut self,
+        old_credential: &Point<G>,
+        new_credential: Point<G>,
+    ) -> Result<(), ProtocolError> {
+        let new_key = new_credential.as_bytes();
+        if self.credentials.contains_key(&new_key) {
+            return Err(ProtocolError::DuplicateCredentialError);
+        }
+        let i = self
+            .credentials
+            .remove(&old_credential.as_bytes())
+            .ok_or(ProtocolError::CredentialNotFoundError)?;
+        self.L[i].0 = new_credential;
+        self.credentials.insert(new_key, i);
+        Ok(())
+    }
+}
+
+// Only L and the ballots are stored, the indices are rebuilt when deserializing.
+impl<G: Group> Serialize for BallotBox<G> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (&self.L, &self.ballots).serialize(serializer)
+    }
+}
+
+impl<'de, G: Group> Deserialize<'de> for BallotBox<G> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (L, ballots) = Deserialize::deserialize(deserializer)?;
+        Ok(Self::from_parts(L, ballots))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::ballot::BallotBuilder;
+    use crate::datatypes::credentials::UUID;
+    use crate::primitives::rng::SecureRandom;
+    use ring::rand::SystemRandom;
+    use std::sync::{Arc, Mutex};
+
+    fn ballot(uuid: &UUID, credential: Point) -> Ballot {
+        BallotBuilder::default()
+            .election_uuid(uuid.clone())
+            .election_hash(Vec::new())
+            .credential(credential)
+            .answers(Vec::new())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_ballot_box_indices() {
+        let rng: Arc<Mutex<dyn SecureRandom>> = Arc::new(Mutex::new(SystemRandom::new()));
+        let uuid = UUID::gen(rng.clone());
+        let credentials: Vec<Point> = (0..4).map(|_| Point::sample_uniform(rng.clone())).collect();
+        let L = credentials.iter().map(|pt| (*pt, 2)).collect();
+        let mut ballot_box: BallotBox = BallotBox::new(L);
+        for pt in credentials.iter().take(3) {
+            assert!(ballot_box.insert(ballot(&uuid, *pt), 2).is_none());
+        }
+        assert!(ballot_box
+            .insert(ballot(&uuid, credentials[1]), 2)
+            .is_some());
+        assert_eq!(ballot_box.ballots().len(), 3);
+
+        // Removing a ballot moves the last one in its place, which stays indexed.
+        assert!(ballot_box.remove(&credentials[0]).is_some());
+        assert!(ballot_box.remove(&credentials[0]).is_none());
+        assert!(ballot_box.has_ballot(&credentials[2]));
+        assert!(ballot_box.remove(&credentials[2]).is_some());
+        assert_eq!(ballot_box.ballots().len(), 1);
+
+        // A replaced credential keeps its weight, and the indices survive serialization.
+        let new_credential = Point::sample_uniform(rng.clone());
+        let updated = ballot_box.update_credential(&credentials[3], credentials[1]);
+        assert_eq!(updated, Err(ProtocolError::DuplicateCredentialError));
+        ballot_box
+            .update_credential(&credentials[3], new_credential)
+            .unwrap();
+        let bytes = bincode::serialize(&ballot_box).unwrap();
+        let ballot_box: BallotBox = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(ballot_box.weight(&new_credential), Some(2));
+        assert_eq!(ballot_box.weight(&credentials[3]), None);
+        assert!(ballot_box.has_ballot(&credentials[1]));
+        assert!(!ballot_box.has_ballot(&credentials[0]));
+    }
+}
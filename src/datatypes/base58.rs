@@ -7,6 +7,7 @@
 //! There are some mild differences between all situations corresponding to the
 //! * length, and
 //! * presence of a checksum "digit"
+//!
 //! in each of the three contexts.
 //!
 //! The shorter lengths in certain contexts were likely chosen for better user experience.
@@ -14,7 +15,7 @@
 //! maximum used.
 //!
 
-use ring::rand::SecureRandom;
+use crate::primitives::rng::SecureRandom;
 use std::fmt;
 use std::sync::{Arc, Mutex};
 
@@ -26,8 +27,7 @@ const BASE58_BYTELEN: usize = 128 / 8;
 pub(crate) const BASE58_STRLEN: usize = 22;
 
 /// The base-58 alphabet used by Belenios, see section 4.7 of the specification.
-const ALPHABET_STR: &'static [u8; 58] =
-    b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+const ALPHABET_STR: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
 
 /// A lookup table such that the ith element is equal to ith byte of ALPHABET_STR.
 /// ```ignore
@@ -78,7 +78,7 @@ impl From<u128> for Base58 {
     /// # use belenios::datatypes::base58::Base58;
     /// let s : String = String::from("1");
     /// let other = Base58(s);
-    /// let bytearray = [0 as u8; 1];
+    /// let bytearray = [0u8; 1];
     /// assert_eq!(Base58::from(&bytearray[..]), other);
     /// ```
     fn from(inp: u128) -> Self {
@@ -93,9 +93,9 @@ impl From<u128> for Base58 {
     }
 }
 
-impl<'a> Into<&'a [u8]> for &'a Base58 {
-    fn into(self) -> &'a [u8] {
-        self.0.as_bytes()
+impl<'a> From<&'a Base58> for &'a [u8] {
+    fn from(base58: &'a Base58) -> Self {
+        base58.0.as_bytes()
     }
 }
 
@@ -110,7 +110,7 @@ impl Base58 {
     /// It is only close to uniformly random as it as 128 bits of entropy,
     /// while there are ~2^128.8 base58 numbers of length 22.
     pub fn gen(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
-        let mut buff = [0 as u8; 128 / 8];
+        let mut buff = [0u8; 128 / 8];
         rng.lock().unwrap().fill(&mut buff).unwrap();
         Base58::from(u128::from_be_bytes(buff))
     }
@@ -123,9 +123,9 @@ mod tests {
     fn test_conversion() {
         // Single character
         let mut v: Vec<u8> = vec![LOOKUPTABLE[0]; BASE58_STRLEN];
-        for num in 0..58 {
+        for (num, c) in LOOKUPTABLE.iter().enumerate() {
             let converted_val: Base58 = (num as u128).into();
-            v[BASE58_STRLEN - 1] = LOOKUPTABLE[num];
+            v[BASE58_STRLEN - 1] = *c;
             let s = String::from_utf8(v.clone()).unwrap();
             let intended_val = Base58(s);
             assert_eq!(&converted_val, &intended_val);
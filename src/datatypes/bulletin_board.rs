@@ -0,0 +1,153 @@
+//! The public bulletin board of an election, e.g. an append-only log of everything the voting
+//! server publishes.
+//!
+//! Each entry holds the hash of the previous one, so the latest hash (the "head") commits to the
+//! whole log: once it is published, removing, reordering or modifying any entry is detected by
+//! recomputing the chain with `BulletinBoard::verify`.
+
+use ring::digest::{digest, SHA256, SHA256_OUTPUT_LEN};
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::ballot::Ballot;
+use crate::datatypes::election::Election;
+use crate::participants::messages::{C3M_CA_to_VS, T3Mi};
+use crate::primitives::group::{Group, Point, Ristretto};
+use crate::primitives::pki::Ciphertext;
+use crate::primitives::vss::PolynomialCommitment;
+use crate::primitives::zkp::Proof;
+use crate::ProtocolError;
+
+/// Something the voting server published, in the order of the steps of the protocol.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum Event<G: Group = Ristretto> {
+    Election(Election<G>),
+    /// The public list L of credentials and their weights.
+    Voters(Vec<(Point<G>, u128)>),
+    /// The keys of the trustees and the proofs of knowledge of their private keys, see the `E9`
+    /// state of the voting server.
+    /// For "pedersen" trustees, the commitments of the i-th dealer if it is qualified, which the
+    /// keys follow from (none for single trustees).
+    TrusteeKeys {
+        trustee_keys: Vec<Point<G>>,
+        proofs: Vec<Option<Proof<G>>>,
+        threshold: Option<usize>,
+        commitments: Vec<Option<PolynomialCommitment<G>>>,
+    },
+    /// A ballot the server accepted. A later ballot for the same credential replaces it.
+    Ballot(Ballot<G>),
+    CredentialUpdate(C3M_CA_to_VS<G>),
+    EncryptedTally(Vec<Vec<Ciphertext<G>>>),
+    /// The partial decryptions which passed the checks of step T4.
+    PartialDecryptions(Vec<Option<T3Mi<G>>>),
+    Result(Vec<Vec<u128>>),
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Entry<G: Group = Ristretto> {
+    /// The hash of the previous entry, or all zeroes for the first one.
+    pub previous: Vec<u8>,
+    pub event: Event<G>,
+}
+
+impl<G: Group> Entry<G> {
+    pub fn hash(&self) -> Vec<u8> {
+        let data = bincode::serialize(self).unwrap();
+        digest(&SHA256, &data).as_ref().into()
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BulletinBoard<G: Group = Ristretto> {
+    entries: Vec<Entry<G>>,
+    head: Vec<u8>,
+}
+
+impl<G: Group> BulletinBoard<G> {
+    pub(crate) fn new() -> Self {
+        BulletinBoard {
+            entries: Vec::new(),
+            head: vec![0; SHA256_OUTPUT_LEN],
+        }
+    }
+    pub(crate) fn append(&mut self, event: Event<G>) {
+        let entry = Entry {
+            previous: self.head.clone(),
+            event,
+        };
+        self.head = entry.hash();
+        self.entries.push(entry);
+    }
+    pub fn entries(&self) -> &[Entry<G>] {
+        &self.entries
+    }
+    /// The hash of the latest entry, which commits to the whole log.
+    pub fn head(&self) -> &[u8] {
+        &self.head
+    }
+    /// Exports the log, to be checked by `BulletinBoard::verify` after `BulletinBoard::import`.
+    pub fn export(&self) -> Vec<u8> {
+        bincode::serialize(&self.entries).unwrap()
+    }
+    pub fn import(bytes: &[u8]) -> Result<Vec<Entry<G>>, ProtocolError> {
+        bincode::deserialize(bytes).map_err(|_| ProtocolError::BulletinBoardChainError(0))
+    }
+    /// Recomputes the chain of hashes, returning the head of the log.
+    /// Fails with the index of the first entry which does not chain to the one before it.
+    ///
+    /// Entries removed from the end of the log are only detected by comparing the returned head
+    /// with the published one.
+    pub fn verify(entries: &[Entry<G>]) -> Result<Vec<u8>, ProtocolError> {
+        let mut head = vec![0; SHA256_OUTPUT_LEN];
+        for (i, entry) in entries.iter().enumerate() {
+            if entry.previous != head {
+                return Err(ProtocolError::BulletinBoardChainError(i));
+            }
+            head = entry.hash();
+        }
+        Ok(head)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type BulletinBoard = super::BulletinBoard<Ristretto>;
+
+    fn sample_board() -> BulletinBoard {
+        let mut board = BulletinBoard::new();
+        for i in 0..4 {
+            board.append(Event::Result(vec![vec![i]]));
+        }
+        board
+    }
+
+    #[test]
+    fn test_chain_detects_tampering() {
+        let board = sample_board();
+        let entries = BulletinBoard::import(&board.export()).unwrap();
+        assert_eq!(BulletinBoard::verify(&entries).unwrap(), board.head());
+
+        let mut removed = entries.clone();
+        removed.remove(1);
+        let check = BulletinBoard::verify(&removed);
+        assert_eq!(check, Err(ProtocolError::BulletinBoardChainError(1)));
+
+        let mut reordered = entries.clone();
+        reordered.swap(2, 3);
+        let check = BulletinBoard::verify(&reordered);
+        assert_eq!(check, Err(ProtocolError::BulletinBoardChainError(2)));
+
+        let mut modified = entries.clone();
+        modified[0].event = Event::Result(vec![vec![5]]);
+        let check = BulletinBoard::verify(&modified);
+        assert_eq!(check, Err(ProtocolError::BulletinBoardChainError(1)));
+
+        // Truncating the log keeps a valid chain, but changes its head.
+        let head = BulletinBoard::verify(&entries[..3]).unwrap();
+        assert_ne!(head, board.head());
+    }
+}
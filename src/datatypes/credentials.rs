@@ -2,30 +2,37 @@
 //!
 //! A credential is (roughly) an El Gamal keypair used by a voter (section 4.7)
 //! This keypair is generated by applying PBKDF2 to some "secret", represented as a Base58 value.
-
+//!
 //! Belenios uses base58 in two places
 //!   * defining UUIDs for each election, and
 //!   * defining "credentials", which are later used to generate El Gamal keypairs.
+//!
 //! This document defines both of these structs, and generally handles parsing base58.
 use crate::datatypes::base58::{Base58, BASE58_STRLEN, INV_LOOKUPTABLE, LOOKUPTABLE};
 use crate::datatypes::voter_ids::Voter_ID;
-use crate::primitives::group::{Point, Scalar};
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::rng::SecureRandom;
+use crate::ProtocolError;
 use ring::digest;
 use ring::pbkdf2::{self, PBKDF2_HMAC_SHA256};
-use ring::rand::SecureRandom;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::num::NonZeroU32;
+use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
 /// A (public) Base58 string, which should uniquely identify the election that is occuring.
 /// UUIDs need not have a valid checksum.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct UUID(Base58);
 
 impl UUID {
     pub fn gen(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
         UUID(Base58::gen(rng))
     }
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        (&self.0).into()
+    }
 }
 
 /// A (secret) Base58 string.
@@ -76,6 +83,28 @@ impl Password {
     }
 }
 
+/// Parses a password, e.g. one handed out by the credential authority.
+impl FromStr for Password {
+    type Err = ProtocolError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let well_formed = s.len() == BASE58_STRLEN
+            && s.bytes()
+                .all(|c| INV_LOOKUPTABLE.get(c as usize).is_some_and(|i| *i != 255));
+        let pass = Password(Base58(s.to_string()));
+        if well_formed && pass.validate_checksum() {
+            Ok(pass)
+        } else {
+            Err(ProtocolError::MalformedPasswordError)
+        }
+    }
+}
+
+impl fmt::Display for Password {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Credential {
     password: Password,
@@ -102,20 +131,21 @@ impl From<(Password, UUID)> for Credential {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ExpandedCredential {
+#[serde(bound = "")]
+pub struct ExpandedCredential<G: Group = Ristretto> {
     pub(crate) password: Password,
     pub(crate) uuid: UUID,
-    pub(crate) secret_key: Scalar,
-    pub(crate) public_key: Point,
+    pub(crate) secret_key: Scalar<G>,
+    pub(crate) public_key: Point<G>,
 }
 
-impl ExpandedCredential {
+impl<G: Group> ExpandedCredential<G> {
     pub fn gen(rng: Arc<Mutex<dyn SecureRandom>>, uuid: &UUID) -> Self {
         Credential::gen(rng, uuid).into()
     }
 }
 
-impl From<Credential> for ExpandedCredential {
+impl<G: Group> From<Credential> for ExpandedCredential<G> {
     fn from(c: Credential) -> Self {
         // I do not believe the hash used in PBKDF2 needs to be domain-separated,
         // it seems like only really the hashes in the ZKPs need to be.
@@ -137,8 +167,8 @@ impl From<Credential> for ExpandedCredential {
     }
 }
 
-impl From<ExpandedCredential> for Credential {
-    fn from(expanded: ExpandedCredential) -> Self {
+impl<G: Group> From<ExpandedCredential<G>> for Credential {
+    fn from(expanded: ExpandedCredential<G>) -> Self {
         Credential {
             uuid: expanded.uuid,
             password: expanded.password,
@@ -161,4 +191,19 @@ mod tests {
             assert!(cred.password.validate_checksum())
         }
     }
+
+    #[test]
+    fn test_password_parsing() {
+        let rng = Arc::new(Mutex::new(ring::rand::SystemRandom::new()));
+        let pass = Password::gen(rng);
+        let s = pass.to_string();
+        assert_eq!(s.parse::<Password>().unwrap().0, pass.0);
+        // A typo is caught by the checksum, as are characters outside of the alphabet.
+        let next = LOOKUPTABLE[(INV_LOOKUPTABLE[s.as_bytes()[0] as usize] as usize + 1) % 58];
+        let typo = format!("{}{}", next as char, &s[1..]);
+        assert!(typo.parse::<Password>().is_err());
+        assert!(format!("0{}", &s[1..]).parse::<Password>().is_err());
+        assert!(s[1..].parse::<Password>().is_err());
+        assert!(format!("é{}", &s[2..]).parse::<Password>().is_err());
+    }
 }
@@ -1,30 +1,82 @@
 //! The Election datatype, defined in section 4.9
 
 use crate::datatypes::credentials::UUID;
+#[cfg(feature = "spec-json")]
+use crate::datatypes::json::{decode_base64, SpecJson};
 use crate::datatypes::questions::Question;
-use crate::primitives::group::{Point, Scalar};
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
 
 use ring::digest::{digest, SHA256};
-use serde::{Deserialize, Serialize};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Builder, Clone, Serialize, Deserialize)]
-pub struct Election {
-    version: usize,
-    description: String,
-    name: String,
-    group: String,
-    pub(crate) public_key: Point,
+#[serde(bound = "")]
+pub struct Election<G: Group = Ristretto> {
+    pub(crate) version: usize,
+    pub(crate) description: String,
+    pub(crate) name: String,
+    // The name of the group G, which is not set by the builder.
+    // Elections of other groups fail to decode.
+    #[builder(setter(skip), default = "G::NAME.to_string()")]
+    #[serde(deserialize_with = "deserialize_group::<G, _>")]
+    pub(crate) group: String,
+    pub(crate) public_key: Point<G>,
     pub(crate) questions: Vec<Question>,
     pub(crate) uuid: UUID,
-    administrator: String,
-    credential_authority: String,
+    pub(crate) administrator: String,
+    pub(crate) credential_authority: String,
+    // Which serialization the fingerprint is computed from, see `Election::fingerprint`.
+    #[builder(default)]
+    #[serde(default)]
+    pub(crate) fingerprint_mode: FingerprintMode,
 }
 
-impl Election {
-    // Won't bother doing the base8 -> base64 conversion
+/// The serialization of an election that its fingerprint, and so every ballot, is bound to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FingerprintMode {
+    /// The bincode serialization of the election.
+    #[default]
+    Bincode,
+    /// The canonical JSON serialization of the specification, see `SpecJson::spec_fingerprint`.
+    #[cfg(feature = "spec-json")]
+    Spec,
+}
+
+fn deserialize_group<'de, G: Group, D: Deserializer<'de>>(d: D) -> Result<String, D::Error> {
+    let group = String::deserialize(d)?;
+    if group != G::NAME {
+        return Err(D::Error::custom(format!("not an election in {}", G::NAME)));
+    }
+    Ok(group)
+}
+
+impl<G: Group> Election<G> {
+    /// The SHA256 hash of the election which ballots are bound to, of the serialization picked by
+    /// its `FingerprintMode`.
+    /// Won't bother doing the base8 -> base64 conversion in the `Bincode` mode.
     pub fn fingerprint(&self) -> Vec<u8> {
-        let data = bincode::serialize(self).unwrap();
-        digest(&SHA256, &data).as_ref().into()
+        match self.fingerprint_mode {
+            FingerprintMode::Bincode => {
+                // The mode itself is left out, so that the default mode keeps the hash of the
+                // election without it (a struct and the tuple of its fields encode the same).
+                let fields = (
+                    &self.version,
+                    &self.description,
+                    &self.name,
+                    &self.group,
+                    &self.public_key,
+                    &self.questions,
+                    &self.uuid,
+                    &self.administrator,
+                    &self.credential_authority,
+                );
+                let data = bincode::serialize(&fields).unwrap();
+                digest(&SHA256, &data).as_ref().into()
+            }
+            #[cfg(feature = "spec-json")]
+            FingerprintMode::Spec => decode_base64(&self.spec_fingerprint()).unwrap(),
+        }
     }
 }
 
@@ -32,7 +84,11 @@ impl Election {
 pub(crate) mod tests {
     use super::*;
     use crate::datatypes::credentials::UUID;
-    use ring::rand::{SecureRandom, SystemRandom};
+    #[cfg(feature = "spec-json")]
+    use crate::datatypes::json::encode_base64;
+    use crate::primitives::ff_group::Belenios2048;
+    use crate::primitives::rng::SecureRandom;
+    use ring::rand::SystemRandom;
     use std::sync::{Arc, Mutex};
 
     pub(crate) fn build_election() -> Election {
@@ -44,7 +100,6 @@ pub(crate) mod tests {
             .version(1)
             .description("Sample".to_string())
             .name("Sample".to_string())
-            .group("RISTRETTO".to_string())
             .public_key(pt)
             .questions(vec![question])
             .uuid(uuid)
@@ -54,4 +109,38 @@ pub(crate) mod tests {
             .unwrap();
         election
     }
+
+    #[test]
+    fn test_group_mismatch() {
+        let election = build_election();
+        let bytes = bincode::serialize(&election).unwrap();
+        let json = serde_json::to_string(&election).unwrap();
+        assert!(bincode::deserialize::<Election>(&bytes).is_ok());
+        assert!(bincode::deserialize::<Election<Belenios2048>>(&bytes).is_err());
+        assert!(serde_json::from_str::<Election<Belenios2048>>(&json).is_err());
+        // Even if the rest of the election would decode.
+        let renamed = json.replace(Ristretto::NAME, "BELENIOS-2048");
+        assert!(serde_json::from_str::<Election>(&renamed).is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_mode() {
+        let election = build_election();
+        assert_eq!(election.fingerprint_mode, FingerprintMode::Bincode);
+        let data = bincode::serialize(&election).unwrap();
+        // Everything but the trailing mode.
+        let data =
+            &data[..data.len() - bincode::serialize(&FingerprintMode::Bincode).unwrap().len()];
+        assert_eq!(election.fingerprint(), digest(&SHA256, data).as_ref());
+        #[cfg(feature = "spec-json")]
+        {
+            let mut spec = election.clone();
+            spec.fingerprint_mode = FingerprintMode::Spec;
+            assert_eq!(encode_base64(&spec.fingerprint()), spec.spec_fingerprint());
+            assert_ne!(spec.fingerprint(), election.fingerprint());
+            let decoded: Election =
+                SpecJson::from_spec_json(&election.to_canonical_json()).unwrap();
+            assert_eq!(decoded.fingerprint_mode, FingerprintMode::Spec);
+        }
+    }
 }
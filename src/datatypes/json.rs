@@ -0,0 +1,663 @@
+//! A JSON codec following the layouts of section 4 of the specification, for interoperating with
+//! other Belenios tooling (e.g. auditors' scripts).
+//!
+//! The bincode encodings of messages are unaffected.
+//! Following the specification,
+//!   * elements of BELENIOS-2048 are decimal strings, and elements of other groups (e.g.
+//!     Ristretto) hex strings of their encoding,
+//!   * scalars are decimal strings, and
+//!   * hashes are base64 strings without padding.
+//!
+//! The canonical serialization is the compact JSON with fields in the order of the
+//! specification, and is what fingerprints are computed from, e.g. `Election::fingerprint`, which
+//! ballots are bound to.
+
+use crate::datatypes::ballot::{Answer, Ballot};
+use crate::datatypes::credentials::UUID;
+use crate::datatypes::election::{Election, FingerprintMode};
+use crate::datatypes::questions::Question;
+use crate::participants::messages::{T3Mi, T7M};
+use crate::participants::trustee::TrusteePublicKey;
+use crate::primitives::ff_group;
+use crate::primitives::group::{Group, Point, Scalar};
+use crate::primitives::pki::{Ciphertext, VerificationKey};
+use crate::primitives::zkp::Proof;
+use crate::ProtocolError;
+use ring::digest::{digest, SHA256};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Types with a JSON representation in the specification.
+pub trait SpecJson: Sized {
+    /// The layout of the specification.
+    type Json: Serialize + DeserializeOwned;
+    fn to_json(&self) -> Self::Json;
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError>;
+
+    /// The canonical serialization, e.g. compact and with fields in the order of the
+    /// specification.
+    fn to_canonical_json(&self) -> String {
+        serde_json::to_string(&self.to_json()).unwrap()
+    }
+    fn to_pretty_json(&self) -> String {
+        serde_json::to_string_pretty(&self.to_json()).unwrap()
+    }
+    /// Parses either of the above serializations.
+    fn from_spec_json(s: &str) -> Result<Self, ProtocolError> {
+        let json = serde_json::from_str(s).map_err(|_| ProtocolError::MalformedJsonError)?;
+        Self::from_json(json)
+    }
+    /// The base64 encoded SHA256 hash of the canonical serialization.
+    fn spec_fingerprint(&self) -> String {
+        let hash = digest(&SHA256, self.to_canonical_json().as_bytes());
+        encode_base64(hash.as_ref())
+    }
+}
+
+pub(crate) fn encode_base64(data: &[u8]) -> String {
+    base64::encode_config(data, base64::STANDARD_NO_PAD)
+}
+
+pub(crate) fn decode_base64(s: &str) -> Result<Vec<u8>, ProtocolError> {
+    base64::decode_config(s, base64::STANDARD_NO_PAD).map_err(|_| ProtocolError::MalformedJsonError)
+}
+
+// The specification encodes elements of finite field groups as (decimal) integers.
+fn decimal_elements<G: Group>() -> bool {
+    G::NAME == ff_group::GROUP_NAME
+}
+
+fn encode_point<G: Group>(pt: &Point<G>) -> String {
+    if decimal_elements::<G>() {
+        return encode_decimal(pt.as_bytes());
+    }
+    pt.as_bytes().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn decode_point<G: Group>(s: &str) -> Result<Point<G>, ProtocolError> {
+    let bytes = if decimal_elements::<G>() {
+        decode_decimal(s, Point::<G>::generator().as_bytes().len())?
+    } else {
+        if !s.len().is_multiple_of(2) || !s.is_ascii() {
+            return Err(ProtocolError::MalformedJsonError);
+        }
+        (0..s.len() / 2)
+            .map(|i| u8::from_str_radix(&s[2 * i..2 * i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| ProtocolError::MalformedJsonError)?
+    };
+    Point::from_bytes(&bytes).ok_or(ProtocolError::MalformedJsonError)
+}
+
+/// Converts big-endian bytes to decimal, by repeated division by 10.
+fn encode_decimal(mut bytes: Vec<u8>) -> String {
+    let mut digits = Vec::new();
+    while bytes.iter().any(|b| *b != 0) {
+        let mut rem = 0u32;
+        for b in bytes.iter_mut() {
+            let cur = (rem << 8) | *b as u32;
+            *b = (cur / 10) as u8;
+            rem = cur % 10;
+        }
+        digits.push(b'0' + rem as u8);
+    }
+    if digits.is_empty() {
+        digits.push(b'0');
+    }
+    digits.reverse();
+    String::from_utf8(digits).unwrap()
+}
+
+/// The inverse of `encode_decimal`, into `len` big-endian bytes.
+/// Fails for integers which do not fit, and for non-canonical decimal strings.
+fn decode_decimal(s: &str, len: usize) -> Result<Vec<u8>, ProtocolError> {
+    let well_formed =
+        !s.is_empty() && s.bytes().all(|d| d.is_ascii_digit()) && (s == "0" || !s.starts_with('0'));
+    if !well_formed {
+        return Err(ProtocolError::MalformedJsonError);
+    }
+    let mut bytes = vec![0u8; len];
+    for d in s.bytes() {
+        let mut carry = (d - b'0') as u32;
+        for b in bytes.iter_mut().rev() {
+            let cur = (*b as u32) * 10 + carry;
+            *b = cur as u8;
+            carry = cur >> 8;
+        }
+        if carry != 0 {
+            return Err(ProtocolError::MalformedJsonError);
+        }
+    }
+    Ok(bytes)
+}
+
+// Scalars are encoded little-endian.
+fn encode_scalar<G: Group>(x: &Scalar<G>) -> String {
+    encode_decimal(x.as_bytes().into_iter().rev().collect())
+}
+
+fn decode_scalar<G: Group>(s: &str) -> Result<Scalar<G>, ProtocolError> {
+    let mut bytes = decode_decimal(s, 32)?;
+    bytes.reverse();
+    Scalar::from_canonical_bytes(&bytes).ok_or(ProtocolError::MalformedJsonError)
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct QuestionJson {
+    answers: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    blank: bool,
+    min: u128,
+    max: u128,
+    question: String,
+}
+
+impl SpecJson for Question {
+    type Json = QuestionJson;
+    fn to_json(&self) -> Self::Json {
+        QuestionJson {
+            answers: self.answers.clone(),
+            blank: self.blank,
+            min: self.min,
+            max: self.max,
+            question: self.question.clone(),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        if json.min > json.max || json.max > json.answers.len() as u128 {
+            return Err(ProtocolError::MalformedJsonError);
+        }
+        Ok(Question {
+            question: json.question,
+            answers: json.answers,
+            blank: json.blank,
+            min: json.min,
+            max: json.max,
+        })
+    }
+}
+
+/// The layout of `election.json`, see section 4.9.
+#[derive(Serialize, Deserialize)]
+pub struct ElectionJson {
+    version: usize,
+    description: String,
+    name: String,
+    group: String,
+    public_key: String,
+    questions: Vec<QuestionJson>,
+    uuid: UUID,
+    administrator: String,
+    credential_authority: String,
+}
+
+impl<G: Group> SpecJson for Election<G> {
+    type Json = ElectionJson;
+    fn to_json(&self) -> Self::Json {
+        ElectionJson {
+            version: self.version,
+            description: self.description.clone(),
+            name: self.name.clone(),
+            group: self.group.clone(),
+            public_key: encode_point(&self.public_key),
+            questions: self.questions.iter().map(|q| q.to_json()).collect(),
+            uuid: self.uuid.clone(),
+            administrator: self.administrator.clone(),
+            credential_authority: self.credential_authority.clone(),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        let questions = json
+            .questions
+            .into_iter()
+            .map(Question::from_json)
+            .collect::<Result<_, _>>()?;
+        // Elections in other groups are not elections in G.
+        if json.group != G::NAME {
+            return Err(ProtocolError::MalformedJsonError);
+        }
+        Ok(Election {
+            version: json.version,
+            description: json.description,
+            name: json.name,
+            group: json.group,
+            public_key: decode_point(&json.public_key)?,
+            questions,
+            uuid: json.uuid,
+            administrator: json.administrator,
+            credential_authority: json.credential_authority,
+            // Elections exchanged in the layout of the specification are bound to its fingerprint.
+            fingerprint_mode: FingerprintMode::Spec,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProofJson {
+    challenge: String,
+    response: String,
+}
+
+impl<G: Group> SpecJson for Proof<G> {
+    type Json = ProofJson;
+    fn to_json(&self) -> Self::Json {
+        ProofJson {
+            challenge: encode_scalar(&self.challenge),
+            response: encode_scalar(&self.response),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        Ok((
+            decode_scalar(&json.challenge)?,
+            decode_scalar(&json.response)?,
+        )
+            .into())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct CiphertextJson {
+    alpha: String,
+    beta: String,
+}
+
+impl<G: Group> SpecJson for Ciphertext<G> {
+    type Json = CiphertextJson;
+    fn to_json(&self) -> Self::Json {
+        CiphertextJson {
+            alpha: encode_point(&self.alpha),
+            beta: encode_point(&self.beta),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        Ok((decode_point(&json.alpha)?, decode_point(&json.beta)?).into())
+    }
+}
+
+fn proofs_to_json<G: Group>(proofs: &[Proof<G>]) -> Vec<ProofJson> {
+    proofs.iter().map(|pf| pf.to_json()).collect()
+}
+
+fn proofs_from_json<G: Group>(proofs: Vec<ProofJson>) -> Result<Vec<Proof<G>>, ProtocolError> {
+    proofs.into_iter().map(Proof::from_json).collect()
+}
+
+/// The layout of an answer to a question, see section 4.11.
+#[derive(Serialize, Deserialize)]
+pub struct AnswerJson {
+    choices: Vec<CiphertextJson>,
+    individual_proofs: Vec<Vec<ProofJson>>,
+    overall_proof: Vec<ProofJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    blank_proof: Option<Vec<ProofJson>>,
+}
+
+impl<G: Group> SpecJson for Answer<G> {
+    type Json = AnswerJson;
+    fn to_json(&self) -> Self::Json {
+        AnswerJson {
+            choices: self.choices.iter().map(|c| c.to_json()).collect(),
+            individual_proofs: self
+                .individual_proofs
+                .iter()
+                .map(|pfs| proofs_to_json(pfs))
+                .collect(),
+            overall_proof: proofs_to_json(&self.overall_proof),
+            blank_proof: self.blank_proof.as_ref().map(|pfs| proofs_to_json(pfs)),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        let choices = json
+            .choices
+            .into_iter()
+            .map(Ciphertext::from_json)
+            .collect::<Result<_, _>>()?;
+        let individual_proofs = json
+            .individual_proofs
+            .into_iter()
+            .map(proofs_from_json)
+            .collect::<Result<_, _>>()?;
+        let blank_proof = match json.blank_proof {
+            Some(pfs) => Some(proofs_from_json(pfs)?),
+            None => None,
+        };
+        Ok(Answer {
+            choices,
+            individual_proofs,
+            overall_proof: proofs_from_json(json.overall_proof)?,
+            blank_proof,
+            commitments: Vec::new(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SignatureJson {
+    hash: String,
+    proof: ProofJson,
+}
+
+/// The layout of a ballot.
+#[derive(Serialize, Deserialize)]
+pub struct BallotJson {
+    election_uuid: UUID,
+    election_hash: String,
+    credential: String,
+    answers: Vec<AnswerJson>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    signature: Option<SignatureJson>,
+}
+
+impl<G: Group> SpecJson for Ballot<G> {
+    type Json = BallotJson;
+    fn to_json(&self) -> Self::Json {
+        let signature = self.signature.as_ref().map(|pf| SignatureJson {
+            hash: encode_base64(&self.hash()),
+            proof: pf.to_json(),
+        });
+        BallotJson {
+            election_uuid: self.election_uuid.clone(),
+            election_hash: encode_base64(&self.election_hash),
+            credential: encode_point(&self.credential),
+            answers: self.answers.iter().map(|a| a.to_json()).collect(),
+            signature,
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        let answers = json
+            .answers
+            .into_iter()
+            .map(Answer::from_json)
+            .collect::<Result<_, _>>()?;
+        let mut ballot = Ballot {
+            election_uuid: json.election_uuid,
+            election_hash: decode_base64(&json.election_hash)?,
+            credential: decode_point(&json.credential)?,
+            answers,
+            signature: None,
+        };
+        if let Some(signature) = json.signature {
+            // The signed hash must be the hash of the ballot it comes with.
+            if decode_base64(&signature.hash)? != ballot.hash() {
+                return Err(ProtocolError::MalformedJsonError);
+            }
+            ballot.signature = Some(Proof::from_json(signature.proof)?);
+        }
+        Ok(ballot)
+    }
+}
+
+/// The layout of a trustee's public key.
+#[derive(Serialize, Deserialize)]
+pub struct TrusteePublicKeyJson {
+    pok: ProofJson,
+    public_key: String,
+}
+
+impl<G: Group> SpecJson for TrusteePublicKey<G> {
+    type Json = TrusteePublicKeyJson;
+    fn to_json(&self) -> Self::Json {
+        TrusteePublicKeyJson {
+            pok: self.proof.to_json(),
+            public_key: encode_point(&self.public_key.0),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        Ok(TrusteePublicKey {
+            public_key: VerificationKey(decode_point(&json.public_key)?),
+            proof: Proof::from_json(json.pok)?,
+        })
+    }
+}
+
+/// The layout of a partial decryption.
+#[derive(Serialize, Deserialize)]
+pub struct PartialDecryptionJson {
+    decryption_factors: Vec<Vec<String>>,
+    decryption_proofs: Vec<Vec<ProofJson>>,
+}
+
+impl<G: Group> SpecJson for T3Mi<G> {
+    type Json = PartialDecryptionJson;
+    fn to_json(&self) -> Self::Json {
+        PartialDecryptionJson {
+            decryption_factors: self
+                .decryption_factors
+                .iter()
+                .map(|factors| factors.iter().map(encode_point).collect())
+                .collect(),
+            decryption_proofs: self
+                .decryption_proofs
+                .iter()
+                .map(|pfs| proofs_to_json(pfs))
+                .collect(),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        let decryption_factors = json
+            .decryption_factors
+            .iter()
+            .map(|factors| factors.iter().map(|s| decode_point(s)).collect())
+            .collect::<Result<_, _>>()?;
+        let decryption_proofs = json
+            .decryption_proofs
+            .into_iter()
+            .map(proofs_from_json)
+            .collect::<Result<_, _>>()?;
+        Ok(T3Mi {
+            decryption_factors,
+            decryption_proofs,
+        })
+    }
+}
+
+/// The layout of the result of the election, see section 4.15.
+/// The partial decryption of a "pedersen" trustee that did not take part in the decryption is
+/// `null`. The specification does not cover failed tallies, which we record in `error`.
+#[derive(Serialize, Deserialize)]
+pub struct ResultJson {
+    num_tallied: usize,
+    encrypted_tally: Vec<Vec<CiphertextJson>>,
+    partial_decryptions: Vec<Option<PartialDecryptionJson>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    result: Option<Vec<Vec<u128>>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<ProtocolError>,
+}
+
+impl<G: Group> SpecJson for T7M<G> {
+    type Json = ResultJson;
+    fn to_json(&self) -> Self::Json {
+        ResultJson {
+            num_tallied: self.num_tallied,
+            encrypted_tally: self
+                .encrypted_tally
+                .iter()
+                .map(|question| question.iter().map(|c| c.to_json()).collect())
+                .collect(),
+            partial_decryptions: self
+                .partial_decryptions
+                .iter()
+                .map(|pd| pd.as_ref().map(|pd| pd.to_json()))
+                .collect(),
+            result: self.result.as_ref().ok().cloned(),
+            error: self.result.as_ref().err().cloned(),
+        }
+    }
+    fn from_json(json: Self::Json) -> Result<Self, ProtocolError> {
+        let result = match (json.result, json.error) {
+            (Some(result), None) => Ok(result),
+            (None, Some(error)) => Err(error),
+            _ => return Err(ProtocolError::MalformedJsonError),
+        };
+        let encrypted_tally = json
+            .encrypted_tally
+            .into_iter()
+            .map(|question| question.into_iter().map(Ciphertext::from_json).collect())
+            .collect::<Result<_, _>>()?;
+        let partial_decryptions = json
+            .partial_decryptions
+            .into_iter()
+            .map(|pd| pd.map(T3Mi::from_json).transpose())
+            .collect::<Result<_, _>>()?;
+        Ok(T7M {
+            num_tallied: json.num_tallied,
+            encrypted_tally,
+            partial_decryptions,
+            result,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::participants::messages::{E8MiBuilder, E9Mi, EmptyMessage, V2Mi, T3M};
+    use crate::participants::participant_template::Participant;
+    use crate::participants::trustee::Trustee;
+    use crate::participants::voting_server::tests::{setup_election, vote};
+    use crate::primitives::ff_group::Belenios2048;
+    use crate::primitives::group::Ristretto;
+    use ring::rand::SystemRandom;
+    use std::sync::{Arc, Mutex};
+
+    type Scalar = super::Scalar<Ristretto>;
+    type Election = super::Election<Ristretto>;
+    type Ballot = super::Ballot<Ristretto>;
+    type TrusteePublicKey = super::TrusteePublicKey<Ristretto>;
+
+    #[test]
+    fn test_scalar_encoding() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        assert_eq!(encode_scalar(&Scalar::zero()), "0");
+        assert_eq!(
+            encode_scalar(&Scalar::from(1234567890123456789_u128)),
+            "1234567890123456789"
+        );
+        // The group order minus one, e.g. the largest scalar.
+        let largest =
+            "7237005577332262213973186563042994240857116359379907606001950938285454250988";
+        assert_eq!(encode_scalar(&-Scalar::one()), largest);
+        assert_eq!(decode_scalar(largest).unwrap(), -Scalar::one());
+        for _ in 0..10 {
+            let x = Scalar::sample_uniform(rng.clone());
+            assert_eq!(decode_scalar(&encode_scalar(&x)).unwrap(), x);
+        }
+        // Non-canonical or non-decimal encodings are rejected.
+        let order = "7237005577332262213973186563042994240857116359379907606001950938285454250989";
+        for s in ["", "01", "-1", "1.0", "0x10", order] {
+            assert!(decode_scalar::<Ristretto>(s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_element_encoding() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        // The generator of BELENIOS-2048, as published in the specification.
+        let g = "2402352677501852209227687703532399932712287657378364916510075318787663274146353219320285676155269678799694668298749389095083896573425601900601068477164491735474137283104610458681314511781646755400527402889846139864532661215055797097162016168270312886432456663834863635782106154918419982534315189740658186868651151358576410138882215396016043228843603930989333662772848406593138406010231675095763777982665103606822406635076697764025346253773085133173495194248967754052573659049492477631475991575198775177711481490920456600205478127054728238140972518639858334115700568353695553423781475582491896050296680037745308460627";
+        assert_eq!(encode_point(&Point::<Belenios2048>::generator()), g);
+        assert_eq!(decode_point::<Belenios2048>(g).unwrap(), Point::generator());
+        assert_eq!(encode_point(&Point::<Belenios2048>::identity()), "1");
+        let x = Point::<Belenios2048>::sample_uniform(rng.clone());
+        assert_eq!(decode_point::<Belenios2048>(&encode_point(&x)).unwrap(), x);
+        // Ristretto elements stay hex strings of their 32 byte encoding.
+        let y = Point::<Ristretto>::sample_uniform(rng);
+        assert_eq!(encode_point(&y).len(), 64);
+        assert_eq!(decode_point::<Ristretto>(&encode_point(&y)).unwrap(), y);
+        // Hex strings, non-canonical decimals and integers outside the subgroup are rejected.
+        let hex: String = Point::<Belenios2048>::generator()
+            .as_bytes()
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        for s in [hex, format!("0{}", g), "0".to_string(), "2".to_string()] {
+            assert!(decode_point::<Belenios2048>(&s).is_err());
+        }
+    }
+
+    #[test]
+    fn test_election_json() {
+        let election = crate::datatypes::election::tests::build_election();
+        let json = election.to_canonical_json();
+        assert!(json.starts_with(r#"{"version":1,"description":"Sample","name":"Sample""#));
+        assert!(json.contains(
+            r#""answers":["Mark Fischlin","Nadia Heninger","Anna Lysyanskaya"],"min":0,"max":1"#
+        ));
+        let decoded = Election::from_spec_json(&election.to_pretty_json()).unwrap();
+        assert_eq!(decoded.to_canonical_json(), json);
+        assert_eq!(decoded.spec_fingerprint(), election.spec_fingerprint());
+        // Fingerprints are unpadded base64 SHA256 hashes.
+        assert_eq!(election.spec_fingerprint().len(), 43);
+        let tampered = json.replace(r#""max":1"#, r#""max":2"#);
+        let tampered = Election::from_spec_json(&tampered).unwrap();
+        assert_ne!(tampered.spec_fingerprint(), election.spec_fingerprint());
+        assert!(Election::from_spec_json(&json.replace(r#""max":1"#, r#""max":4"#)).is_err());
+    }
+
+    #[test]
+    fn test_ballot_and_result_json() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = E8MiBuilder::default()
+            .uuid(UUID::gen(rng.clone()))
+            .index(0)
+            .build()
+            .unwrap();
+        let (_, trustee_key) = Trustee::new(rng).process_message(context);
+        let trustee_key: E9Mi = trustee_key;
+        let json = trustee_key.trustee_key.to_canonical_json();
+        assert!(json.starts_with(r#"{"pok":{"challenge":""#));
+        let decoded = TrusteePublicKey::from_spec_json(&json).unwrap();
+        assert_eq!(decoded.to_canonical_json(), json);
+
+        let (voting_server, mut voters, _) = setup_election(vec![1], 1);
+        let (_, ballot) = voters.remove(0).process_message(V2Mi {
+            choices: vec![vec![false, true, false]],
+        });
+        let json = ballot.vote.to_canonical_json();
+        // Ballots are bound to the fingerprint of their election, of its `FingerprintMode`.
+        let election_hash = encode_base64(&voting_server.election().fingerprint());
+        assert!(json.contains(&format!(r#""election_hash":"{}""#, election_hash)));
+        let decoded = Ballot::from_spec_json(&json).unwrap();
+        assert!(decoded.verify_signature());
+        assert_eq!(decoded.to_canonical_json(), json);
+        // The signature covers the answers.
+        let mut other = ballot.vote.clone();
+        other.answers[0].choices.swap(0, 1);
+        let forged = json.replace(
+            &encode_base64(&ballot.vote.hash()),
+            &encode_base64(&other.hash()),
+        );
+        assert!(Ballot::from_spec_json(&forged).is_err());
+        assert!(Ballot::from_spec_json(&json.replace("election_hash", "hash")).is_err());
+
+        let (voting_server, voters, trustees) = setup_election(vec![1, 1], 1);
+        let choices = vec![vec![false, true, false], vec![true, false, false]];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        let partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        let (voting_server, _) = voting_server.process_message(T3M::from(partial_decryptions));
+        let (_, result) = voting_server.process_message(EmptyMessage);
+        let json = result.to_canonical_json();
+        assert!(json.starts_with(r#"{"num_tallied":2,"encrypted_tally":[[{"alpha":""#));
+        assert!(json.contains(r#""partial_decryptions":[{"decryption_factors":[[""#));
+        assert!(json.ends_with(r#""result":[[1,1,0]]}"#));
+        let decoded = T7M::<Ristretto>::from_spec_json(&json).unwrap();
+        assert_eq!(decoded.to_canonical_json(), json);
+
+        let failed = T7M::<Ristretto> {
+            num_tallied: 3,
+            encrypted_tally: result.encrypted_tally,
+            partial_decryptions: vec![None],
+            result: Err(ProtocolError::NotEnoughPartialDecryptionsError),
+        };
+        let json = failed.to_canonical_json();
+        assert!(json.contains(r#""partial_decryptions":[null]"#));
+        let decoded = T7M::<Ristretto>::from_spec_json(&json).unwrap();
+        assert_eq!(
+            decoded.result,
+            Err(ProtocolError::NotEnoughPartialDecryptionsError)
+        );
+    }
+}
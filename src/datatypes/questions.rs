@@ -44,8 +44,8 @@ pub(crate) mod tests {
         let quest = "Who should be IACR director in 2021?";
         let ans = vec!["Mark Fischlin", "Nadia Heninger", "Anna Lysyanskaya"];
         let question = QuestionBuilder::default()
-            .question(quest.clone())
-            .answers(ans.clone())
+            .question(quest)
+            .answers(ans)
             .build()
             .unwrap();
         question
@@ -59,7 +59,7 @@ pub(crate) mod tests {
         let question = build_question();
         assert_eq!(question.question, quest);
         assert_eq!(question.answers, ans);
-        assert_eq!(question.blank, false);
+        assert!(!question.blank);
         assert_eq!(question.min, 0);
         assert_eq!(question.max, 1);
     }
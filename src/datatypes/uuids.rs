@@ -1,7 +1,7 @@
 //! UUIDs
 
 use bs58::Alphabet;
-use ring::rand::SecureRandom;
+use crate::primitives::rng::SecureRandom;
 use std::sync::{Arc, Mutex};
 
 /// Base58 alphabet used for encoding/decoding, taken from [page 7 of the Belenios paper](https://www.belenios.org/specification.pdf#page=7).
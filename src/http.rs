@@ -0,0 +1,517 @@
+//! An HTTP service for the voting server, and a client for it.
+//!
+//! The service runs the voting server from the voting phase onwards, with the following
+//! endpoints, whose bodies are the JSON encodings of the messages of the protocol:
+//! * `GET /election`: the election and the public list L (`E11M`),
+//! * `POST /ballots`: submits a ballot (`V3Mi`),
+//! * `GET /ballots`: the accepted ballots,
+//! * `POST /close`: closes the election, and returns the encrypted tally (`T2M`),
+//! * `GET /encrypted-tally`: the encrypted tally, once the election is closed,
+//! * `POST /partial-decryptions/{i}`: the partial decryption of the i-th trustee (`T3Mi`), which
+//!   is checked on submission and cannot be replaced once accepted,
+//! * `POST /tally`: combines the partial decryptions received so far, and returns the result
+//!   (`T7M`),
+//! * `GET /result`: the result, once tallied,
+//! * `GET /bulletin-board`: the bulletin board, as given by `BulletinBoard::export`.
+//!
+//! Closing the election and tallying it are restricted to the server administrator, who passes
+//! the key the service was started with as a bearer token.
+//! A request which breaks the protocol, e.g. an invalid ballot, fails with status 422 and the
+//! `ProtocolError` as its body.
+
+use std::error::Error;
+use std::fmt;
+use std::io::Read;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::primitives::rng::SecureRandom;
+use ring::rand::SystemRandom;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response};
+
+use crate::datatypes::ballot::Ballot;
+use crate::datatypes::bulletin_board::{BulletinBoard, Entry, Event};
+use crate::datatypes::credentials::Password;
+use crate::participants::messages::{EmptyMessage, T3Mi, V3Mi, E11M, T2M, T3M, T7M};
+use crate::participants::participant_template::Participant;
+use crate::participants::snapshot::Snapshot;
+use crate::participants::voting_server::{VotingServer, E11, T2, V4};
+use crate::primitives::group::{Group, Ristretto};
+use crate::ProtocolError;
+
+/// The largest request body the service reads, e.g. a ballot for a large election.
+const MAX_BODY_LEN: u64 = 1 << 24;
+
+enum Phase<G: Group> {
+    Voting(VotingServer<V4<G>, G>),
+    Tallying {
+        server: VotingServer<T2<G>, G>,
+        encrypted_tally: T2M<G>,
+        partial_decryptions: Vec<Option<T3Mi<G>>>,
+    },
+    Tallied {
+        board: BulletinBoard<G>,
+        ballots: Vec<Ballot<G>>,
+        encrypted_tally: T2M<G>,
+        result: T7M<G>,
+    },
+}
+
+type Reply = (u16, Vec<u8>);
+
+fn json<T: Serialize>(status: u16, value: &T) -> Reply {
+    (status, serde_json::to_vec(value).unwrap())
+}
+
+fn text(status: u16, message: &str) -> Reply {
+    (status, message.as_bytes().to_vec())
+}
+
+fn parse<T: DeserializeOwned>(body: &[u8]) -> Result<T, Reply> {
+    serde_json::from_slice(body).map_err(|e| text(400, &e.to_string()))
+}
+
+struct Service<G: Group> {
+    admin_key: Password,
+    election: E11M<G>,
+    // Only empty while moving from one phase to the next.
+    phase: Option<Phase<G>>,
+}
+
+impl<G: Group> Service<G> {
+    fn new(snapshot: &Snapshot<G>, admin_key: Password) -> Result<Self, ProtocolError> {
+        let rng: Arc<Mutex<dyn SecureRandom>> = Arc::new(Mutex::new(SystemRandom::new()));
+        // The states of the voting server are public, so any key resumes them.
+        let server = if snapshot.phase() == "VotingServer/E11" {
+            let server = VotingServer::<E11<G>, G>::resume(rng.clone(), snapshot, &admin_key)?;
+            server.process_message(EmptyMessage).0
+        } else {
+            VotingServer::<V4<G>, G>::resume(rng.clone(), snapshot, &admin_key)?
+        };
+        let election = E11M {
+            election: server.election().clone(),
+            L: server.voters().to_vec(),
+        };
+        Ok(Service {
+            admin_key,
+            election,
+            phase: Some(Phase::Voting(server)),
+        })
+    }
+
+    fn is_admin(&self, request: &Request) -> bool {
+        let expected = format!("Bearer {}", self.admin_key);
+        request
+            .headers()
+            .iter()
+            .filter(|h| h.field.equiv("Authorization"))
+            .any(|h| {
+                ring::constant_time::verify_slices_are_equal(
+                    h.value.as_bytes(),
+                    expected.as_bytes(),
+                )
+                .is_ok()
+            })
+    }
+
+    fn handle(&mut self, request: &mut Request) -> Reply {
+        let mut body = Vec::new();
+        let read = request
+            .as_reader()
+            .take(MAX_BODY_LEN)
+            .read_to_end(&mut body);
+        if read.is_err() {
+            return text(400, "unreadable body");
+        }
+        let path = request
+            .url()
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .to_string();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        let admin = self.is_admin(request);
+        let reply = match (request.method(), segments.as_slice()) {
+            (Method::Get, ["election"]) => Ok(json(200, &self.election)),
+            (Method::Post, ["ballots"]) => parse(&body).map(|ballot| self.submit_ballot(ballot)),
+            (Method::Get, ["ballots"]) => Ok(self.ballots()),
+            (Method::Post, ["close"]) if admin => Ok(self.close()),
+            (Method::Get, ["encrypted-tally"]) => Ok(self.encrypted_tally()),
+            (Method::Post, ["partial-decryptions", i]) => match i.parse() {
+                Ok(i) => parse(&body).map(|pd| self.submit_partial_decryption(i, pd)),
+                Err(_) => Ok(text(404, "no such trustee")),
+            },
+            (Method::Post, ["tally"]) if admin => Ok(self.tally()),
+            (Method::Get, ["result"]) => Ok(self.result()),
+            (Method::Get, ["bulletin-board"]) => Ok(self.bulletin_board()),
+            (Method::Post, ["close"]) | (Method::Post, ["tally"]) => {
+                Ok(text(401, "restricted to the server administrator"))
+            }
+            _ => Ok(text(404, "no such endpoint")),
+        };
+        reply.unwrap_or_else(|reply| reply)
+    }
+
+    fn submit_ballot(&mut self, ballot: V3Mi<G>) -> Reply {
+        match self.phase.take() {
+            Some(Phase::Voting(server)) => {
+                let (server, message) = server.process_message(ballot);
+                self.phase = Some(Phase::Voting(server));
+                match message.check {
+                    Ok(()) => text(200, ""),
+                    Err(e) => json(422, &e),
+                }
+            }
+            phase => {
+                self.phase = phase;
+                text(409, "the election is closed")
+            }
+        }
+    }
+
+    fn ballots(&self) -> Reply {
+        let ballots: Vec<&Ballot<G>> = match self.phase.as_ref().unwrap() {
+            Phase::Voting(server) => server.ballots().collect(),
+            Phase::Tallying { server, .. } => server.ballots().collect(),
+            Phase::Tallied { ballots, .. } => ballots.iter().collect(),
+        };
+        json(200, &ballots)
+    }
+
+    fn close(&mut self) -> Reply {
+        match self.phase.take() {
+            Some(Phase::Voting(server)) => {
+                let (server, encrypted_tally) = server.process_message(EmptyMessage);
+                let num_trustees = server
+                    .bulletin_board()
+                    .entries()
+                    .iter()
+                    .find_map(|entry| match &entry.event {
+                        Event::TrusteeKeys { trustee_keys, .. } => Some(trustee_keys.len()),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                let reply = json(200, &encrypted_tally);
+                self.phase = Some(Phase::Tallying {
+                    server,
+                    encrypted_tally,
+                    partial_decryptions: vec![None; num_trustees],
+                });
+                reply
+            }
+            phase => {
+                self.phase = phase;
+                text(409, "the election is already closed")
+            }
+        }
+    }
+
+    fn encrypted_tally(&self) -> Reply {
+        match self.phase.as_ref().unwrap() {
+            Phase::Voting(_) => text(409, "the election is not closed yet"),
+            Phase::Tallying {
+                encrypted_tally, ..
+            }
+            | Phase::Tallied {
+                encrypted_tally, ..
+            } => json(200, encrypted_tally),
+        }
+    }
+
+    fn submit_partial_decryption(&mut self, i: usize, partial_decryption: T3Mi<G>) -> Reply {
+        match self.phase.as_mut().unwrap() {
+            Phase::Tallying {
+                server,
+                partial_decryptions,
+                ..
+            } => match partial_decryptions.get_mut(i) {
+                // Only valid partial decryptions are kept, which nobody may then replace.
+                Some(Some(_)) => text(409, "the partial decryption was already submitted"),
+                Some(pd) => match server.verify_partial_decryption(i, &partial_decryption) {
+                    Ok(()) => {
+                        *pd = Some(partial_decryption);
+                        text(200, "")
+                    }
+                    Err(e) => json(422, &e),
+                },
+                None => text(404, "no such trustee"),
+            },
+            Phase::Voting(_) => text(409, "the election is not closed yet"),
+            Phase::Tallied { .. } => text(409, "the election is already tallied"),
+        }
+    }
+
+    fn tally(&mut self) -> Reply {
+        match self.phase.take() {
+            Some(Phase::Tallying {
+                server,
+                encrypted_tally,
+                partial_decryptions,
+            }) => {
+                // The partial decryptions were checked on submission, and the result tells
+                // whether there are enough of them.
+                let (server, _) = server.process_message(T3M {
+                    partial_decryptions,
+                });
+                let (server, result) = server.process_message(EmptyMessage);
+                let reply = json(200, &result);
+                self.phase = Some(Phase::Tallied {
+                    board: server.bulletin_board().clone(),
+                    ballots: server.ballots().cloned().collect(),
+                    encrypted_tally,
+                    result,
+                });
+                reply
+            }
+            phase => {
+                self.phase = phase;
+                text(409, "the election is not being tallied")
+            }
+        }
+    }
+
+    fn result(&self) -> Reply {
+        match self.phase.as_ref().unwrap() {
+            Phase::Tallied { result, .. } => json(200, result),
+            _ => text(409, "the election is not tallied yet"),
+        }
+    }
+
+    fn bulletin_board(&self) -> Reply {
+        let board = match self.phase.as_ref().unwrap() {
+            Phase::Voting(server) => server.bulletin_board(),
+            Phase::Tallying { server, .. } => server.bulletin_board(),
+            Phase::Tallied { board, .. } => board,
+        };
+        (200, board.export())
+    }
+}
+
+/// A running HTTP service for a voting server, which stops when dropped.
+pub struct HttpServer {
+    addr: SocketAddr,
+    server: Arc<tiny_http::Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HttpServer {
+    /// Serves the voting server resumed from `snapshot`, taken at step E11 or V4, on `addr`
+    /// (e.g. `"127.0.0.1:0"` for any free port).
+    ///
+    /// As participants are not `Send`, the voting server is resumed on the thread serving the
+    /// requests, which handles them one at a time.
+    pub fn spawn<G: Group>(
+        addr: impl ToSocketAddrs,
+        snapshot: Snapshot<G>,
+        admin_key: Password,
+    ) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let server = Arc::new(tiny_http::Server::http(addr)?);
+        let addr = server.server_addr().to_ip().ok_or("not an IP address")?;
+        let (started, startup) = mpsc::channel();
+        let listener = server.clone();
+        let thread = std::thread::spawn(move || {
+            let mut service = match Service::new(&snapshot, admin_key) {
+                Ok(service) => {
+                    started.send(Ok(())).unwrap();
+                    service
+                }
+                Err(e) => return started.send(Err(e)).unwrap(),
+            };
+            for mut request in listener.incoming_requests() {
+                let (status, body) = service.handle(&mut request);
+                let content_type = match request.url().trim_matches('/') {
+                    "bulletin-board" if status == 200 => "application/octet-stream",
+                    _ if status == 200 || status == 422 => "application/json",
+                    _ => "text/plain",
+                };
+                let header = Header::from_bytes("Content-Type", content_type).unwrap();
+                let response = Response::from_data(body)
+                    .with_status_code(status)
+                    .with_header(header);
+                // The client may have gone away, which does not concern other requests.
+                let _ = request.respond(response);
+            }
+        });
+        startup.recv()??;
+        Ok(HttpServer {
+            addr,
+            server,
+            thread: Some(thread),
+        })
+    }
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+    /// The base URL of the service, e.g. to create a `Client`.
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+}
+
+impl Drop for HttpServer {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The service rejected the request as breaking the protocol, e.g. an invalid ballot.
+    Protocol(ProtocolError),
+    /// The service rejected the request otherwise, e.g. it was made at the wrong step.
+    Status(u16, String),
+    /// The service could not be reached, or its reply could not be decoded.
+    Transport(String),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Protocol(e) => write!(f, "{}", e),
+            ClientError::Status(status, message) => write!(f, "{} ({})", message, status),
+            ClientError::Transport(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl Error for ClientError {}
+
+/// A client of the HTTP service of a voting server, see `HttpServer`.
+pub struct Client<G: Group = Ristretto> {
+    url: String,
+    agent: ureq::Agent,
+    admin_key: Option<Password>,
+    group: PhantomData<G>,
+}
+
+impl Client {
+    pub fn new(url: &str) -> Client {
+        Client::with_group(url)
+    }
+}
+
+impl<G: Group> Client<G> {
+    /// A client for a service running an election in the group G.
+    pub fn with_group(url: &str) -> Self {
+        Client {
+            url: url.trim_end_matches('/').to_string(),
+            agent: ureq::Agent::new(),
+            admin_key: None,
+            group: PhantomData,
+        }
+    }
+    /// Authenticates the requests of the server administrator, e.g. `close` and `tally`.
+    pub fn with_admin_key(mut self, admin_key: Password) -> Self {
+        self.admin_key = Some(admin_key);
+        self
+    }
+
+    fn send(
+        &self,
+        method: &str,
+        path: &str,
+        body: Option<Vec<u8>>,
+    ) -> Result<Vec<u8>, ClientError> {
+        let mut request = self.agent.request(method, &format!("{}{}", self.url, path));
+        if let Some(key) = &self.admin_key {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let response = match body {
+            Some(body) => request
+                .set("Content-Type", "application/json")
+                .send_bytes(&body),
+            None => request.call(),
+        };
+        let read = |response: ureq::Response| {
+            let mut body = Vec::new();
+            response
+                .into_reader()
+                .take(MAX_BODY_LEN)
+                .read_to_end(&mut body)
+                .map(|_| body)
+                .map_err(|e| ClientError::Transport(e.to_string()))
+        };
+        match response {
+            Ok(response) => read(response),
+            Err(ureq::Error::Status(422, response)) => {
+                let body = read(response)?;
+                match serde_json::from_slice(&body) {
+                    Ok(e) => Err(ClientError::Protocol(e)),
+                    Err(e) => Err(ClientError::Transport(e.to_string())),
+                }
+            }
+            Err(ureq::Error::Status(status, response)) => {
+                let body = read(response)?;
+                let message = String::from_utf8_lossy(&body).to_string();
+                Err(ClientError::Status(status, message))
+            }
+            Err(e) => Err(ClientError::Transport(e.to_string())),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, ClientError> {
+        let body = self.send("GET", path, None)?;
+        serde_json::from_slice(&body).map_err(|e| ClientError::Transport(e.to_string()))
+    }
+
+    fn post<M: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        message: &M,
+    ) -> Result<T, ClientError> {
+        let body = serde_json::to_vec(message).unwrap();
+        let body = self.send("POST", path, Some(body))?;
+        if body.is_empty() {
+            // The request had no reply but its status, e.g. a ballot was accepted.
+            return serde_json::from_slice(b"null")
+                .map_err(|e| ClientError::Transport(e.to_string()));
+        }
+        serde_json::from_slice(&body).map_err(|e| ClientError::Transport(e.to_string()))
+    }
+
+    pub fn election(&self) -> Result<E11M<G>, ClientError> {
+        self.get("/election")
+    }
+    /// Submits a ballot, failing with the reason the voting server rejected it, if any.
+    pub fn submit_ballot(&self, ballot: &V3Mi<G>) -> Result<(), ClientError> {
+        self.post("/ballots", ballot)
+    }
+    pub fn ballots(&self) -> Result<Vec<Ballot<G>>, ClientError> {
+        self.get("/ballots")
+    }
+    pub fn close(&self) -> Result<T2M<G>, ClientError> {
+        self.post("/close", &EmptyMessage)
+    }
+    pub fn encrypted_tally(&self) -> Result<T2M<G>, ClientError> {
+        self.get("/encrypted-tally")
+    }
+    /// Submits the partial decryption of the i-th trustee, failing with the reason the voting
+    /// server rejected it, if any.
+    pub fn submit_partial_decryption(
+        &self,
+        i: usize,
+        partial_decryption: &T3Mi<G>,
+    ) -> Result<(), ClientError> {
+        self.post(&format!("/partial-decryptions/{}", i), partial_decryption)
+    }
+    pub fn tally(&self) -> Result<T7M<G>, ClientError> {
+        self.post("/tally", &EmptyMessage)
+    }
+    pub fn result(&self) -> Result<T7M<G>, ClientError> {
+        self.get("/result")
+    }
+    /// The entries of the bulletin board, to be checked with `BulletinBoard::verify` or
+    /// `verify::verify_election`.
+    pub fn bulletin_board(&self) -> Result<Vec<Entry<G>>, ClientError> {
+        let body = self.send("GET", "/bulletin-board", None)?;
+        BulletinBoard::import(&body).map_err(ClientError::Protocol)
+    }
+}
@@ -5,7 +5,7 @@ extern crate derive_builder;
 #[macro_use]
 extern crate serde;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ProtocolError {
     IncorrectLenError,
     DifferentMultisetError,
@@ -14,14 +14,73 @@ pub enum ProtocolError {
     DisagreementOverLError,
     CredentialNotFoundError,
     CredentialUsedTwiceError,
+    // A valid ballot was kept for auditing but does not count, as its credential already has an
+    // accepted ballot (see `RevotePolicy::KeepFirst`).
+    BallotDiscardedError,
     BallotVerificationError,
+    // A ballot was not signed, or its signature does not verify under its credential.
+    BallotSignatureError,
+    // A partial decryption does not have the shape of the encrypted tally, or is missing.
+    PartialDecryptionMalformedError(Vec<usize>),
+    // The proof of correct decryption of some trustees failed to verify.
+    PartialDecryptionProofFailedError(Vec<usize>),
+    // Too few trustees gave a valid partial decryption to decrypt the tally.
+    NotEnoughPartialDecryptionsError,
+    // Some dealers of the threshold key-generation ceremony were disqualified.
+    DealerDisqualifiedError(Vec<usize>),
+    // The threshold of "pedersen" trustees is not between 1 and the number of trustees.
+    InvalidThresholdError,
+    // The complaints relayed to a "pedersen" trustee are not the ones it made, or it got no valid
+    // share from some qualified dealer, so it cannot derive its key share.
+    KeyGenerationTranscriptError,
+    // A symmetric ciphertext failed to authenticate, e.g. it was tampered with or the wrong key
+    // was used.
+    DecryptionError,
+    // A discrete logarithm (e.g. some tallied value) was not in the range of the `DLogTable`.
+    DLogOutOfRangeError,
+    // The bound of a `DLogTable` (e.g. the total weight of all voters) is too large to build it.
+    DLogBoundTooLargeError,
+    // A deserialized `DLogTable` is not the table of its bound, e.g. it has the wrong size, or
+    // gives a wrong discrete logarithm.
+    MalformedDLogTableError,
+    // Some JSON does not follow the layout of the specification, or encodes an invalid value.
+    MalformedJsonError,
+    // A credential recovery was requested for a voter that is not registered.
+    UnknownVoterError,
+    // A credential recovery request does not come with the recovery code of the voter.
+    RecoveryCodeError,
+    // A new credential is already in the public list L.
+    DuplicateCredentialError,
+    // The entry at this index of a bulletin board does not chain to the one before it (or the
+    // log could not be decoded).
+    BulletinBoardChainError(usize),
+    // A snapshot was written by another version of this crate.
+    SnapshotVersionError,
+    // A snapshot is of another role, step or group than the participant it is resumed into.
+    SnapshotPhaseError,
+    // A snapshot could not be decoded.
+    MalformedSnapshotError,
+    // A password is not a Base58 string of the right length, or fails its checksum.
+    MalformedPasswordError,
 }
 
+impl std::fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
 pub mod datatypes {
     pub mod ballot;
+    pub mod ballot_box;
     pub mod base58;
+    pub mod bulletin_board;
     pub mod credentials;
     pub mod election;
+    #[cfg(feature = "spec-json")]
+    pub mod json;
     pub mod questions;
     pub mod voter_ids;
 }
@@ -30,14 +89,27 @@ pub mod participants {
     pub mod credential_authority;
     pub mod messages;
     pub mod participant_template;
+    pub mod pedersen_trustee;
     pub mod server_admin;
+    pub mod snapshot;
     pub mod trustee;
     pub mod voter;
     pub mod voting_server;
 }
 
 pub mod primitives {
+    pub mod ff_group;
     pub mod group;
     pub mod pki;
+    pub mod rng;
+    pub(crate) mod transcript;
+    pub mod vss;
     pub mod zkp;
 }
+
+pub mod verify;
+
+pub(crate) mod parallel;
+
+#[cfg(feature = "http")]
+pub mod http;
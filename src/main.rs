@@ -1,173 +1,442 @@
-use belenios::datatypes::election::ElectionBuilder;
-use belenios::datatypes::questions::QuestionBuilder;
+//! The `belenios` command-line tool, which lets each stakeholder run their part of an election
+//! on their own machine, exchanging files with the others (similarly to `belenios-tool`).
+//!
+//! An election goes as follows, where each participant works in its own directory:
+//! 1. the administrator runs `setup` on a template of the election, and sends the credential
+//!    request to the credential authority,
+//! 2. the credential authority runs `credentials`, sending the i-th credential to the i-th voter
+//!    and the public credentials to the administrator,
+//! 3. each trustee runs `trustee-keygen` on the credential request (for the UUID of the election)
+//!    with its index, sending its public key to the administrator,
+//! 4. the administrator runs `make-election`, and publishes the election,
+//! 5. each voter runs `vote`, and sends their ballot to the administrator (anybody may check a
+//!    ballot with `verify-ballot`),
+//! 6. the administrator runs `aggregate` on the ballots, and sends the encrypted tally to the
+//!    trustees,
+//! 7. each trustee runs `partial-decrypt`, sending its partial decryption to the administrator,
+//! 8. the administrator runs `finalize`, publishing the result and the bulletin board, which
+//!    anybody may check with `verify-election`.
+//!
+//! Between their steps, the voting server and the trustees are saved as snapshots in their
+//! directory. Messages are JSON files.
+//!
+//! The election runs in the group named by the `group` of the template (Ristretto by default),
+//! and each later step runs in the group of its input, e.g. the `group` of the election.
+#![allow(non_snake_case)]
+use belenios::datatypes::bulletin_board::BulletinBoard;
+use belenios::datatypes::credentials::Password;
 use belenios::participants::credential_authority::CredentialAuthority;
 use belenios::participants::messages::*;
 use belenios::participants::participant_template::*;
 use belenios::participants::server_admin::ServerAdmin;
-use belenios::participants::trustee::Trustee;
+use belenios::participants::snapshot::{snapshot_group, Snapshot};
+use belenios::participants::trustee::{self, Trustee};
 use belenios::participants::voter::Voter;
-use belenios::participants::voting_server;
-use belenios::participants::voting_server::VotingServer;
+use belenios::participants::voting_server::{self, VotingServer};
+use belenios::primitives::ff_group::Belenios2048;
+use belenios::primitives::group::{Group, Ristretto};
+#[cfg(feature = "deterministic-rng")]
+use belenios::primitives::rng::DeterministicRandom;
+use belenios::primitives::rng::SecureRandom;
+use belenios::verify::verify_election;
+use clap::{Parser, Subcommand};
 use ring::rand::SystemRandom;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::sync::{Arc, Mutex};
 
-fn main() {
-    //
-    // ***START OF THE ELECTION SETUP PHASE***
-    //
+#[derive(Parser)]
+#[command(
+    name = "belenios",
+    about = "Offline administration of Belenios elections"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Draws all randomness from this seed, to reproduce a run. Insecure, for tests only.
+    #[cfg(feature = "deterministic-rng")]
+    #[arg(long, global = true)]
+    seed: Option<String>,
+}
 
-    // THE ELECTION SETUP
-    let NUM_VOTERS = 10;
-    let NUM_TRUSTEES = 9;
-    // Defining the weights of the voters (10 voters of weight 1 each).
-    let voters = vec![1; NUM_VOTERS];
-    let election = E1MBuilder::default().voters(voters).build().unwrap();
+impl Cli {
+    fn rng(&self) -> Arc<Mutex<dyn SecureRandom>> {
+        #[cfg(feature = "deterministic-rng")]
+        if let Some(seed) = &self.seed {
+            let digest = ring::digest::digest(&ring::digest::SHA256, seed.as_bytes());
+            let seed = digest.as_ref().try_into().unwrap();
+            return Arc::new(Mutex::new(DeterministicRandom::new(seed)));
+        }
+        Arc::new(Mutex::new(SystemRandom::new()))
+    }
+}
 
-    // Defining the various parties who participate in the election.
-    let rng = Arc::new(Mutex::new(SystemRandom::new()));
-    // Loading election into voting server and server administrator
-    let server_admin = ServerAdmin::new(rng.clone());
-    // Loading election into voting server
-    let voting_server = VotingServer::new(rng.clone());
+#[derive(Subcommand)]
+enum Command {
+    /// Sets up the voting server from a template of the election.
+    Setup {
+        /// The voters' weights and the questions, see `Template`.
+        #[arg(long)]
+        template: PathBuf,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Generates a credential for each voter, along with the public list of credentials.
+    Credentials {
+        /// The credential request written by `setup`.
+        #[arg(long)]
+        request: PathBuf,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Generates the key of a trustee, and prints the key protecting it at rest.
+    TrusteeKeygen {
+        /// The credential request written by `setup`, for the UUID of the election.
+        #[arg(long)]
+        request: PathBuf,
+        /// The position of the trustee's public key in the keys given to `make-election`.
+        #[arg(long)]
+        index: usize,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Creates the election from the public credentials and the trustees' public keys.
+    MakeElection {
+        #[arg(long)]
+        template: PathBuf,
+        #[arg(long)]
+        public_credentials: PathBuf,
+        #[arg(long, num_args = 1.., required = true)]
+        trustee_keys: Vec<PathBuf>,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Creates a ballot from a credential and a choice file, e.g. `[[true, false, false]]` for
+    /// the first answer of a single question.
+    Vote {
+        #[arg(long)]
+        credential: PathBuf,
+        #[arg(long)]
+        election: PathBuf,
+        #[arg(long)]
+        choices: PathBuf,
+        #[arg(long)]
+        out: PathBuf,
+    },
+    /// Checks a ballot against the election.
+    VerifyBallot {
+        #[arg(long)]
+        election: PathBuf,
+        #[arg(long)]
+        ballot: PathBuf,
+    },
+    /// Adds the ballots to the ballot box, closes the election and computes the encrypted tally.
+    Aggregate {
+        #[arg(long, num_args = 0..)]
+        ballots: Vec<PathBuf>,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Computes the partial decryption of the encrypted tally by a trustee.
+    PartialDecrypt {
+        #[arg(long)]
+        tally: PathBuf,
+        /// The key printed by `trustee-keygen`.
+        #[arg(long)]
+        key: Password,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Combines the partial decryptions of the trustees, and publishes the result.
+    Finalize {
+        /// In the same order as the keys given to `make-election`.
+        #[arg(long, num_args = 1.., required = true)]
+        partial_decryptions: Vec<PathBuf>,
+        #[arg(long, default_value = ".")]
+        dir: PathBuf,
+    },
+    /// Checks a whole election from its bulletin board.
+    VerifyElection {
+        #[arg(long)]
+        board: PathBuf,
+        /// The published head of the bulletin board, in hexadecimal.
+        #[arg(long)]
+        head: Option<String>,
+    },
+}
 
-    let (server_admin, message_SA_to_CA) = server_admin.process_message(election.clone());
-    let (voting_server, message_VS_to_CA) = voting_server.process_message(election);
-    let message_to_CA = E3M::from((message_VS_to_CA, message_SA_to_CA));
-    let credential_authority = CredentialAuthority::new(rng.clone());
-    let (credential_authority, message_to_voters) =
-        credential_authority.process_message(message_to_CA);
+/// The template of an election: the weights of the voters and the fields of `E10M`, e.g.
+/// `{"voters": [1, 1], "election": {"version": 1, "name": "...", "description": "...",
+/// "administrator": "...", "credential_authority": "...", "questions": [{"question": "...",
+/// "answers": ["...", "..."], "blank": false, "min": 0, "max": 1}]}}`, and optionally the
+/// group, e.g. `"group": "BELENIOS-2048"`.
+#[derive(Deserialize)]
+struct Template {
+    #[serde(default = "default_group")]
+    group: String,
+    voters: Vec<u128>,
+    election: E10M,
+}
 
-    let message_to_voters: Vec<E4Mi> = message_to_voters.into();
-    let mut voters = Vec::new();
-    // Setting up the voters
-    for i in 0..NUM_VOTERS {
-        let new_voter = Voter::new(rng.clone());
-        let (new_voter, _) = new_voter.process_message(message_to_voters[i].clone());
-        voters.push(new_voter);
-    }
-    // Credential Authority gives the public list of weights/stuff to the Voting Server.
-    let (credential_authority, message) = credential_authority.process_message(EmptyMessage);
-    let (voting_server, message) = voting_server.process_message(message);
-    message
-        .check
-        .expect("The voting server failed the check in E7");
-    // Voting Server initializes the Trustees
-    let mut trustees = Vec::new();
-    let mut trustee_keys = Vec::new();
+fn default_group() -> String {
+    Ristretto::NAME.to_string()
+}
 
-    for i in 0..NUM_TRUSTEES {
-        let new_trustee = Trustee::new(rng.clone());
-        let (new_trustee, trustee_key) = new_trustee.process_message(EmptyMessage);
-        trustees.push(new_trustee);
-        trustee_keys.push(trustee_key);
-    }
-    let trustee_keys: E9M = trustee_keys.into();
-    let (voting_server, message) = voting_server.process_message(trustee_keys);
-    message.check.expect(
-        "If a trustee tried to fake a ZK proof, their index has been recorded in this message",
-    );
-    //
-    // DEFINING THE ELECTION
-    //
-    let quest = "Who should be IACR director in 2021?";
-    let ans = vec!["Mark Fischlin", "Nadia Heninger", "Anna Lysyanskaya"];
-    let question_one = QuestionBuilder::default()
-        .question(quest)
-        .answers(ans)
-        .build()
-        .unwrap();
-    let quest = "Which Hardness Assumption will be broken next?";
-    let ans = vec![
-        "RLWE with Small Galois Group",
-        "Small Moduli LWR",
-        "RSA will be destroye",
-    ];
-    let question_two = QuestionBuilder::default()
-        .question(quest)
-        .answers(ans)
-        .build()
-        .unwrap();
-    let version: usize = 1;
-    let description = "This is a Test Election".to_string();
-    let name = "Test Election".to_string();
-    let admin_name = "Mark Schultz's Left Hand".to_string();
-    let ca_name = "Mark Schultz's Right Hand".to_string();
-    //
-    // END DEFINING THE ELECTION
-    //
+const SERVER: &str = "server.snapshot";
+const TRUSTEE: &str = "trustee.snapshot";
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let data = std::fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    Ok(serde_json::from_slice(&data).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
 
-    let message: E10M = E10MBuilder::default()
-        .questions(vec![question_one, question_two])
-        .version(1)
-        .description(description)
-        .name(name)
-        .administrator(admin_name)
-        .credential_authority(ca_name)
-        .build()
-        .unwrap();
-    let (voting_server, election_message) = voting_server.process_message(message);
-    // This message defines the election, transmit copies of it to voters eventually.
-    let (credential_authority, message) =
-        credential_authority.process_message(election_message.clone());
-    message
-        .check
-        .expect("The Credential Authority and the Voting Server disagree over the public list L");
-    //
-    // *** END OF THE ELECTION SETUP PHASE ***
-    //
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), Box<dyn Error>> {
+    std::fs::write(path, serde_json::to_vec(value)?)
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
 
-    //
-    // *** START OF THE VOTING PHASE ***
-    //
+fn load_snapshot<G: Group>(path: &Path) -> Result<Snapshot<G>, Box<dyn Error>> {
+    Ok(Snapshot::load(path).map_err(|e| format!("{}: {}", path.display(), e))?)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
 
-    // Transmit the election to each voter.
-    let voters: Vec<(Voter<belenios::participants::voter::V1>, EmptyMessage)> = voters
-        .into_iter()
-        .map(|v| v.process_message(election_message.clone()))
-        .collect();
-    // Create votes for voters.
-    // Each choice is a Vec<bool> denoting which options to vote for on a particular question.
-    // Associated with each voter is then a Vec<Vec<bool>>, denoting for each questino which
-    // options to vote for.
+/// The name of the group the command runs in, from its input.
+fn group(command: &Command) -> Result<String, Box<dyn Error>> {
+    let group_of_snapshot = |path: PathBuf| -> Result<String, Box<dyn Error>> {
+        let bytes = std::fs::read(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(snapshot_group(&bytes).map_err(|e| format!("{}: {}", path.display(), e))?)
+    };
+    match command {
+        Command::Setup { template, .. } => Ok(read_json::<Template>(template)?.group),
+        Command::Credentials { request, .. } | Command::TrusteeKeygen { request, .. } => {
+            Ok(read_json::<E3M>(request)?.group().to_string())
+        }
+        Command::MakeElection { dir, .. }
+        | Command::Aggregate { dir, .. }
+        | Command::Finalize { dir, .. } => group_of_snapshot(dir.join(SERVER)),
+        Command::PartialDecrypt { dir, .. } => group_of_snapshot(dir.join(TRUSTEE)),
+        Command::Vote { election, .. } | Command::VerifyBallot { election, .. } => {
+            let json: serde_json::Value = read_json(election)?;
+            match json["election"]["group"].as_str() {
+                Some(group) => Ok(group.to_string()),
+                None => Err(format!("{}: the election has no group", election.display()).into()),
+            }
+        }
+        // Elections of other groups fail to decode, so only the group of the election decodes
+        // its bulletin board.
+        Command::VerifyElection { board, .. } => {
+            let bytes = std::fs::read(board)?;
+            match BulletinBoard::<Belenios2048>::import(&bytes) {
+                Ok(_) => Ok(Belenios2048::NAME.to_string()),
+                Err(_) => Ok(Ristretto::NAME.to_string()),
+            }
+        }
+    }
+}
 
-    // Q1 everyone votes for the same person
-    let choice_q1: Vec<bool> = vec![false, false, true];
-    // Q2 there will be three options people are roughly split between
-    let choice_q2a = vec![true, false, false];
-    let choice_q2b = vec![false, true, false];
-    let choice_q2c = vec![false, false, true];
+fn run(rng: Arc<Mutex<dyn SecureRandom>>, command: Command) -> Result<bool, Box<dyn Error>> {
+    match group(&command)? {
+        group if group == Ristretto::NAME => run_in::<Ristretto>(rng, command),
+        group if group == Belenios2048::NAME => run_in::<Belenios2048>(rng, command),
+        group => Err(format!("unsupported group {}", group).into()),
+    }
+}
 
-    let voters: Vec<(Voter<belenios::participants::voter::V1>, V3Mi)> = voters
-        .into_iter()
-        .enumerate()
-        .map(|(idx, (v, _))| {
-            let mut choices = Vec::new();
-            choices.push(choice_q1.clone());
-            if idx <= 3 {
-                choices.push(choice_q2a.clone());
-            } else if idx <= 7 {
-                choices.push(choice_q2b.clone())
-            } else {
-                choices.push(choice_q2c.clone());
+fn run_in<G: Group>(
+    rng: Arc<Mutex<dyn SecureRandom>>,
+    command: Command,
+) -> Result<bool, Box<dyn Error>> {
+    // The states of the voting server are public, so its snapshots are not encrypted, and any
+    // key resumes them.
+    let server_key = Password::gen(rng.clone());
+    match command {
+        Command::Setup { template, dir } => {
+            let template: Template = read_json(&template)?;
+            let voters = E1MBuilder::default().voters(template.voters).build()?;
+            let (_, admin_message) = ServerAdmin::new(rng.clone()).process_message(voters.clone());
+            let (voting_server, server_message) =
+                VotingServer::<EmptyState, G>::with_group(rng.clone()).process_message(voters);
+            write_json(
+                &dir.join("credential-request.json"),
+                &E3M::from((server_message, admin_message)),
+            )?;
+            voting_server.snapshot(&server_key).save(dir.join(SERVER))?;
+        }
+        Command::Credentials { request, dir } => {
+            let request: E3M = read_json(&request)?;
+            let (credential_authority, passwords) =
+                CredentialAuthority::<EmptyState, G>::with_group(rng.clone())
+                    .process_message(request);
+            for (i, password) in Vec::<E4Mi>::from(passwords).iter().enumerate() {
+                write_json(&dir.join(format!("credential-{}.json", i)), password)?;
+            }
+            let (_, public_credentials) = credential_authority.process_message(EmptyMessage);
+            write_json(&dir.join("public-credentials.json"), &public_credentials)?;
+        }
+        Command::TrusteeKeygen {
+            request,
+            index,
+            dir,
+        } => {
+            let request: E3M = read_json(&request)?;
+            let context = E8MiBuilder::default()
+                .uuid(request.uuid().clone())
+                .index(index)
+                .build()?;
+            let key = Password::gen(rng.clone());
+            let (trustee, public_key) =
+                Trustee::<EmptyState, G>::with_group(rng.clone()).process_message(context);
+            write_json(&dir.join("trustee-public-key.json"), &public_key)?;
+            trustee.snapshot(&key).save(dir.join(TRUSTEE))?;
+            println!("The key of the trustee, needed to decrypt, is {}", key);
+        }
+        Command::MakeElection {
+            template,
+            public_credentials,
+            trustee_keys,
+            dir,
+        } => {
+            let template: Template = read_json(&template)?;
+            let public_credentials: E7M<G> = read_json(&public_credentials)?;
+            let trustee_keys = trustee_keys
+                .iter()
+                .map(|path| read_json(path))
+                .collect::<Result<Vec<E9Mi<G>>, _>>()?;
+            let snapshot = load_snapshot(&dir.join(SERVER))?;
+            let voting_server =
+                VotingServer::<voting_server::E3, G>::resume(rng.clone(), &snapshot, &server_key)?;
+            let (voting_server, message) = voting_server.process_message(public_credentials);
+            message.check?;
+            let (voting_server, message) = voting_server.process_message(E9M::from(trustee_keys));
+            message.check?;
+            let (voting_server, election) = voting_server.process_message(template.election);
+            let (voting_server, _) = voting_server.process_message(EmptyMessage);
+            write_json(&dir.join("election.json"), &election)?;
+            voting_server.snapshot(&server_key).save(dir.join(SERVER))?;
+        }
+        Command::Vote {
+            credential,
+            election,
+            choices,
+            out,
+        } => {
+            let credential: E4Mi = read_json(&credential)?;
+            let election: E11M<G> = read_json(&election)?;
+            let choices = V2Mi {
+                choices: read_json(&choices)?,
+            };
+            let (voter, _) =
+                Voter::<EmptyState, G>::with_group(rng.clone()).process_message(credential);
+            let (voter, _) = voter.process_message(election);
+            let (_, ballot) = voter.process_message(choices);
+            write_json(&out, &ballot)?;
+        }
+        Command::VerifyBallot { election, ballot } => {
+            let election: E11M<G> = read_json(&election)?;
+            let ballot: V3Mi<G> = read_json(&ballot)?;
+            match election.verify_ballot(&ballot) {
+                Ok(()) => println!("The ballot is valid"),
+                Err(e) => {
+                    println!("The ballot is invalid: {}", e);
+                    return Ok(false);
+                }
             }
-            let message = V2Mi { choices };
-            v.process_message(message)
-        })
-        .collect();
-    let mut new_voters: Vec<Voter<belenios::participants::voter::V1>> = Vec::new();
-    let mut messages = Vec::new();
-    for (voter, message) in voters.into_iter() {
-        new_voters.push(voter);
-        messages.push(message);
+        }
+        Command::Aggregate { ballots, dir } => {
+            let snapshot = load_snapshot(&dir.join(SERVER))?;
+            let mut voting_server = VotingServer::<voting_server::V4<G>, G>::resume(
+                rng.clone(),
+                &snapshot,
+                &server_key,
+            )?;
+            for path in ballots.iter() {
+                let ballot: V3Mi<G> = read_json(path)?;
+                let (new_server, message) = voting_server.process_message(ballot);
+                if let Err(e) = message.check {
+                    println!("Rejected {}: {}", path.display(), e);
+                }
+                voting_server = new_server;
+            }
+            let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+            write_json(&dir.join("encrypted-tally.json"), &encrypted_tally)?;
+            voting_server.snapshot(&server_key).save(dir.join(SERVER))?;
+        }
+        Command::PartialDecrypt { tally, key, dir } => {
+            let tally: T2M<G> = read_json(&tally)?;
+            let snapshot = load_snapshot(&dir.join(TRUSTEE))?;
+            let trustee = Trustee::<trustee::E9<G>, G>::resume(rng.clone(), &snapshot, &key)?;
+            let (trustee, partial_decryption) = trustee.process_message(tally);
+            write_json(&dir.join("partial-decryption.json"), &partial_decryption)?;
+            trustee.snapshot(&key).save(dir.join(TRUSTEE))?;
+        }
+        Command::Finalize {
+            partial_decryptions,
+            dir,
+        } => {
+            let partial_decryptions = partial_decryptions
+                .iter()
+                .map(|path| read_json(path))
+                .collect::<Result<Vec<T3Mi<G>>, _>>()?;
+            let snapshot = load_snapshot(&dir.join(SERVER))?;
+            let voting_server = VotingServer::<voting_server::T2<G>, G>::resume(
+                rng.clone(),
+                &snapshot,
+                &server_key,
+            )?;
+            let (voting_server, message) =
+                voting_server.process_message(T3M::from(partial_decryptions));
+            message.check?;
+            let (voting_server, result) = voting_server.process_message(EmptyMessage);
+            write_json(&dir.join("result.json"), &result)?;
+            let board = voting_server.bulletin_board();
+            let path = dir.join("bulletin-board.bin");
+            std::fs::write(&path, board.export())?;
+            println!(
+                "Wrote {}, with head {}",
+                path.display(),
+                to_hex(board.head())
+            );
+            voting_server.snapshot(&server_key).save(dir.join(SERVER))?;
+            for (i, counts) in result.result?.iter().enumerate() {
+                println!("Question {}: {:?}", i + 1, counts);
+            }
+        }
+        Command::VerifyElection { board, head } => {
+            let entries = BulletinBoard::<G>::import(&std::fs::read(&board)?)?;
+            let report = verify_election(&entries);
+            for failure in report.failures.iter() {
+                println!("Failed: {:?}", failure);
+            }
+            let head_matches =
+                head.is_none_or(|head| head.eq_ignore_ascii_case(&to_hex(&report.head)));
+            if !head_matches {
+                println!("Failed: the head of the bulletin board is not the published one");
+            }
+            if !report.is_valid() || !head_matches {
+                return Ok(false);
+            }
+            println!("The election is valid");
+        }
     }
-    let (mut voting_server, _) = voting_server.process_message(EmptyMessage);
-    for ballot in messages.into_iter() {
-        let (temp_voting_server, message) = voting_server.process_message(ballot);
-        // Could handle one of ~3 different errors that could happen here.
-        message
-            .check
-            .expect("Cheap error handling --- will panic if any misbehavior is detected");
-        voting_server = temp_voting_server;
+    Ok(true)
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli.rng(), cli.command) {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
     }
-    // Have the list of accepted_ballots now.
 }
@@ -0,0 +1,52 @@
+//! Helpers which spread work across threads (with rayon) when the `parallel` feature is enabled,
+//! and run it sequentially otherwise, so that callers need not care which.
+//!
+//! Only verification and aggregation are parallel: they need no randomness, so the threads never
+//! contend for the RNG of a participant.
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// `items.iter().map(f).collect()`.
+pub(crate) fn map<T, U, F>(items: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&T) -> U + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().map(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().map(f).collect();
+}
+
+/// `items.chunks(size).flat_map(f).collect()`, for work which is cheaper on many items at once.
+pub(crate) fn flat_map_chunks<T, U, F>(items: &[T], size: usize, f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send,
+    F: Fn(&[T]) -> Vec<U> + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_chunks(size).flat_map_iter(f).collect();
+    #[cfg(not(feature = "parallel"))]
+    return items.chunks(size).flat_map(f).collect();
+}
+
+/// Folds `items` into an accumulator starting from `identity()`.
+/// Across threads, each thread folds some of the items and the accumulators are then combined with
+/// `merge`, so folding must not depend on the order of the items.
+#[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+pub(crate) fn fold<T, A, I, F, M>(items: &[T], identity: I, f: F, merge: M) -> A
+where
+    T: Sync,
+    A: Send,
+    I: Fn() -> A + Sync + Send,
+    F: Fn(A, &T) -> A + Sync + Send,
+    M: Fn(A, A) -> A + Sync + Send,
+{
+    #[cfg(feature = "parallel")]
+    return items.par_iter().fold(&identity, f).reduce(&identity, merge);
+    #[cfg(not(feature = "parallel"))]
+    return items.iter().fold(identity(), f);
+}
@@ -2,15 +2,21 @@
 
 use std::sync::{Arc, Mutex};
 
+use ring::digest::{digest, SHA256};
+
 use crate::datatypes::credentials::{Credential, ExpandedCredential, Password, UUID};
 use crate::datatypes::election::Election;
 use crate::participants::messages::*;
 use crate::participants::participant_template::*;
-use crate::primitives::group::{Point, Scalar};
+use crate::participants::server_admin::recovery_verifier;
+use crate::participants::snapshot::persistent_state_impl;
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::rng::SecureRandom;
 use crate::ProtocolError;
-use ring::rand::SecureRandom;
+use serde::{Deserialize, Serialize};
 
 initialize_participant_impl!(CredentialAuthority);
+persistent_state_impl!(CredentialAuthority, true, E4<G>, E12<G>);
 
 /// Randomly samples from [a, b).
 /// For |b-a| large enough there may be issues with the distribution being non-uniform.
@@ -19,13 +25,26 @@ initialize_participant_impl!(CredentialAuthority);
 fn rand_range(rng: Arc<Mutex<dyn SecureRandom>>, a: usize, b: usize) -> usize {
     assert!(a <= b);
     let zero_centered_range = (b - a) as u128;
-    let mut buff = [0 as u8; 128 / 8];
+    let mut buff = [0u8; 128 / 8];
     rng.lock().unwrap().fill(&mut buff).unwrap();
     let rand_val: u128 = u128::from_be_bytes(buff);
     let mod_val = (rand_val % zero_centered_range) as usize;
     a + mod_val
 }
 
+/// A commitment to the public credential of a voter, which only their recovery code opens.
+/// This lets the CA find the credential of a voter in L during credential recovery, while
+/// otherwise knowing no more than L of who each credential belongs to.
+fn commit_credential<G: Group>(
+    uuid: &UUID,
+    voter: usize,
+    recovery_code: &Password,
+    credential: &Point<G>,
+) -> Vec<u8> {
+    let data = bincode::serialize(&(uuid, voter, recovery_code, credential)).unwrap();
+    digest(&SHA256, &data).as_ref().into()
+}
+
 /// Uniformly permutes a vector using the Fischer-Yates Shuffle
 fn uniformly_permute<T>(rng: Arc<Mutex<dyn SecureRandom>>, vec: Vec<T>) -> Vec<T> {
     let mut vec = vec;
@@ -39,35 +58,53 @@ fn uniformly_permute<T>(rng: Arc<Mutex<dyn SecureRandom>>, vec: Vec<T>) -> Vec<T
 }
 
 process_message_impl!(
+    <G>,
     CredentialAuthority,
     EmptyState,
-    E4,
+    E4<G>,
     E3M,
     E4M,
-    |s: CredentialAuthority<EmptyState>, message: E3M| {
+    |s: CredentialAuthority<EmptyState, G>, message: E3M| {
         let rng = s.rng.clone();
         let uuid = message.uuid;
         let num_voters = message.voters.len();
         let mut passwords: Vec<Password> = Vec::with_capacity(num_voters);
-        let mut L: Vec<(Point, u128)> = Vec::with_capacity(num_voters);
+        let mut recovery_codes: Vec<Password> = Vec::with_capacity(num_voters);
+        let mut L: Vec<(Point<G>, u128)> = Vec::with_capacity(num_voters);
+        let mut commitments: Vec<Vec<u8>> = Vec::with_capacity(num_voters);
         for i in 0..num_voters {
-            let expanded_cred = ExpandedCredential::gen(rng.clone(), &uuid);
-            let (pass, pub_key): (Password, Point) =
+            let expanded_cred = ExpandedCredential::<G>::gen(rng.clone(), &uuid);
+            let (pass, pub_key): (Password, Point<G>) =
                 (expanded_cred.password, expanded_cred.public_key);
+            let recovery_code = Password::gen(rng.clone());
+            commitments.push(commit_credential(&uuid, i, &recovery_code, &pub_key));
             passwords.push(pass);
+            recovery_codes.push(recovery_code);
             L.push((pub_key, message.voters[i]))
         }
         L = uniformly_permute(rng, L);
-        let state = E4Builder::default().uuid(uuid).L(L).build().unwrap();
-        let message = E4MBuilder::default().passwords(passwords).build().unwrap();
+        let state = E4Builder::default()
+            .uuid(uuid)
+            .L(L)
+            .commitments(commitments)
+            .build()
+            .unwrap();
+        let message = E4MBuilder::default()
+            .passwords(passwords)
+            .recovery_codes(recovery_codes)
+            .build()
+            .unwrap();
         (state, message)
     }
 );
 
-#[derive(Builder)]
-pub struct E4 {
+#[derive(Builder, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E4<G: Group = Ristretto> {
     uuid: UUID,
-    L: Vec<(Point, u128)>,
+    L: Vec<(Point<G>, u128)>,
+    // The commitment to the public credential of the i-th voter, see `commit_credential`.
+    commitments: Vec<Vec<u8>>,
 }
 
 // Note that we have already had the CA forget the credentials c1, ..., cn,
@@ -77,29 +114,34 @@ pub struct E4 {
 // but this would either require copying the ci's, or dealing with lifetimes, for no good reason.
 
 process_message_impl!(
+    <G>,
     CredentialAuthority,
-    E4,
-    E4,
+    E4<G>,
+    E4<G>,
     EmptyMessage,
-    E7M,
-    |s: CredentialAuthority<E4>, _: EmptyMessage| {
+    E7M<G>,
+    |s: CredentialAuthority<E4<G>, G>, _: EmptyMessage| {
         let L = s.state.L.clone();
         (s.state, E7M { L })
     }
 );
 
-pub struct E12 {
-    election: Election,
-    L: Vec<(Point, u128)>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E12<G: Group = Ristretto> {
+    election: Election<G>,
+    L: Vec<(Point<G>, u128)>,
+    commitments: Vec<Vec<u8>>,
 }
 
 process_message_impl!(
+    <G>,
     CredentialAuthority,
-    E4,
-    E12,
-    E11M,
+    E4<G>,
+    E12<G>,
+    E11M<G>,
     ErrorM,
-    |s: CredentialAuthority<E4>, m: E11M| {
+    |s: CredentialAuthority<E4<G>, G>, m: E11M<G>| {
         let election = m.election;
         let check = if m.L != s.state.L {
             // Voting Server posted wrong L
@@ -110,8 +152,59 @@ process_message_impl!(
         let state = E12 {
             election,
             L: s.state.L,
+            commitments: s.state.commitments,
         };
         let message = ErrorM { check };
         (state, message)
     }
 );
+
+// C2: generate a new credential for a voter whose identity the server administrator confirmed,
+// replacing their old public credential, which their recovery code lets us find in L.
+// The recovery code is used up: the voter gets a new one, which the new credential is committed
+// under, and whose hash replaces the old one at the server administrator.
+process_message_impl!(
+    <G>,
+    CredentialAuthority,
+    E12<G>,
+    E12<G>,
+    C2M,
+    C3M<G>,
+    |s: CredentialAuthority<E12<G>, G>, m: C2M| {
+        let mut state = s.state;
+        let recovery = m.request.and_then(|request| {
+            let uuid = &state.election.uuid;
+            let (voter, code) = (request.voter, &request.recovery_code);
+            let commitment = state
+                .commitments
+                .get(voter)
+                .ok_or(ProtocolError::UnknownVoterError)?;
+            let entry = state
+                .L
+                .iter_mut()
+                .find(|(pt, _)| commit_credential(uuid, voter, code, pt) == *commitment)
+                .ok_or(ProtocolError::RecoveryCodeError)?;
+            let expanded_cred = ExpandedCredential::<G>::gen(s.rng.clone(), uuid);
+            let old_credential = entry.0;
+            let new_credential = expanded_cred.public_key;
+            entry.0 = new_credential;
+            let recovery_code = Password::gen(s.rng.clone());
+            state.commitments[voter] =
+                commit_credential(uuid, voter, &recovery_code, &new_credential);
+            let verifier = C3M_CA_to_SA {
+                voter,
+                verifier: recovery_verifier(voter, &recovery_code),
+            };
+            let password = E4Mi {
+                password: expanded_cred.password,
+                recovery_code,
+            };
+            let update = C3M_CA_to_VS {
+                old_credential,
+                new_credential,
+            };
+            Ok((password, update, verifier))
+        });
+        (state, C3M { recovery })
+    }
+);
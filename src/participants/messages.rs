@@ -5,20 +5,27 @@
 
 use crate::datatypes::ballot::Ballot;
 use crate::datatypes::credentials::{Credential, Password, UUID};
-use crate::datatypes::election::Election;
+use crate::datatypes::election::{Election, FingerprintMode};
 use crate::datatypes::questions::Question;
+use crate::participants::server_admin::recovery_verifier;
 use crate::participants::trustee::TrusteePublicKey;
-use crate::primitives::group::{Point, Scalar};
+use crate::participants::voting_server::{check_ballot, RevotePolicy};
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::pki::{Ciphertext, EncryptedMessage};
+use crate::primitives::rng::SecureRandom;
+use crate::primitives::vss::PolynomialCommitment;
+use crate::primitives::zkp::Proof;
 use crate::ProtocolError;
-use ring::rand::SecureRandom;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
 /// Type used to raise an error when some cheating behavior is detected.
+#[derive(Serialize, Deserialize)]
 pub struct ErrorM {
     pub check: Result<(), ProtocolError>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct EmptyMessage;
 
 /// The initialization message for the Server administrator.
@@ -28,37 +35,57 @@ pub struct EmptyMessage;
 ///
 /// The number of voters is `voters.len()`.
 /// Voter i's weight (written `wi` in the spec) is `voters[i]`.
-#[derive(Builder, Clone)]
+#[derive(Builder, Clone, Serialize, Deserialize)]
 pub struct E1M {
     pub(crate) voters: Vec<u128>,
 }
 
-/// The UUID
+/// The UUID, and the name of the group of the election (e.g. for the credentials).
 ///
 /// FROM: Voting Server
 /// TO: Credential Authority
-#[derive(Builder)]
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E3M_VS_to_CA {
     pub(crate) uuid: UUID,
+    pub(crate) group: String,
+}
+
+impl E3M_VS_to_CA {
+    pub fn uuid(&self) -> &UUID {
+        &self.uuid
+    }
 }
 /// The list of weights of voters
 ///
 /// FROM: ServerAdmin
 /// TO: CredentialAuthority.
-#[derive(Builder, PartialEq)]
+#[derive(Builder, PartialEq, Serialize, Deserialize)]
 pub struct E3M_SA_to_CA {
     pub(crate) voters: Vec<u128>,
 }
+#[derive(Serialize, Deserialize)]
 pub struct E3M {
     pub(crate) uuid: UUID,
+    pub(crate) group: String,
     pub(crate) voters: Vec<u128>,
 }
 
+impl E3M {
+    pub fn uuid(&self) -> &UUID {
+        &self.uuid
+    }
+    /// The name of the group of the election, e.g. to pick the group of the participants.
+    pub fn group(&self) -> &str {
+        &self.group
+    }
+}
+
 /// Combining the two messages for step E3
 impl From<(E3M_VS_to_CA, E3M_SA_to_CA)> for E3M {
     fn from((message_VS, message_CA): (E3M_VS_to_CA, E3M_SA_to_CA)) -> Self {
         E3M {
             uuid: message_VS.uuid,
+            group: message_VS.group,
             voters: message_CA.voters,
         }
     }
@@ -72,31 +99,62 @@ impl From<(E3M_VS_to_CA, E3M_SA_to_CA)> for E3M {
 /// Each individual voter should only get their password,
 /// e.g. the Credential Authority should iterate over this, sending
 /// the i-th password to the i-th voter.
-#[derive(Builder)]
+/// Each voter also gets a recovery code, which they should store apart from their password, as
+/// it is how they prove who they are during credential recovery.
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E4M {
     pub(crate) passwords: Vec<Password>,
+    pub(crate) recovery_codes: Vec<Password>,
 }
 
-/// The password of the ith voter.
+/// The password and recovery code of the ith voter.
 ///
 /// FROM: CredentialAuthority,
 /// TO: (the i-th) Voter.
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct E4Mi {
     pub(crate) password: Password,
+    pub(crate) recovery_code: Password,
+}
+
+impl E4Mi {
+    pub fn recovery_code(&self) -> &Password {
+        &self.recovery_code
+    }
 }
 
 impl From<E4M> for Vec<E4Mi> {
     fn from(message: E4M) -> Self {
-        let vec_of_passes = message.passwords;
-        let mut output = Vec::new();
-        for i in 0..vec_of_passes.len() {
-            let individual_pass = E4Mi {
-                password: vec_of_passes[i].clone(),
-            };
-            output.push(individual_pass)
-        }
-        output
+        message
+            .passwords
+            .into_iter()
+            .zip(message.recovery_codes)
+            .map(|(password, recovery_code)| E4Mi {
+                password,
+                recovery_code,
+            })
+            .collect()
+    }
+}
+
+/// What the server administrator needs to check recovery codes, e.g. their hashes.
+///
+/// FROM: CredentialAuthority,
+/// TO: ServerAdmin.
+#[derive(Serialize, Deserialize)]
+pub struct E4M_CA_to_SA {
+    pub(crate) verifiers: Vec<Vec<u8>>,
+}
+
+impl From<&E4M> for E4M_CA_to_SA {
+    fn from(message: &E4M) -> Self {
+        let verifiers = message
+            .recovery_codes
+            .iter()
+            .enumerate()
+            .map(|(voter, code)| recovery_verifier(voter, code))
+            .collect();
+        E4M_CA_to_SA { verifiers }
     }
 }
 
@@ -104,28 +162,258 @@ impl From<E4M> for Vec<E4Mi> {
 ///
 /// FROM: CredentialAuthority,
 /// TO: VotingServer.
-pub struct E7M {
-    pub(crate) L: Vec<(Point, u128)>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E7M<G: Group = Ristretto> {
+    pub(crate) L: Vec<(Point<G>, u128)>,
 }
 
-pub struct E9Mi {
-    pub(crate) trustee_key: TrusteePublicKey,
+/// The UUID of the election, and the index of the trustee in E9M, which the proofs of the trustee
+/// are bound to.
+///
+/// FROM: VotingServer,
+/// TO: (the i-th) Trustee.
+#[derive(Builder, Clone, Serialize, Deserialize)]
+pub struct E8Mi {
+    pub(crate) uuid: UUID,
+    pub(crate) index: usize,
 }
-pub struct E9M {
-    pub(crate) trustee_keys: Vec<TrusteePublicKey>,
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9Mi<G: Group = Ristretto> {
+    pub(crate) trustee_key: TrusteePublicKey<G>,
+}
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9M<G: Group = Ristretto> {
+    pub(crate) trustee_keys: Vec<TrusteePublicKey<G>>,
 }
 
-impl From<Vec<E9Mi>> for E9M {
-    fn from(v: Vec<E9Mi>) -> Self {
+impl<G: Group> From<Vec<E9Mi<G>>> for E9M<G> {
+    fn from(v: Vec<E9Mi<G>>) -> Self {
         let mut trustee_keys = Vec::new();
-        for i in 0..v.len() {
-            trustee_keys.push(v[i].trustee_key.clone());
+        for key in v.into_iter() {
+            trustee_keys.push(key.trustee_key);
         }
         E9M { trustee_keys }
     }
 }
 
-#[derive(Builder)]
+/// The parameters of the threshold key-generation ceremony, for "pedersen" trustees.
+/// Trustees are indexed from 0, and the i-th trustee's shares are evaluations at i + 1.
+/// As for single trustees (see E8Mi), the proofs of the trustee are bound to the UUID of the
+/// election and its index.
+///
+/// The threshold must be between 1 and the number of trustees.
+///
+/// FROM: VotingServer,
+/// TO: (the i-th) PedersenTrustee.
+#[derive(Builder, Clone, Serialize, Deserialize)]
+#[builder(build_fn(validate = "Self::validate"))]
+pub struct P1M {
+    pub(crate) uuid: UUID,
+    pub(crate) index: usize,
+    pub(crate) threshold: usize,
+    pub(crate) num_trustees: usize,
+}
+
+impl P1MBuilder {
+    fn validate(&self) -> Result<(), String> {
+        match (self.threshold, self.num_trustees) {
+            (Some(t), Some(n)) if !valid_threshold(t, n) => Err(format!(
+                "The threshold {} is not between 1 and the number of trustees {}",
+                t, n
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether `threshold` of `num_trustees` "pedersen" trustees may decrypt, e.g. whether
+/// 0 < threshold <= num_trustees.
+pub(crate) fn valid_threshold(threshold: usize, num_trustees: usize) -> bool {
+    0 < threshold && threshold <= num_trustees
+}
+
+/// The key the shares dealt to the i-th trustee are encrypted to.
+///
+/// FROM: (the i-th) PedersenTrustee,
+/// TO: Everyone.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P1Mi<G: Group = Ristretto> {
+    pub(crate) encryption_key: Point<G>,
+}
+
+/// The encryption keys of all trustees, in order.
+///
+/// FROM: PedersenTrustees,
+/// TO: PedersenTrustees.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P1M_Keys<G: Group = Ristretto> {
+    pub(crate) encryption_keys: Vec<Point<G>>,
+}
+
+impl<G: Group> From<Vec<P1Mi<G>>> for P1M_Keys<G> {
+    fn from(keys: Vec<P1Mi<G>>) -> Self {
+        P1M_Keys {
+            encryption_keys: keys.into_iter().map(|k| k.encryption_key).collect(),
+        }
+    }
+}
+
+/// The dealing of the i-th trustee.
+/// The commitment is public, while `shares[j]` is encrypted to the key of the j-th trustee, so
+/// that whoever relays the dealings (e.g. the VotingServer) learns none of the shares.
+///
+/// FROM: (the i-th) PedersenTrustee,
+/// TO: VotingServer (commitment), (the j-th) PedersenTrustee (j-th share).
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P2Mi<G: Group = Ristretto> {
+    pub(crate) commitment: PolynomialCommitment<G>,
+    pub(crate) shares: Vec<EncryptedMessage<G>>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P2M<G: Group = Ristretto> {
+    pub(crate) dealings: Vec<P2Mi<G>>,
+}
+
+impl<G: Group> From<Vec<P2Mi<G>>> for P2M<G> {
+    fn from(dealings: Vec<P2Mi<G>>) -> Self {
+        P2M { dealings }
+    }
+}
+
+impl<G: Group> P2M<G> {
+    /// The public part of the dealings.
+    pub(crate) fn commitments(&self) -> Vec<PolynomialCommitment<G>> {
+        self.dealings.iter().map(|d| d.commitment.clone()).collect()
+    }
+}
+
+/// All commitments, along with the (encrypted) shares dealt to the j-th trustee, None if a
+/// dealer sent none.
+///
+/// FROM: VotingServer (commitments), PedersenTrustees (shares),
+/// TO: (the j-th) PedersenTrustee.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P3Mi<G: Group = Ristretto> {
+    pub(crate) commitments: Vec<PolynomialCommitment<G>>,
+    pub(crate) shares: Vec<Option<EncryptedMessage<G>>>,
+}
+
+impl<G: Group> From<P2M<G>> for Vec<P3Mi<G>> {
+    fn from(message: P2M<G>) -> Self {
+        let commitments = message.commitments();
+        let num_trustees = message.dealings.len();
+        (0..num_trustees)
+            .map(|j| P3Mi {
+                commitments: commitments.clone(),
+                shares: message
+                    .dealings
+                    .iter()
+                    .map(|d| d.shares.get(j).cloned())
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// The dealers whose share the i-th trustee could not verify.
+///
+/// FROM: (the i-th) PedersenTrustee,
+/// TO: Everyone.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct P4Mi {
+    pub(crate) complaints: Vec<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct P4M {
+    pub(crate) complaints: Vec<P4Mi>,
+}
+
+impl From<Vec<P4Mi>> for P4M {
+    fn from(complaints: Vec<P4Mi>) -> Self {
+        P4M { complaints }
+    }
+}
+
+/// The shares the i-th trustee reveals in response to complaints, as (complainer, share) pairs.
+///
+/// FROM: (the i-th) PedersenTrustee,
+/// TO: Everyone.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P5Mi<G: Group = Ristretto> {
+    pub(crate) revealed: Vec<(usize, Scalar<G>)>,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P5M<G: Group = Ristretto> {
+    pub(crate) responses: Vec<P5Mi<G>>,
+}
+
+impl<G: Group> From<Vec<P5Mi<G>>> for P5M<G> {
+    fn from(responses: Vec<P5Mi<G>>) -> Self {
+        P5M { responses }
+    }
+}
+
+/// The verification key the i-th "pedersen" trustee derived from the ceremony, or the reason it
+/// could not derive its key share.
+///
+/// FROM: (the i-th) PedersenTrustee,
+/// TO: VotingServer.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9Mi_Pedersen<G: Group = Ristretto> {
+    pub(crate) trustee_key: Result<TrusteePublicKey<G>, ProtocolError>,
+}
+
+/// The transcript of the threshold key-generation ceremony, along with the verification key each
+/// trustee derived from it (None if it could not), which replaces E9M for "pedersen" trustees.
+///
+/// FROM: PedersenTrustees,
+/// TO: VotingServer.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9M_Pedersen<G: Group = Ristretto> {
+    pub(crate) threshold: usize,
+    pub(crate) commitments: Vec<PolynomialCommitment<G>>,
+    pub(crate) complaints: P4M,
+    pub(crate) responses: P5M<G>,
+    pub(crate) trustee_keys: Vec<Option<TrusteePublicKey<G>>>,
+}
+
+impl<G: Group> From<(usize, P2M<G>, P4M, P5M<G>, Vec<E9Mi_Pedersen<G>>)> for E9M_Pedersen<G> {
+    fn from(
+        (threshold, dealings, complaints, responses, keys): (
+            usize,
+            P2M<G>,
+            P4M,
+            P5M<G>,
+            Vec<E9Mi_Pedersen<G>>,
+        ),
+    ) -> Self {
+        E9M_Pedersen {
+            threshold,
+            commitments: dealings.commitments(),
+            complaints,
+            responses,
+            trustee_keys: keys.into_iter().map(|k| k.trustee_key.ok()).collect(),
+        }
+    }
+}
+
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E10M {
     pub(crate) description: String,
     pub(crate) name: String,
@@ -133,35 +421,184 @@ pub struct E10M {
     pub(crate) questions: Vec<Question>,
     pub(crate) administrator: String,
     pub(crate) credential_authority: String,
+    // What the voting server does with a second ballot for the same credential.
+    #[builder(default)]
+    #[serde(default)]
+    pub(crate) revote_policy: RevotePolicy,
+    #[builder(default)]
+    #[serde(default)]
+    pub(crate) fingerprint_mode: FingerprintMode,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E11M<G: Group = Ristretto> {
+    pub(crate) election: Election<G>,
+    pub(crate) L: Vec<(Point<G>, u128)>,
 }
 
-#[derive(Clone)]
-pub struct E11M {
-    pub(crate) election: Election,
-    pub(crate) L: Vec<(Point, u128)>,
+impl<G: Group> E11M<G> {
+    /// Checks a ballot as the voting server would before accepting it, e.g. so that anybody may
+    /// check a ballot against the published election.
+    pub fn verify_ballot(&self, ballot: &V3Mi<G>) -> Result<(), ProtocolError> {
+        let weight = self
+            .L
+            .iter()
+            .find(|(pt, _)| *pt == ballot.vote.credential)
+            .map(|(_, wt)| *wt);
+        check_ballot(&self.election, weight, &ballot.vote).map(|_| ())
+    }
 }
 
 // The voting choices made in the election for each question.
+#[derive(Serialize, Deserialize)]
 pub struct V2Mi {
     pub choices: Vec<Vec<bool>>,
 }
 
-pub struct V3Mi {
-    pub(crate) vote: Ballot,
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct V3Mi<G: Group = Ristretto> {
+    pub(crate) vote: Ballot<G>,
 }
 
-impl From<Vec<V3Mi>> for V3M {
-    fn from(v: Vec<V3Mi>) -> Self {
+impl<G: Group> From<Vec<V3Mi<G>>> for V3M<G> {
+    fn from(v: Vec<V3Mi<G>>) -> Self {
         let votes = v.iter().map(|s| s.vote.clone()).collect();
         V3M { votes }
     }
 }
 
-pub struct V3M {
-    pub(crate) votes: Vec<Ballot>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct V3M<G: Group = Ristretto> {
+    pub(crate) votes: Vec<Ballot<G>>,
+}
+
+/// The encrypted tally, e.g. for each question and answer the sum of the (weighted) encrypted
+/// choices of every accepted ballot.
+///
+/// FROM: VotingServer,
+/// TO: Trustees.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T2M<G: Group = Ristretto> {
+    pub(crate) num_tallied: usize,
+    pub(crate) encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+}
+
+/// The partial decryption of the encrypted tally by the i-th trustee.
+/// For each question and answer with encrypted tally (alpha, beta), the decryption factor is
+/// `x * alpha`, where `x` is the trustee's private key, along with a proof that it was computed
+/// correctly.
+///
+/// FROM: (the i-th) Trustee,
+/// TO: VotingServer.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T3Mi<G: Group = Ristretto> {
+    pub(crate) decryption_factors: Vec<Vec<Point<G>>>,
+    pub(crate) decryption_proofs: Vec<Vec<Proof<G>>>,
+}
+
+/// The partial decryptions of all trustees, in the same order as their keys in step E9.
+/// A "pedersen" trustee that did not take part in the decryption is `None`.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T3M<G: Group = Ristretto> {
+    pub(crate) partial_decryptions: Vec<Option<T3Mi<G>>>,
+}
+
+impl<G: Group> From<Vec<T3Mi<G>>> for T3M<G> {
+    fn from(partial_decryptions: Vec<T3Mi<G>>) -> Self {
+        T3M {
+            partial_decryptions: partial_decryptions.into_iter().map(Some).collect(),
+        }
+    }
+}
+
+impl<G: Group> From<Vec<Option<T3Mi<G>>>> for T3M<G> {
+    fn from(partial_decryptions: Vec<Option<T3Mi<G>>>) -> Self {
+        T3M {
+            partial_decryptions,
+        }
+    }
+}
+
+/// The result of the election.
+/// `result[i][j]` is the (weighted) number of votes for the j-th answer of the i-th question.
+/// If the i-th question allows blank votes, `result[i][0]` is the (weighted) number of blank votes,
+/// and the answers start at `result[i][1]`.
+/// It comes with the encrypted tally and the (verified) partial decryptions it was decrypted
+/// from, so that it can be checked, see section 4.15.
+///
+/// FROM: VotingServer,
+/// TO: Everyone.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T7M<G: Group = Ristretto> {
+    pub num_tallied: usize,
+    pub encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+    pub partial_decryptions: Vec<Option<T3Mi<G>>>,
+    pub result: Result<Vec<Vec<u128>>, ProtocolError>,
+}
+
+/// A request for a new credential by the i-th voter, e.g. after they lost their password, along
+/// with their recovery code.
+///
+/// FROM: (the i-th) Voter,
+/// TO: ServerAdmin.
+#[derive(Builder, Clone, Serialize, Deserialize)]
+pub struct C1M {
+    pub(crate) voter: usize,
+    pub(crate) recovery_code: Password,
+}
+
+/// The request for a new credential, once the server administrator confirmed the identity of the
+/// voter, or the reason it was refused.
+///
+/// FROM: ServerAdmin,
+/// TO: CredentialAuthority.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct C2M {
+    pub(crate) request: Result<C1M, ProtocolError>,
+}
+
+/// The replacement of the public credential of a voter in L, which the voting server logs
+/// publicly.
+///
+/// FROM: CredentialAuthority,
+/// TO: VotingServer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct C3M_CA_to_VS<G: Group = Ristretto> {
+    pub(crate) old_credential: Point<G>,
+    pub(crate) new_credential: Point<G>,
+}
+
+/// The hash of the new recovery code of a voter after a credential recovery, which replaces the
+/// one of the code they used, so that no recovery code is used twice.
+///
+/// FROM: CredentialAuthority,
+/// TO: ServerAdmin.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct C3M_CA_to_SA {
+    pub(crate) voter: usize,
+    pub(crate) verifier: Vec<u8>,
+}
+
+/// The outcome of a credential recovery, e.g. the new password and recovery code of the voter,
+/// along with the replacement of their public credential, and of the hash of their recovery code.
+///
+/// FROM: CredentialAuthority,
+/// TO: (the i-th) Voter (password), VotingServer (replacement), ServerAdmin (hash).
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct C3M<G: Group = Ristretto> {
+    pub(crate) recovery: Result<(E4Mi, C3M_CA_to_VS<G>, C3M_CA_to_SA), ProtocolError>,
 }
 
-/// The result of the Voting Server's check
+// The result of the Voting Server's check
 
 /// The Election Setup phase is divided into twelve steps, described in section 3.1.
 struct E1;
@@ -196,3 +633,186 @@ struct T4;
 struct T5;
 struct T6;
 struct T7;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::questions::QuestionBuilder;
+    use crate::participants::credential_authority::CredentialAuthority;
+    use crate::participants::participant_template::Participant;
+    use crate::participants::pedersen_trustee::PedersenTrustee;
+    use crate::participants::server_admin::ServerAdmin;
+    use crate::participants::trustee::Trustee;
+    use crate::participants::voter::Voter;
+    use crate::participants::voting_server::VotingServer;
+    use ring::rand::SystemRandom;
+    use serde::de::DeserializeOwned;
+
+    /// Checks that a message survives being sent as both bincode and JSON, by comparing the
+    /// bincode encodings of the decoded messages.
+    fn assert_round_trips<T: Serialize + DeserializeOwned>(message: &T) -> T {
+        let encoded = bincode::serialize(message).unwrap();
+        let from_bincode: T = bincode::deserialize(&encoded).unwrap();
+        assert_eq!(bincode::serialize(&from_bincode).unwrap(), encoded);
+        let json = serde_json::to_string(message).unwrap();
+        let from_json: T = serde_json::from_str(&json).unwrap();
+        assert_eq!(bincode::serialize(&from_json).unwrap(), encoded);
+        from_json
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let election = E1MBuilder::default().voters(vec![1, 2]).build().unwrap();
+        assert_round_trips(&election);
+        let (_, message_SA) = ServerAdmin::new(rng.clone()).process_message(election.clone());
+        let (voting_server, message_VS) = VotingServer::new(rng.clone()).process_message(election);
+        let message_SA = assert_round_trips(&message_SA);
+        let message_VS = assert_round_trips(&message_VS);
+        let uuid = message_VS.uuid().clone();
+        let message = assert_round_trips(&E3M::from((message_VS, message_SA)));
+        let (credential_authority, passwords) =
+            CredentialAuthority::new(rng.clone()).process_message(message);
+        let passwords: Vec<E4Mi> = assert_round_trips(&passwords).into();
+        let voters: Vec<_> = passwords
+            .iter()
+            .map(|pass| {
+                let pass = assert_round_trips(pass);
+                Voter::new(rng.clone()).process_message(pass).0
+            })
+            .collect();
+        let (credential_authority, message) = credential_authority.process_message(EmptyMessage);
+        let message: E7M = assert_round_trips(&message);
+        let (voting_server, message) = voting_server.process_message(message);
+        assert_round_trips(&message).check.unwrap();
+        let context = E8MiBuilder::default().uuid(uuid).index(0).build().unwrap();
+        let (trustee, trustee_key) =
+            Trustee::new(rng.clone()).process_message(assert_round_trips(&context));
+        let trustee_key = assert_round_trips(&trustee_key);
+        let message = assert_round_trips(&E9M::from(vec![trustee_key]));
+        let (voting_server, message) = voting_server.process_message(message);
+        assert_round_trips(&message).check.unwrap();
+        let question = QuestionBuilder::default()
+            .question("Serialized?")
+            .answers(vec!["Yes", "No"])
+            .blank(true)
+            .build()
+            .unwrap();
+        let message = E10MBuilder::default()
+            .questions(vec![question])
+            .version(1)
+            .description("Sample".to_string())
+            .name("Sample".to_string())
+            .administrator("Sample".to_string())
+            .credential_authority("Sample".to_string())
+            .build()
+            .unwrap();
+        let (voting_server, election_message) =
+            voting_server.process_message(assert_round_trips(&message));
+        let election_message = assert_round_trips(&election_message);
+        let (_, message) = credential_authority.process_message(election_message.clone());
+        message.check.unwrap();
+        let (mut voting_server, _) = voting_server.process_message(EmptyMessage);
+        for (voter, choice) in voters
+            .into_iter()
+            .zip([vec![true, false], vec![false, false]])
+        {
+            let voter = voter.process_message(election_message.clone()).0;
+            let choices = assert_round_trips(&V2Mi {
+                choices: vec![choice],
+            });
+            let (_, ballot) = voter.process_message(choices);
+            let ballot = assert_round_trips(&ballot);
+            assert!(ballot.vote.verify_signature());
+            let (new_server, message) = voting_server.process_message(ballot);
+            assert_round_trips(&message).check.unwrap();
+            voting_server = new_server;
+        }
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let encrypted_tally = assert_round_trips(&encrypted_tally);
+        let (_, partial_decryption) = trustee.process_message(encrypted_tally);
+        let partial_decryption = assert_round_trips(&partial_decryption);
+        let message = assert_round_trips(&T3M::from(vec![partial_decryption]));
+        let (voting_server, message) = voting_server.process_message(message);
+        message.check.unwrap();
+        let (_, result) = voting_server.process_message(EmptyMessage);
+        let result = assert_round_trips(&result);
+        assert_eq!(result.result.unwrap(), vec![vec![2, 1, 0]]);
+        // Errors are sent along as well.
+        let error = ErrorM {
+            check: Err(ProtocolError::PartialDecryptionProofFailedError(vec![1, 2])),
+        };
+        match assert_round_trips(&error).check {
+            Err(ProtocolError::PartialDecryptionProofFailedError(cheaters)) => {
+                assert_eq!(cheaters, vec![1, 2])
+            }
+            _ => panic!("The error was not preserved"),
+        }
+    }
+
+    #[test]
+    fn test_pedersen_parameters() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let uuid = UUID::gen(rng);
+        for (threshold, num_trustees, valid) in
+            [(0, 3, false), (1, 3, true), (3, 3, true), (4, 3, false)]
+        {
+            let params = P1MBuilder::default()
+                .uuid(uuid.clone())
+                .index(0)
+                .threshold(threshold)
+                .num_trustees(num_trustees)
+                .build();
+            assert_eq!(params.is_ok(), valid);
+        }
+    }
+
+    #[test]
+    fn test_pedersen_messages_round_trip() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (threshold, num_trustees) = (2, 3);
+        let (voting_server, message_VS) = VotingServer::new(rng.clone())
+            .process_message(E1MBuilder::default().voters(vec![1]).build().unwrap());
+        let (trustees, keys): (Vec<_>, Vec<P1Mi>) = (0..num_trustees)
+            .map(|index| {
+                let params = P1MBuilder::default()
+                    .uuid(message_VS.uuid().clone())
+                    .index(index)
+                    .threshold(threshold)
+                    .num_trustees(num_trustees)
+                    .build()
+                    .unwrap();
+                PedersenTrustee::new(rng.clone()).process_message(assert_round_trips(&params))
+            })
+            .unzip();
+        let keys = assert_round_trips(&P1M_Keys::from(keys));
+        let (trustees, dealings): (Vec<_>, Vec<P2Mi>) = trustees
+            .into_iter()
+            .map(|t| t.process_message(keys.clone()))
+            .unzip();
+        let dealings = assert_round_trips(&P2M::from(dealings));
+        let shares: Vec<P3Mi> = dealings.clone().into();
+        let (trustees, complaints): (Vec<_>, Vec<P4Mi>) = trustees
+            .into_iter()
+            .zip(shares.iter())
+            .map(|(t, m)| t.process_message(assert_round_trips(m)))
+            .unzip();
+        let complaints = assert_round_trips(&P4M::from(complaints));
+        let (trustees, responses): (Vec<_>, Vec<P5Mi>) = trustees
+            .into_iter()
+            .map(|t| t.process_message(complaints.clone()))
+            .unzip();
+        let responses = assert_round_trips(&P5M::from(responses));
+        let keys: Vec<E9Mi_Pedersen> = trustees
+            .into_iter()
+            .map(|t| t.process_message(responses.clone()).1)
+            .collect();
+        let message = E9M_Pedersen::from((threshold, dealings, complaints, responses, keys));
+        // Only the voting server's check of the (decoded) ceremony matters here.
+        let (_, message) = voting_server
+            .process_message(E7M { L: vec![] })
+            .0
+            .process_message(assert_round_trips(&message));
+        message.check.unwrap();
+    }
+}
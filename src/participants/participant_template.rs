@@ -6,9 +6,9 @@
 //! * VS: the Voting Server.
 //!
 //! The only roles that are not uniquely specified by the above are the Trustees and the Voters, as
-//! multiple participants may hold each of these roles. We assume (for simplicity) that there is
-//! only a single Trustee, e.g. we implement the `Single` version of the protocol (see section
-//! 3.1.1 of the specification).
+//! multiple participants may hold each of these roles. Trustees come in two kinds, namely the
+//! `Single` version of the protocol (see section 3.1.1 of the specification), implemented by
+//! `Trustee`, and the threshold version, implemented by `PedersenTrustee`.
 //!
 //! As mentioned in the `messages.rs` file, we implement the protocol using the State Machine
 //! pattern.
@@ -16,14 +16,18 @@
 //!
 //! ```rust
 //! # use std::sync::{Arc, Mutex};
-//! # use ring::rand::SecureRandom;
-//! struct Role<T> {
+//! # use belenios::primitives::rng::SecureRandom;
+//! struct Role<T, G> {
 //!     state: T,
 //!     rng: Arc<Mutex<dyn SecureRandom>>,
+//!     group: std::marker::PhantomData<G>,
 //! }
 //! ```
+//!
+//! where G is the group the election runs in, which defaults to `Ristretto`.
+//! Participants may be saved to disk and resumed at the same step, see `snapshot.rs`.
 
-use ring::rand::SecureRandom;
+use crate::primitives::rng::SecureRandom;
 use std::sync::{Arc, Mutex};
 
 /// Implements the aformentioned abstract form for each role uniformly.
@@ -31,19 +35,59 @@ use std::sync::{Arc, Mutex};
 pub struct EmptyState;
 macro_rules! initialize_participant_impl {
     ($Role: ident) => {
-        pub struct $Role<T> {
+        pub struct $Role<
+            T,
+            G: $crate::primitives::group::Group = $crate::primitives::group::Ristretto,
+        > {
             state: T,
             rng: Arc<Mutex<dyn SecureRandom>>,
+            group: std::marker::PhantomData<G>,
         }
 
         impl $Role<EmptyState> {
             pub fn new(rng: Arc<Mutex<dyn SecureRandom>>) -> $Role<EmptyState> {
-                $Role::<EmptyState> {
+                $Role::with_group(rng)
+            }
+        }
+
+        impl<G: $crate::primitives::group::Group> $Role<EmptyState, G> {
+            /// A participant of an election in the group G.
+            pub fn with_group(rng: Arc<Mutex<dyn SecureRandom>>) -> $Role<EmptyState, G> {
+                $Role::<EmptyState, G> {
                     state: EmptyState,
                     rng,
+                    group: std::marker::PhantomData,
                 }
             }
         }
+
+        impl<
+                T: $crate::participants::snapshot::PersistentState,
+                G: $crate::primitives::group::Group,
+            > $Role<T, G>
+        {
+            /// Takes a snapshot of the participant, encrypting its state under `key` if it holds
+            /// secrets.
+            pub fn snapshot(
+                &self,
+                key: &$crate::datatypes::credentials::Password,
+            ) -> $crate::participants::snapshot::Snapshot<G> {
+                $crate::participants::snapshot::Snapshot::new(self.rng.clone(), &self.state, key)
+            }
+            /// Resumes a participant from a snapshot taken at the same step.
+            pub fn resume(
+                rng: Arc<Mutex<dyn SecureRandom>>,
+                snapshot: &$crate::participants::snapshot::Snapshot<G>,
+                key: &$crate::datatypes::credentials::Password,
+            ) -> Result<$Role<T, G>, $crate::ProtocolError> {
+                let state = snapshot.state(key)?;
+                Ok($Role::<T, G> {
+                    state,
+                    rng,
+                    group: std::marker::PhantomData,
+                })
+            }
+        }
     };
 }
 pub(crate) use initialize_participant_impl;
@@ -68,14 +112,30 @@ pub trait Participant<M1, M2, S2> {
 /// * M1 some message for R to process at that timestep,
 /// * M2 some message for R to produce at that timestep,
 /// * f some closure from (State, Message) -> (State, Message) pairs which encodes
-///     how R will act at that timestep.
+///   how R will act at that timestep.
+///
+/// Transitions that involve group elements are generic over the group, which is named by a
+/// leading `<G>`, e.g. `process_message_impl!(<G>, Role, S1<G>, S2<G>, M1<G>, M2<G>, f)`.
 macro_rules! process_message_impl {
     ($Role: ident, $S1:ty, $S2:ty, $M1:ty, $M2:ty, $f:expr) => {
         impl Participant<$M1, $M2, $Role<$S2>> for $Role<$S1> {
             fn process_message(self, message: $M1) -> ($Role<$S2>, $M2) {
                 let rng = self.rng.clone();
                 let (state, message) = $f(self, message);
-                ($Role::<$S2> { state, rng }, message)
+                let group = std::marker::PhantomData;
+                ($Role::<$S2> { state, rng, group }, message)
+            }
+        }
+    };
+    (<$G: ident>, $Role: ident, $S1:ty, $S2:ty, $M1:ty, $M2:ty, $f:expr) => {
+        impl<$G: $crate::primitives::group::Group> Participant<$M1, $M2, $Role<$S2, $G>>
+            for $Role<$S1, $G>
+        {
+            fn process_message(self, message: $M1) -> ($Role<$S2, $G>, $M2) {
+                let rng = self.rng.clone();
+                let (state, message) = $f(self, message);
+                let group = std::marker::PhantomData;
+                ($Role::<$S2, $G> { state, rng, group }, message)
             }
         }
     };
@@ -0,0 +1,335 @@
+//! The "pedersen" Trustee(s), which have a threshold-type behavior: any `threshold` of the
+//! `num_trustees` trustees can decrypt the tally, see section 3.1.2 of the specification.
+//!
+//! Rather than each generating an independent key (as single trustees do in step E9), the
+//! trustees run a distributed key-generation ceremony based on verifiable secret sharing (see
+//! `primitives/vss.rs`), which we split into the following steps.
+//!
+//! P1: each trustee receives the UUID of the election, its index and the parameters of the
+//!     ceremony, and publishes a fresh encryption key.
+//! P2: each trustee receives the encryption keys of all trustees, deals shares of a random
+//!     polynomial, encrypting the j-th share to the j-th trustee, and publishes commitments to
+//!     the polynomial.
+//! P3: each trustee receives its encrypted shares, and checks them against the commitments.
+//! P4: each trustee publishes complaints against the dealers whose shares did not check out.
+//! P5: each dealer answers the complaints against it by revealing the disputed shares.
+//! Finally (in step E9) each trustee derives its key share from the shares of the qualified
+//! dealers, and publishes the corresponding verification key, or fails if the complaints relayed
+//! to it are not its own.
+
+use crate::datatypes::credentials::{Password, UUID};
+use crate::participants::messages::*;
+use crate::participants::participant_template::*;
+use crate::participants::snapshot::persistent_state_impl;
+use crate::participants::trustee::{partially_decrypt, TrusteePublicKey};
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::pki::{EncryptionKey, EncryptionKeys, VerificationKey};
+use crate::primitives::rng::SecureRandom;
+use crate::primitives::vss::{qualified_dealers, Polynomial, PolynomialCommitment};
+use crate::primitives::zkp::{DLog, ProofContext, ProofSystem};
+use crate::ProtocolError;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+initialize_participant_impl!(PedersenTrustee);
+persistent_state_impl!(
+    PedersenTrustee,
+    true,
+    P1<G>,
+    P2<G>,
+    P4<G>,
+    P5<G>,
+    E9<G>,
+    T3<G>
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P1<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    threshold: usize,
+    num_trustees: usize,
+    // The secret the encryption keys of this trustee are derived from.
+    secret: Password,
+    group: PhantomData<G>,
+}
+
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    EmptyState,
+    P1<G>,
+    P1M,
+    P1Mi<G>,
+    |s: PedersenTrustee<EmptyState, G>, m: P1M| {
+        let secret = Password::gen(s.rng.clone());
+        let keys = EncryptionKeys::<G>::from(&secret);
+        let state = P1 {
+            uuid: m.uuid,
+            index: m.index,
+            threshold: m.threshold,
+            num_trustees: m.num_trustees,
+            secret,
+            group: PhantomData,
+        };
+        let encryption_key = keys.public.into();
+        (state, P1Mi { encryption_key })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P2<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    threshold: usize,
+    secret: Password,
+    polynomial: Polynomial<G>,
+}
+
+// P2: deal the shares, the j-th of which only the j-th trustee can decrypt.
+// A trustee without an encryption key gets no share, and will complain about it.
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    P1<G>,
+    P2<G>,
+    P1M_Keys<G>,
+    P2Mi<G>,
+    |s: PedersenTrustee<P1<G>, G>, m: P1M_Keys<G>| {
+        let polynomial = Polynomial::sample(s.rng.clone(), s.state.threshold);
+        let context = ProofContext::new(&s.state.uuid, Some(s.state.index));
+        let commitment = polynomial.commit(s.rng.clone(), &context);
+        let shares = m
+            .encryption_keys
+            .iter()
+            .take(s.state.num_trustees)
+            .enumerate()
+            .map(|(j, key)| {
+                let share = polynomial.eval(j as u128 + 1);
+                let key = EncryptionKey::from(*key);
+                EncryptionKeys::encrypt(s.rng.clone(), &key, share.as_bytes())
+            })
+            .collect();
+        let state = P2 {
+            uuid: s.state.uuid,
+            index: s.state.index,
+            threshold: s.state.threshold,
+            secret: s.state.secret,
+            polynomial,
+        };
+        (state, P2Mi { commitment, shares })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P4<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    threshold: usize,
+    polynomial: Polynomial<G>,
+    commitments: Vec<PolynomialCommitment<G>>,
+    // The shares dealt to this trustee, None if they failed to verify.
+    shares: Vec<Option<Scalar<G>>>,
+}
+
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    P2<G>,
+    P4<G>,
+    P3Mi<G>,
+    P4Mi,
+    |s: PedersenTrustee<P2<G>, G>, m: P3Mi<G>| {
+        let x = s.state.index as u128 + 1;
+        let keys = EncryptionKeys::<G>::from(&s.state.secret);
+        let mut shares = Vec::with_capacity(m.commitments.len());
+        let mut complaints = Vec::new();
+        for (i, commitment) in m.commitments.iter().enumerate() {
+            let share = m
+                .shares
+                .get(i)
+                .and_then(|share| share.as_ref())
+                .and_then(|share| keys.decrypt(share).ok())
+                .and_then(|bytes| Scalar::from_canonical_bytes(&bytes));
+            match share {
+                Some(share) if commitment.verify_share(x, share) => shares.push(Some(share)),
+                _ => {
+                    shares.push(None);
+                    complaints.push(i);
+                }
+            }
+        }
+        let state = P4 {
+            uuid: s.state.uuid,
+            index: s.state.index,
+            threshold: s.state.threshold,
+            polynomial: s.state.polynomial,
+            commitments: m.commitments,
+            shares,
+        };
+        (state, P4Mi { complaints })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct P5<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    threshold: usize,
+    commitments: Vec<PolynomialCommitment<G>>,
+    shares: Vec<Option<Scalar<G>>>,
+    complaints: P4M,
+}
+
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    P4<G>,
+    P5<G>,
+    P4M,
+    P5Mi<G>,
+    |s: PedersenTrustee<P4<G>, G>, m: P4M| {
+        let index = s.state.index;
+        let revealed = m
+            .complaints
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.complaints.contains(&index))
+            .map(|(j, _)| (j, s.state.polynomial.eval(j as u128 + 1)))
+            .collect();
+        let state = P5 {
+            uuid: s.state.uuid,
+            index,
+            threshold: s.state.threshold,
+            commitments: s.state.commitments,
+            shares: s.state.shares,
+            complaints: m,
+        };
+        (state, P5Mi { revealed })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    key_share: Scalar<G>,
+    verification_key: Point<G>,
+}
+
+// E9: derive our key share from the shares of the qualified dealers.
+// If the complaints relayed to us are not ours, or a qualified dealer gave us no valid share (e.g.
+// as our complaint against it was dropped), we cannot, and the election should be set up again.
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    P5<G>,
+    E9<G>,
+    P5M<G>,
+    E9Mi_Pedersen<G>,
+    |s: PedersenTrustee<P5<G>, G>, m: P5M<G>| {
+        let index = s.state.index;
+        let x = index as u128 + 1;
+        // The complaints relayed to us should include ours, e.g. against the dealers whose share
+        // we could not verify.
+        let own_complaints: Vec<usize> = (0..s.state.shares.len())
+            .filter(|i| s.state.shares[*i].is_none())
+            .collect();
+        let mut check = match s.state.complaints.complaints.get(index) {
+            Some(c) if c.complaints == own_complaints => Ok(()),
+            _ => Err(ProtocolError::KeyGenerationTranscriptError),
+        };
+        let complaints: Vec<Vec<usize>> = s
+            .state
+            .complaints
+            .complaints
+            .iter()
+            .map(|c| c.complaints.clone())
+            .collect();
+        let responses: Vec<Vec<(usize, Scalar<G>)>> =
+            m.responses.iter().map(|r| r.revealed.clone()).collect();
+        let qualified = qualified_dealers(
+            &s.state.uuid,
+            s.state.threshold,
+            &s.state.commitments,
+            &complaints,
+            &responses,
+        );
+        let mut key_share = Scalar::zero();
+        for i in qualified {
+            // A qualified dealer either sent a valid share, or revealed one after we complained.
+            let share = s.state.shares.get(i).copied().flatten().or_else(|| {
+                responses
+                    .get(i)?
+                    .iter()
+                    .find(|(j, share)| {
+                        *j == index && s.state.commitments[i].verify_share(x, *share)
+                    })
+                    .map(|(_, share)| *share)
+            });
+            match share {
+                Some(share) => key_share = key_share + share,
+                None => check = Err(ProtocolError::KeyGenerationTranscriptError),
+            }
+        }
+        let verification_key = key_share * Point::generator();
+        let instance = DLog {
+            pt: verification_key,
+        };
+        let context = ProofContext::new(&s.state.uuid, Some(index));
+        let proof = instance.prove(s.rng.clone(), &context, &key_share);
+        let trustee_key = check.map(|_| TrusteePublicKey {
+            public_key: VerificationKey(verification_key),
+            proof,
+        });
+        let state = E9 {
+            uuid: s.state.uuid,
+            index,
+            key_share,
+            verification_key,
+        };
+        (state, E9Mi_Pedersen { trustee_key })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T3<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    key_share: Scalar<G>,
+    verification_key: Point<G>,
+}
+
+// T3: partially decrypt the encrypted tally with our key share.
+process_message_impl!(
+    <G>,
+    PedersenTrustee,
+    E9<G>,
+    T3<G>,
+    T2M<G>,
+    T3Mi<G>,
+    |s: PedersenTrustee<E9<G>, G>, m: T2M<G>| {
+        let context = ProofContext::new(&s.state.uuid, Some(s.state.index));
+        let message = partially_decrypt(
+            s.rng.clone(),
+            &context,
+            s.state.key_share,
+            s.state.verification_key,
+            &m.encrypted_tally,
+        );
+        let state = T3 {
+            uuid: s.state.uuid,
+            index: s.state.index,
+            key_share: s.state.key_share,
+            verification_key: s.state.verification_key,
+        };
+        (state, message)
+    }
+);
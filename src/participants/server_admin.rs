@@ -2,12 +2,18 @@
 
 use std::sync::{Arc, Mutex};
 
+use crate::datatypes::credentials::Password;
 use crate::datatypes::questions::Question;
 use crate::participants::messages::*;
 use crate::participants::participant_template::*;
-use ring::rand::SecureRandom;
+use crate::participants::snapshot::persistent_state_impl;
+use crate::primitives::rng::SecureRandom;
+use crate::ProtocolError;
+use ring::digest::{digest, SHA256};
+use serde::{Deserialize, Serialize};
 
 initialize_participant_impl!(ServerAdmin);
+persistent_state_impl!(ServerAdmin, false, E2);
 
 process_message_impl!(
     ServerAdmin,
@@ -29,13 +35,79 @@ process_message_impl!(
 );
 
 /// The state of the ServerAdmin at the end of step E1/beginning of step E2.
-#[derive(Builder)]
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E2 {
     voters: Vec<u128>,
+    // The hash of the recovery code of the i-th voter, see `recovery_verifier`.
+    #[builder(default)]
+    verifiers: Vec<Vec<u8>>,
 }
 
-#[cfg(test)]
+/// The hash of the recovery code of a voter, which lets the server administrator check it
+/// without being able to recover a credential by itself.
+pub(crate) fn recovery_verifier(voter: usize, recovery_code: &Password) -> Vec<u8> {
+    let data = bincode::serialize(&("recovery code", voter, recovery_code)).unwrap();
+    digest(&SHA256, &data).as_ref().into()
+}
+
+process_message_impl!(
+    ServerAdmin,
+    E2,
+    E2,
+    E4M_CA_to_SA,
+    ErrorM,
+    |s: ServerAdmin<E2>, m: E4M_CA_to_SA| {
+        let mut state = s.state;
+        let check = if m.verifiers.len() == state.voters.len() {
+            state.verifiers = m.verifiers;
+            Ok(())
+        } else {
+            Err(ProtocolError::IncorrectLenError)
+        };
+        (state, ErrorM { check })
+    }
+);
+
+// C1: confirm the identity of a voter requesting a new credential, by their recovery code, before
+// forwarding the request to the credential authority.
+process_message_impl!(
+    ServerAdmin,
+    E2,
+    E2,
+    C1M,
+    C2M,
+    |s: ServerAdmin<E2>, m: C1M| {
+        let request = match s.state.verifiers.get(m.voter) {
+            _ if m.voter >= s.state.voters.len() => Err(ProtocolError::UnknownVoterError),
+            Some(verifier) if *verifier == recovery_verifier(m.voter, &m.recovery_code) => Ok(m),
+            _ => Err(ProtocolError::RecoveryCodeError),
+        };
+        (s.state, C2M { request })
+    }
+);
+
+// C3: replace the hash of the recovery code of a voter once it was used, so that the request
+// cannot be replayed.
+process_message_impl!(
+    ServerAdmin,
+    E2,
+    E2,
+    C3M_CA_to_SA,
+    ErrorM,
+    |s: ServerAdmin<E2>, m: C3M_CA_to_SA| {
+        let mut state = s.state;
+        let check = match state.verifiers.get_mut(m.voter) {
+            Some(verifier) => {
+                *verifier = m.verifier;
+                Ok(())
+            }
+            None => Err(ProtocolError::UnknownVoterError),
+        };
+        (state, ErrorM { check })
+    }
+);
 
+#[cfg(test)]
 mod tests {
     use ring::rand::SystemRandom;
 
@@ -0,0 +1,228 @@
+//! Snapshots of the participants, so that an election survives restarts of the processes running
+//! it.
+//!
+//! As a participant is generic over its state, resuming one requires knowing the type of its
+//! state beforehand. A snapshot therefore records the role and step it was taken at (along with
+//! the group and the version of its encoding), and resuming it into a participant at any other
+//! step fails with `ProtocolError::SnapshotPhaseError`.
+//!
+//! States which hold secrets, e.g. the `TrusteeKeys`, key shares or a voter's password, are
+//! encrypted under a `Password` kept by whoever runs the participant. Other states are public
+//! anyway, so are stored in the clear and may be inspected by auditors.
+
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::primitives::rng::SecureRandom;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::credentials::Password;
+use crate::primitives::group::{Group, Ristretto};
+use crate::primitives::pki::{EncryptedMessage, EncryptionKeys};
+use crate::ProtocolError;
+
+/// The version of the encoding of snapshots, bumped whenever some state changes shape.
+pub const SNAPSHOT_VERSION: u32 = 5;
+
+/// The state of some role at some step of the protocol.
+pub trait PersistentState: Serialize + DeserializeOwned {
+    /// The role and step, e.g. `"VotingServer/V4"`.
+    const PHASE: &'static str;
+    /// Whether the state holds secrets, in which case it is encrypted at rest.
+    const SECRET: bool;
+}
+
+/// Implements `PersistentState` for the given states of a role, which are all secret or all
+/// public.
+/// States that are generic over the group are written with their parameter, e.g.
+/// `persistent_state_impl!(VotingServer, false, E3, E8<G>)`.
+macro_rules! persistent_state_impl {
+    ($Role:ident, $secret:literal, $($S:ident $(<$G:ident>)?),+) => {
+        $(
+            impl$(<$G: $crate::primitives::group::Group>)?
+                $crate::participants::snapshot::PersistentState for $S$(<$G>)?
+            {
+                const PHASE: &'static str = concat!(stringify!($Role), "/", stringify!($S));
+                const SECRET: bool = $secret;
+            }
+        )+
+    };
+}
+pub(crate) use persistent_state_impl;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+enum SnapshotData<G: Group> {
+    Public(Vec<u8>),
+    Secret(EncryptedMessage<G>),
+}
+
+/// A versioned snapshot of the state of a participant, see `Role::snapshot` and `Role::resume`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Snapshot<G: Group = Ristretto> {
+    version: u32,
+    phase: String,
+    group: String,
+    data: SnapshotData<G>,
+}
+
+impl<G: Group> Snapshot<G> {
+    pub(crate) fn new<S: PersistentState>(
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        state: &S,
+        key: &Password,
+    ) -> Self {
+        let bytes = bincode::serialize(state).unwrap();
+        let data = if S::SECRET {
+            let keys = EncryptionKeys::<G>::from(key);
+            SnapshotData::Secret(EncryptionKeys::encrypt(rng, &keys.public, bytes))
+        } else {
+            SnapshotData::Public(bytes)
+        };
+        Snapshot {
+            version: SNAPSHOT_VERSION,
+            phase: S::PHASE.to_string(),
+            group: G::NAME.to_string(),
+            data,
+        }
+    }
+    /// Recovers the state, checking the snapshot was taken at the step S.
+    pub(crate) fn state<S: PersistentState>(&self, key: &Password) -> Result<S, ProtocolError> {
+        if self.version != SNAPSHOT_VERSION {
+            return Err(ProtocolError::SnapshotVersionError);
+        }
+        if self.phase != S::PHASE || self.group != G::NAME {
+            return Err(ProtocolError::SnapshotPhaseError);
+        }
+        let bytes = match (&self.data, S::SECRET) {
+            (SnapshotData::Public(bytes), false) => bytes.clone(),
+            (SnapshotData::Secret(c), true) => EncryptionKeys::<G>::from(key).decrypt(c)?,
+            // A secret state was stored in the clear, or the converse.
+            _ => return Err(ProtocolError::MalformedSnapshotError),
+        };
+        bincode::deserialize(&bytes).map_err(|_| ProtocolError::MalformedSnapshotError)
+    }
+    /// The role and step the snapshot was taken at, e.g. `"Trustee/E9"`.
+    pub fn phase(&self) -> &str {
+        &self.phase
+    }
+    pub fn as_bytes(&self) -> Vec<u8> {
+        bincode::serialize(self).unwrap()
+    }
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ProtocolError> {
+        bincode::deserialize(bytes).map_err(|_| ProtocolError::MalformedSnapshotError)
+    }
+    /// Writes the snapshot to `path`.
+    /// The snapshot is first written next to it, then renamed over it, so that a crash while
+    /// saving leaves the previous snapshot intact.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, self.as_bytes())?;
+        std::fs::rename(&tmp, path)
+    }
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = std::fs::read(path)?;
+        Self::from_bytes(&bytes)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e)))
+    }
+}
+
+/// The name of the group of a snapshot, read without decoding the rest of it (which depends on
+/// the group), e.g. to pick the group to resume it in.
+pub fn snapshot_group(bytes: &[u8]) -> Result<String, ProtocolError> {
+    #[derive(Deserialize)]
+    struct Header {
+        _version: u32,
+        _phase: String,
+        group: String,
+    }
+    let header: Header =
+        bincode::deserialize(bytes).map_err(|_| ProtocolError::MalformedSnapshotError)?;
+    Ok(header.group)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::participants::messages::*;
+    use crate::participants::participant_template::Participant;
+    use crate::participants::trustee::{self, Trustee};
+    use crate::participants::voter::{self, Voter};
+    use crate::participants::voting_server::tests::setup_election;
+    use crate::participants::voting_server::{self, VotingServer};
+    use ring::rand::SystemRandom;
+
+    #[test]
+    fn test_resume_mid_election() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let key = Password::gen(rng.clone());
+        let (voting_server, voters, trustees) = setup_election(vec![1, 2], 2);
+        let mut voters = voters.into_iter();
+        let (_, ballot) = voters.next().unwrap().process_message(V2Mi {
+            choices: vec![vec![true, false, false]],
+        });
+        let (voting_server, message) = voting_server.process_message(ballot);
+        message.check.unwrap();
+
+        // Every participant goes down, and is resumed from its snapshot on disk.
+        let path = std::env::temp_dir().join(format!("belenios-{}.snapshot", std::process::id()));
+        voting_server.snapshot(&key).save(&path).unwrap();
+        let snapshot = Snapshot::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(snapshot.phase(), "VotingServer/V4");
+        let voting_server =
+            VotingServer::<voting_server::V4>::resume(rng.clone(), &snapshot, &key).unwrap();
+        let voter = voters.next().unwrap().snapshot(&key);
+        let voter = Voter::<voter::V1>::resume(rng.clone(), &voter, &key).unwrap();
+        let trustees: Vec<Trustee<trustee::E9>> = trustees
+            .iter()
+            .map(|t| Snapshot::from_bytes(&t.snapshot(&key).as_bytes()).unwrap())
+            .map(|s| Trustee::resume(rng.clone(), &s, &key).unwrap())
+            .collect();
+
+        let (_, ballot) = voter.process_message(V2Mi {
+            choices: vec![vec![false, true, false]],
+        });
+        let (voting_server, message) = voting_server.process_message(ballot);
+        message.check.unwrap();
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![1, 2, 0]]);
+    }
+
+    #[test]
+    fn test_snapshot_rejections() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let key = Password::gen(rng.clone());
+        let (voting_server, _, trustees) = setup_election(vec![1], 1);
+
+        // A snapshot only resumes the step it was taken at.
+        let snapshot = voting_server.snapshot(&key);
+        let resumed = VotingServer::<voting_server::T2>::resume(rng.clone(), &snapshot, &key);
+        assert_eq!(resumed.err(), Some(ProtocolError::SnapshotPhaseError));
+        let mut old = snapshot.clone();
+        old.version = SNAPSHOT_VERSION + 1;
+        let resumed = VotingServer::<voting_server::V4>::resume(rng.clone(), &old, &key);
+        assert_eq!(resumed.err(), Some(ProtocolError::SnapshotVersionError));
+
+        // The trustee's keys are not stored in the clear, and only the right key recovers them.
+        let snapshot = trustees[0].snapshot(&key);
+        assert!(matches!(snapshot.data, SnapshotData::Secret(_)));
+        let wrong_key = Password::gen(rng.clone());
+        let resumed = Trustee::<trustee::E9>::resume(rng.clone(), &snapshot, &wrong_key);
+        assert_eq!(resumed.err(), Some(ProtocolError::DecryptionError));
+        assert!(Trustee::<trustee::E9>::resume(rng.clone(), &snapshot, &key).is_ok());
+    }
+}
@@ -3,65 +3,151 @@
 //! * Single trustees,
 //! * "pederson" trustees, which have a "threshold"-type behavior.
 //!
-//! To start, I will work in terms of single trustees for simplicity.
+//! This file implements single trustees, see `pedersen_trustee.rs` for the threshold variant.
 
-use crate::datatypes::credentials::Password;
+use crate::datatypes::credentials::{Password, UUID};
 use crate::participants::messages::*;
 use crate::participants::participant_template::*;
+use crate::participants::snapshot::persistent_state_impl;
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::pki::Ciphertext;
 use crate::primitives::pki::{SigningKeys, VerificationKey};
-use crate::primitives::zkp::{DLog, ProofSystem};
-use ring::rand::SecureRandom;
+use crate::primitives::rng::SecureRandom;
+use crate::primitives::zkp::{DLog, EqualDLog, ProofContext, ProofSystem};
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
 // Doing the "single" trustee protocol, not "pederson".
 initialize_participant_impl!(Trustee);
+persistent_state_impl!(Trustee, true, E9<G>, T3<G>);
 
-pub struct E9 {
-    keys: TrusteeKeys,
-    proof: <DLog as ProofSystem>::Proof,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9<G: Group = Ristretto> {
+    uuid: UUID,
+    index: usize,
+    keys: TrusteeKeys<G>,
+    proof: <DLog<G> as ProofSystem<G>>::Proof,
 }
 
-#[derive(Clone)]
-pub(crate) struct TrusteePublicKey {
-    pub(crate) public_key: VerificationKey,
-    pub(crate) proof: <DLog as ProofSystem>::Proof,
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct TrusteePublicKey<G: Group = Ristretto> {
+    pub(crate) public_key: VerificationKey<G>,
+    pub(crate) proof: <DLog<G> as ProofSystem<G>>::Proof,
 }
 
-pub(crate) struct TrusteeKeys {
+/// Only the secret is serialized, the keys are derived from it again when deserializing.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(bound = "", from = "Password", into = "Password")]
+pub(crate) struct TrusteeKeys<G: Group = Ristretto> {
     pub(crate) secret: Password,
-    pub(crate) keys: SigningKeys,
+    pub(crate) keys: SigningKeys<G>,
 }
 
-impl TrusteeKeys {
+impl<G: Group> TrusteeKeys<G> {
     pub fn gen(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
-        let secret = Password::gen(rng);
+        Self::from(Password::gen(rng))
+    }
+}
+
+impl<G: Group> From<Password> for TrusteeKeys<G> {
+    fn from(secret: Password) -> Self {
         let keys = SigningKeys::from(&secret);
         TrusteeKeys { secret, keys }
     }
 }
 
+impl<G: Group> From<TrusteeKeys<G>> for Password {
+    fn from(keys: TrusteeKeys<G>) -> Self {
+        keys.secret
+    }
+}
+
 process_message_impl!(
+    <G>,
     Trustee,
     EmptyState,
-    E9,
-    EmptyMessage,
-    E9Mi,
-    |state: Trustee<EmptyState>, _: EmptyMessage| {
+    E9<G>,
+    E8Mi,
+    E9Mi<G>,
+    |state: Trustee<EmptyState, G>, m: E8Mi| {
         let keys = TrusteeKeys::gen(state.rng.clone());
         let public_key = &keys.keys.public;
         let instance = DLog {
-            pt: public_key.0.clone(),
-            rng: state.rng.clone(),
+            pt: public_key.0,
         };
-        let proof = instance.prove(&keys.keys.private.0);
+        let context = ProofContext::new(&m.uuid, Some(m.index));
+        let proof = instance.prove(state.rng.clone(), &context, &keys.keys.private.0);
         let trustee_key = TrusteePublicKey {
             public_key: public_key.clone(),
             proof: proof.clone(),
         };
         let message = E9Mi { trustee_key };
-        let state = E9 { keys, proof };
+        let state = E9 {
+            uuid: m.uuid,
+            index: m.index,
+            keys,
+            proof,
+        };
         (state, message)
     }
 );
 
 // Send trustee public key to S. Is a verification key, along with a ZK proof.
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T3<G: Group = Ristretto> {
+    keys: TrusteeKeys<G>,
+}
+
+/// Partially decrypts the encrypted tally with the private key x, proving each decryption factor
+/// is correct with respect to the public key pk = x * g.
+pub(crate) fn partially_decrypt<G: Group>(
+    rng: Arc<Mutex<dyn SecureRandom>>,
+    context: &ProofContext,
+    x: Scalar<G>,
+    pk: Point<G>,
+    encrypted_tally: &[Vec<Ciphertext<G>>],
+) -> T3Mi<G> {
+    let mut decryption_factors = Vec::with_capacity(encrypted_tally.len());
+    let mut decryption_proofs = Vec::with_capacity(encrypted_tally.len());
+    for question in encrypted_tally.iter() {
+        let mut factors = Vec::with_capacity(question.len());
+        let mut proofs = Vec::with_capacity(question.len());
+        for ctxt in question.iter() {
+            let instance = EqualDLog {
+                pk,
+                alpha: ctxt.alpha,
+                factor: ctxt.alpha * x,
+            };
+            proofs.push(instance.prove(rng.clone(), context, &x));
+            factors.push(instance.factor);
+        }
+        decryption_factors.push(factors);
+        decryption_proofs.push(proofs);
+    }
+    T3Mi {
+        decryption_factors,
+        decryption_proofs,
+    }
+}
+
+// T3: partially decrypt the encrypted tally.
+process_message_impl!(
+    <G>,
+    Trustee,
+    E9<G>,
+    T3<G>,
+    T2M<G>,
+    T3Mi<G>,
+    |s: Trustee<E9<G>, G>, m: T2M<G>| {
+        let x = s.state.keys.keys.private.0;
+        let pk = s.state.keys.keys.public.0;
+        let context = ProofContext::new(&s.state.uuid, Some(s.state.index));
+        let message = partially_decrypt(s.rng.clone(), &context, x, pk, &m.encrypted_tally);
+        let state = T3 { keys: s.state.keys };
+        (state, message)
+    }
+);
@@ -8,44 +8,50 @@ use crate::datatypes::election::Election;
 use crate::datatypes::questions::Question;
 use crate::participants::messages::*;
 use crate::participants::participant_template::*;
-use crate::primitives::group::{Point, Scalar};
+use crate::participants::snapshot::persistent_state_impl;
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
 use crate::primitives::pki::{Ciphertext, EncryptionKeys};
+use crate::primitives::rng::SecureRandom;
 use crate::primitives::zkp::{IntervalMembership, IntervalMembershipWitness, ProofSystem};
-use ring::rand::SecureRandom;
+use serde::{Deserialize, Serialize};
 use std::sync::{Arc, Mutex};
 
 initialize_participant_impl!(Voter);
+persistent_state_impl!(Voter, true, E5, V1<G>);
 
 process_message_impl!(
+    <G>,
     Voter,
     EmptyState,
     E5,
     E4Mi,
     EmptyMessage,
-    |_: Voter<EmptyState>, message: E4Mi| {
+    |_: Voter<EmptyState, G>, message: E4Mi| {
         let state = E5Builder::default().pass(message.password).build().unwrap();
         (state, EmptyMessage)
     }
 );
 
-#[derive(Builder)]
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E5 {
     pass: Password,
 }
 
-#[derive(Builder)]
-pub struct V1 {
+#[derive(Builder, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct V1<G: Group = Ristretto> {
     pass: Password,
-    election: Election,
+    election: Election<G>,
 }
 
 process_message_impl!(
+    <G>,
     Voter,
     E5,
-    V1,
-    E11M,
+    V1<G>,
+    E11M<G>,
     EmptyMessage,
-    |s: Voter<E5>, message: E11M| {
+    |s: Voter<E5, G>, message: E11M<G>| {
         let state = V1Builder::default()
             .pass(s.state.pass)
             .election(message.election)
@@ -55,21 +61,38 @@ process_message_impl!(
     }
 );
 
-pub struct V2 {
+// C2: replace a lost password by the one the credential authority regenerated.
+process_message_impl!(
+    <G>,
+    Voter,
+    V1<G>,
+    V1<G>,
+    E4Mi,
+    EmptyMessage,
+    |s: Voter<V1<G>, G>, message: E4Mi| {
+        let state = V1 {
+            pass: message.password,
+            election: s.state.election,
+        };
+        (state, EmptyMessage)
+    }
+);
+
+pub struct V2<G: Group = Ristretto> {
     pass: Password,
-    election: Election,
+    election: Election<G>,
 }
 
-process_message_impl!(Voter, V1, V1, V2Mi, V3Mi, |s: Voter<V1>, message: V2Mi| {
+process_message_impl!(<G>, Voter, V1<G>, V1<G>, V2Mi, V3Mi<G>, |s: Voter<V1<G>, G>, message: V2Mi| {
     let choices_vec = &message.choices;
     let election = s.state.election.clone();
     let pass = s.state.pass.clone();
     let uuid = s.state.election.uuid.clone();
-    let mut answers: Vec<Answer> = Vec::new();
-    for i in 0..election.questions.len() {
+    let mut answers: Vec<Answer<G>> = Vec::new();
+    for (choices, question) in choices_vec.iter().zip(election.questions.iter()) {
         let answer = StateNeededForAnswerBuilder::default()
-            .choices(choices_vec[i].clone())
-            .question(election.questions[i].clone())
+            .choices(choices.clone())
+            .question(question.clone())
             .pass(s.state.pass.clone())
             .rng(s.rng.clone())
             .election(election.clone())
@@ -79,16 +102,16 @@ process_message_impl!(Voter, V1, V1, V2Mi, V3Mi, |s: Voter<V1>, message: V2Mi| {
     }
     let election_hash = election.fingerprint();
     let cred: Credential = (pass, uuid).into();
-    let cred: ExpandedCredential = cred.into();
-    let cred = cred.public_key;
+    let cred: ExpandedCredential<G> = cred.into();
 
-    let ballot = BallotBuilder::default()
+    let mut ballot = BallotBuilder::default()
         .election_uuid(election.uuid)
         .election_hash(election_hash)
         .answers(answers)
-        .credential(cred)
+        .credential(cred.public_key)
         .build()
         .unwrap();
+    ballot.sign(s.rng.clone(), cred.secret_key);
     let message = V3Mi { vote: ballot };
     (s.state, message)
 });
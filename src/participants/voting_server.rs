@@ -1,40 +1,63 @@
 //! The voting server
 
 use crate::datatypes::ballot::Ballot;
+use crate::datatypes::ballot_box::BallotBox;
+use crate::datatypes::bulletin_board::{BulletinBoard, Event};
 use crate::datatypes::election::{Election, ElectionBuilder};
 use crate::datatypes::{base58::Base58, credentials::UUID, questions::Question};
+use crate::parallel;
 use crate::participants::messages::*;
 use crate::participants::participant_template::*;
-use crate::primitives::group::{Point, Scalar};
-use crate::primitives::pki::VerificationKey;
-use crate::primitives::zkp::{DLog, ProofSystem};
+use crate::participants::snapshot::persistent_state_impl;
+use crate::primitives::group::{DLogTable, Group, Point, Ristretto, Scalar};
+use crate::primitives::pki::{Ciphertext, VerificationKey};
+use crate::primitives::rng::SecureRandom;
+use crate::primitives::vss::{lagrange_coefficient, qualified_dealers, PolynomialCommitment};
+use crate::primitives::zkp::{DLog, EqualDLog, Proof, ProofContext, ProofSystem};
 use crate::ProtocolError;
-use ring::rand::SecureRandom;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::error::Error;
 use std::sync::{Arc, Mutex};
 
 initialize_participant_impl!(VotingServer);
+persistent_state_impl!(
+    VotingServer,
+    false,
+    E3,
+    E8<G>,
+    E9<G>,
+    E11<G>,
+    V4<G>,
+    T2<G>,
+    T4<G>,
+    T7<G>
+);
 
 process_message_impl!(
+    <G>,
     VotingServer,
     EmptyState,
     E3,
     E1M,
     E3M_VS_to_CA,
-    |s: VotingServer<EmptyState>, m: E1M| {
+    |s: VotingServer<EmptyState, G>, m: E1M| {
         let uuid = UUID::gen(s.rng);
         let state = E3Builder::default()
             .voters(m.voters)
             .uuid(uuid.clone())
             .build()
             .unwrap();
-        let message = E3M_VS_to_CABuilder::default().uuid(uuid).build().unwrap();
+        let message = E3M_VS_to_CABuilder::default()
+            .uuid(uuid)
+            .group(G::NAME.to_string())
+            .build()
+            .unwrap();
         (state, message)
     }
 );
 
-#[derive(Builder)]
+#[derive(Builder, Serialize, Deserialize)]
 pub struct E3 {
     voters: Vec<u128>,
     uuid: UUID,
@@ -42,12 +65,13 @@ pub struct E3 {
 
 // E7:
 process_message_impl!(
+    <G>,
     VotingServer,
     E3,
-    E8,
-    E7M,
+    E8<G>,
+    E7M<G>,
     ErrorM,
-    |s: VotingServer<E3>, m: E7M| {
+    |s: VotingServer<E3, G>, m: E7M<G>| {
         // Verify the multi-set of weights is correct.
         let mut local_weights = s.state.voters.clone();
         local_weights.sort_unstable();
@@ -68,43 +92,52 @@ process_message_impl!(
     }
 );
 
-#[derive(Builder)]
-pub struct E8 {
+#[derive(Builder, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E8<G: Group = Ristretto> {
     uuid: UUID,
-    L: Vec<(Point, u128)>,
+    L: Vec<(Point<G>, u128)>,
 }
 
 process_message_impl!(
+    <G>,
     VotingServer,
-    E8,
-    E9,
-    E9M,
+    E8<G>,
+    E9<G>,
+    E9M<G>,
     ErrorM,
-    |s: VotingServer<E8>, m: E9M| {
-        // Check all of the proofs of the trustees.
+    |s: VotingServer<E8<G>, G>, m: E9M<G>| {
+        // Check all of the proofs of the trustees, for their index in the message.
         // Record any indices of failing proofs.
+        // As the proofs of the remaining trustees stay bound to their index in the message, the
+        // election should be set up again without the cheaters rather than go on.
         let trustee_keys = m.trustee_keys;
         let mut cheaters = Vec::new();
         let mut trustee_pk = Point::identity();
-        for i in 0..trustee_keys.len() {
-            let pk: Point = trustee_keys[i].public_key.clone().into();
-            let dlog = DLog {
-                rng: s.rng.clone(),
-                pt: pk.clone(),
-            };
-            if !dlog.verify(&trustee_keys[i].proof) {
+        let mut honest_keys = Vec::new();
+        let mut proofs = Vec::new();
+        for (i, key) in trustee_keys.iter().enumerate() {
+            let pk: Point<G> = key.public_key.clone().into();
+            let dlog = DLog { pt: pk };
+            let context = ProofContext::new(&s.state.uuid, Some(i));
+            if !dlog.verify(&context, &key.proof) {
                 cheaters.push(i);
             } else {
                 trustee_pk = trustee_pk + pk;
+                honest_keys.push(pk);
+                proofs.push(Some(key.proof.clone()));
             }
         }
         let state = E9Builder::default()
             .uuid(s.state.uuid)
             .L(s.state.L)
             .trustee_pk(trustee_pk)
+            .trustee_keys(honest_keys)
+            .trustee_proofs(proofs)
+            .threshold(None)
             .build()
             .unwrap();
-        let check = if cheaters.len() > 0 {
+        let check = if !cheaters.is_empty() {
             Err(ProtocolError::TrusteePKProofFailedError(cheaters))
         } else {
             Ok(())
@@ -113,35 +146,157 @@ process_message_impl!(
     }
 );
 
-#[derive(Builder)]
-pub struct E9 {
+#[derive(Builder, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E9<G: Group = Ristretto> {
     uuid: UUID,
-    L: Vec<(Point, u128)>,
-    trustee_pk: Point,
+    L: Vec<(Point<G>, u128)>,
+    trustee_pk: Point<G>,
+    // For single trustees, the public keys of the trustees whose proofs verified, which sum to
+    // `trustee_pk`.
+    // For "pedersen" trustees, the verification keys of all trustees' key shares.
+    trustee_keys: Vec<Point<G>>,
+    // The proof of knowledge of the private key of each of `trustee_keys`, if the trustee sent
+    // one.
+    trustee_proofs: Vec<Option<Proof<G>>>,
+    // The number of "pedersen" trustees needed to decrypt, None for single trustees.
+    threshold: Option<usize>,
+    // The commitments of the qualified dealers of "pedersen" trustees (by index), published so
+    // that anybody can recompute the keys.
+    #[builder(default)]
+    commitments: Vec<Option<PolynomialCommitment<G>>>,
 }
 
+// E9 for "pedersen" trustees.
+// Recomputes the qualified dealers from the transcript of the ceremony, from which the election
+// public key and the verification key of each trustee follow.
+// Record any indices of disqualified dealers, or of trustees who claim a different verification
+// key (or fail to prove knowledge of it).
 process_message_impl!(
+    <G>,
     VotingServer,
-    E9,
-    E11,
+    E8<G>,
+    E9<G>,
+    E9M_Pedersen<G>,
+    ErrorM,
+    |s: VotingServer<E8<G>, G>, m: E9M_Pedersen<G>| {
+        let num_trustees = m.commitments.len();
+        let complaints: Vec<Vec<usize>> = m
+            .complaints
+            .complaints
+            .iter()
+            .map(|c| c.complaints.clone())
+            .collect();
+        let responses: Vec<Vec<(usize, Scalar<G>)>> = m
+            .responses
+            .responses
+            .iter()
+            .map(|r| r.revealed.clone())
+            .collect();
+        let qualified = qualified_dealers(
+            &s.state.uuid,
+            m.threshold,
+            &m.commitments,
+            &complaints,
+            &responses,
+        );
+        let mut trustee_pk = Point::identity();
+        for i in qualified.iter() {
+            trustee_pk = trustee_pk + m.commitments[*i].coefficients[0];
+        }
+        let mut trustee_keys = Vec::with_capacity(num_trustees);
+        let mut proofs = Vec::with_capacity(num_trustees);
+        let mut cheaters = Vec::new();
+        for j in 0..num_trustees {
+            let mut vk = Point::identity();
+            for i in qualified.iter() {
+                vk = vk + m.commitments[*i].eval(j as u128 + 1);
+            }
+            let key = m.trustee_keys.get(j).and_then(|key| key.as_ref());
+            let verified = match key {
+                Some(key) => {
+                    let dlog = DLog {
+                        pt: vk,
+                    };
+                    let context = ProofContext::new(&s.state.uuid, Some(j));
+                    key.public_key.0 == vk && dlog.verify(&context, &key.proof)
+                }
+                None => false,
+            };
+            if !verified {
+                cheaters.push(j);
+            }
+            trustee_keys.push(vk);
+            proofs.push(key.map(|key| key.proof.clone()));
+        }
+        let disqualified: Vec<usize> = (0..num_trustees)
+            .filter(|i| !qualified.contains(i))
+            .collect();
+        let commitments = m
+            .commitments
+            .into_iter()
+            .enumerate()
+            .map(|(i, commitment)| qualified.contains(&i).then_some(commitment))
+            .collect();
+        let check = if !valid_threshold(m.threshold, num_trustees) {
+            Err(ProtocolError::InvalidThresholdError)
+        } else if !disqualified.is_empty() {
+            Err(ProtocolError::DealerDisqualifiedError(disqualified))
+        } else if !cheaters.is_empty() {
+            Err(ProtocolError::TrusteePKProofFailedError(cheaters))
+        } else {
+            Ok(())
+        };
+        let state = E9Builder::default()
+            .uuid(s.state.uuid)
+            .L(s.state.L)
+            .trustee_pk(trustee_pk)
+            .trustee_keys(trustee_keys)
+            .trustee_proofs(proofs)
+            .threshold(Some(m.threshold))
+            .commitments(commitments)
+            .build()
+            .unwrap();
+        (state, ErrorM { check })
+    }
+);
+
+process_message_impl!(
+    <G>,
+    VotingServer,
+    E9<G>,
+    E11<G>,
     E10M,
-    E11M,
-    |s: VotingServer<E9>, m: E10M| {
+    E11M<G>,
+    |s: VotingServer<E9<G>, G>, m: E10M| {
         let election = ElectionBuilder::default()
             .version(m.version)
             .description(m.description)
             .name(m.name)
-            .group("RISTRETTO-25519".to_string())
             .public_key(s.state.trustee_pk)
             .questions(m.questions)
             .uuid(s.state.uuid)
             .administrator(m.administrator)
             .credential_authority(m.credential_authority)
+            .fingerprint_mode(m.fingerprint_mode)
             .build()
             .unwrap();
+        let mut board = BulletinBoard::new();
+        board.append(Event::Election(election.clone()));
+        board.append(Event::Voters(s.state.L.clone()));
+        board.append(Event::TrusteeKeys {
+            trustee_keys: s.state.trustee_keys.clone(),
+            proofs: s.state.trustee_proofs,
+            threshold: s.state.threshold,
+            commitments: s.state.commitments,
+        });
         let state = E11 {
             election: election.clone(),
             L: s.state.L.clone(),
+            trustee_keys: s.state.trustee_keys,
+            threshold: s.state.threshold,
+            revote_policy: m.revote_policy,
+            board,
         };
         let message = E11M {
             election,
@@ -151,23 +306,48 @@ process_message_impl!(
     }
 );
 
-pub struct E11 {
-    pub(crate) election: Election,
-    pub(crate) L: Vec<(Point, u128)>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct E11<G: Group = Ristretto> {
+    pub(crate) election: Election<G>,
+    pub(crate) L: Vec<(Point<G>, u128)>,
+    pub(crate) trustee_keys: Vec<Point<G>>,
+    pub(crate) threshold: Option<usize>,
+    pub(crate) revote_policy: RevotePolicy,
+    pub(crate) board: BulletinBoard<G>,
+}
+
+/// What the voting server does when a valid ballot arrives for a credential which already has an
+/// accepted ballot.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RevotePolicy {
+    /// The new ballot is rejected with `ProtocolError::CredentialUsedTwiceError`.
+    Reject,
+    /// The new ballot replaces the earlier one, e.g. the last ballot counts, as in Belenios.
+    #[default]
+    Replace,
+    /// The new ballot is discarded with `ProtocolError::BallotDiscardedError`, e.g. the first
+    /// ballot counts.
+    KeepFirst,
 }
 
 process_message_impl!(
+    <G>,
     VotingServer,
-    E11,
-    V4,
+    E11<G>,
+    V4<G>,
     EmptyMessage,
     EmptyMessage,
-    |s: VotingServer<E11>, m: EmptyMessage| {
-        let accepted_ballots: Vec<(Ballot, u128)> = Vec::new();
+    |s: VotingServer<E11<G>, G>, _: EmptyMessage| {
         let state = V4 {
             election: s.state.election,
-            L: s.state.L,
-            accepted_ballots,
+            ballot_box: BallotBox::new(s.state.L),
+            trustee_keys: s.state.trustee_keys,
+            threshold: s.state.threshold,
+            revote_policy: s.state.revote_policy,
+            discarded_ballots: Vec::new(),
+            credential_updates: Vec::new(),
+            board: s.state.board,
         };
         (state, EmptyMessage)
     }
@@ -176,69 +356,1296 @@ process_message_impl!(
 // Processing a Ballot
 
 process_message_impl!(
+    <G>,
     VotingServer,
-    V4,
-    V4,
-    V3Mi,
+    V4<G>,
+    V4<G>,
+    V3Mi<G>,
     ErrorM,
-    |s: VotingServer<V4>, m: V3Mi| {
-        let L = s.state.L.clone();
-        let election = s.state.election.clone();
-        let accepted_ballots = s.state.accepted_ballots.clone();
+    |s: VotingServer<V4<G>, G>, m: V3Mi<G>| {
+        let mut state = s.state;
         let ballot = m.vote;
-        let cred = ballot.credential;
-        // Find the weight of the ballot
-        let mut found = false;
-        let mut found_wt = 1;
-        for (pt, wt) in L.iter() {
-            if *pt == cred {
-                found = true;
-                found_wt = *wt;
-            }
-        }
-        if !found {
-            return (
-                s.state,
-                ErrorM {
-                    check: Err(ProtocolError::CredentialNotFoundError),
-                },
-            );
-        }
-        let mut found = false;
-        for (ballot, _) in accepted_ballots.iter() {
-            if cred == ballot.credential {
-                found = true;
-            }
-        }
-        if found {
-            return (
-                s.state,
-                ErrorM {
-                    check: Err(ProtocolError::CredentialUsedTwiceError),
-                },
-            );
-        }
-        if !ballot.verify(s.rng.clone(), &election.public_key, &election.questions) {
-            return (
-                s.state,
-                ErrorM {
-                    check: Err(ProtocolError::BallotVerificationError),
-                },
-            );
-        }
-        let mut accepted_ballots = accepted_ballots;
-        accepted_ballots.push((ballot, found_wt));
-        let state = V4 {
-            election,
+        let weight = state.ballot_box.weight(&ballot.credential);
+        let weight = match check_ballot(&state.election, weight, &ballot) {
+            Ok(weight) => weight,
+            Err(e) => return (state, ErrorM { check: Err(e) }),
+        };
+        // Each credential has at most one accepted ballot, the other ones are kept for auditing.
+        // Only accepted ballots are published.
+        let voted = state.ballot_box.has_ballot(&ballot.credential);
+        match (voted, state.revote_policy) {
+            (true, RevotePolicy::Reject) => {
+                let check = Err(ProtocolError::CredentialUsedTwiceError);
+                return (state, ErrorM { check });
+            }
+            (true, RevotePolicy::KeepFirst) => {
+                state.discarded_ballots.push(ballot);
+                let check = Err(ProtocolError::BallotDiscardedError);
+                return (state, ErrorM { check });
+            }
+            (false, _) | (true, RevotePolicy::Replace) => {
+                state.board.append(Event::Ballot(ballot.clone()));
+                if let Some(replaced) = state.ballot_box.insert(ballot, weight) {
+                    state.discarded_ballots.push(replaced);
+                }
+            }
+        }
+        (state, ErrorM { check: Ok(()) })
+    }
+);
+
+/// Checks that a ballot is for a credential of L and signed under it (so that only the holder of
+/// the credential may submit a ballot for it), and that it is a valid ballot for the election.
+/// Takes the weight of the credential of the ballot in L, if it is there, and returns it.
+pub(crate) fn check_ballot<G: Group>(
+    election: &Election<G>,
+    weight: Option<u128>,
+    ballot: &Ballot<G>,
+) -> Result<u128, ProtocolError> {
+    let weight = weight.ok_or(ProtocolError::CredentialNotFoundError)?;
+    if !ballot.verify_signature() {
+        return Err(ProtocolError::BallotSignatureError);
+    }
+    let for_election = ballot.election_uuid == election.uuid
+        && ballot.election_hash == election.fingerprint()
+        && ballot.answers.len() == election.questions.len();
+    if !for_election || !ballot.verify(&election.public_key, &election.questions) {
+        return Err(ProtocolError::BallotVerificationError);
+    }
+    Ok(weight)
+}
+
+// C3: replace the public credential of a voter who went through credential recovery.
+// The ballot accepted for the old credential (if any) no longer counts, and is discarded.
+process_message_impl!(
+    <G>,
+    VotingServer,
+    V4<G>,
+    V4<G>,
+    C3M_CA_to_VS<G>,
+    ErrorM,
+    |s: VotingServer<V4<G>, G>, m: C3M_CA_to_VS<G>| {
+        let mut state = s.state;
+        let updated = state
+            .ballot_box
+            .update_credential(&m.old_credential, m.new_credential);
+        if let Err(e) = updated {
+            return (state, ErrorM { check: Err(e) });
+        }
+        if let Some(ballot) = state.ballot_box.remove(&m.old_credential) {
+            state.discarded_ballots.push(ballot);
+        }
+        state.board.append(Event::CredentialUpdate(m.clone()));
+        state.credential_updates.push(m);
+        (state, ErrorM { check: Ok(()) })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct V4<G: Group = Ristretto> {
+    pub(crate) election: Election<G>,
+    // The list L and the accepted ballots.
+    pub(crate) ballot_box: BallotBox<G>,
+    pub(crate) trustee_keys: Vec<Point<G>>,
+    pub(crate) threshold: Option<usize>,
+    pub(crate) revote_policy: RevotePolicy,
+    // Valid ballots which do not count, as their credential has another accepted ballot (or was
+    // replaced), in the order they were discarded.
+    pub(crate) discarded_ballots: Vec<Ballot<G>>,
+    // The public log of the credentials replaced during credential recovery, in order.
+    pub(crate) credential_updates: Vec<C3M_CA_to_VS<G>>,
+    pub(crate) board: BulletinBoard<G>,
+}
+
+/// Sums the encrypted choices of the ballots, each multiplied by the weight of its credential.
+/// With the `parallel` feature, the ballots are summed across threads.
+pub(crate) fn aggregate_ballots<G: Group>(
+    questions: &[Question],
+    ballots: &[(&Ballot<G>, u128)],
+) -> Vec<Vec<Ciphertext<G>>> {
+    let empty = || -> Vec<Vec<Ciphertext<G>>> {
+        questions
+            .iter()
+            .map(|q| vec![Ciphertext::identity(); q.answers.len() + q.blank as usize])
+            .collect()
+    };
+    let add_ballot = |mut encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+                      (ballot, weight): &(&Ballot<G>, u128)| {
+        let weight = Scalar::from(*weight);
+        for (i, answer) in ballot.answers.iter().enumerate() {
+            for (j, choice) in answer.choices.iter().enumerate() {
+                encrypted_tally[i][j] = encrypted_tally[i][j] + (*choice * weight);
+            }
+        }
+        encrypted_tally
+    };
+    let add_tallies = |mut encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+                       other: Vec<Vec<Ciphertext<G>>>| {
+        for (sums, others) in encrypted_tally.iter_mut().zip(other) {
+            for (sum, other) in sums.iter_mut().zip(others) {
+                *sum = *sum + other;
+            }
+        }
+        encrypted_tally
+    };
+    parallel::fold(ballots, empty, add_ballot, add_tallies)
+}
+
+// The Tally phase.
+//
+// T1: the election is closed, e.g. the server stops accepting ballots.
+// We do not make this an explicit step, as moving out of state V4 already prevents further
+// ballots from being processed.
+// T2: the server computes (and publishes) the encrypted tally.
+process_message_impl!(
+    <G>,
+    VotingServer,
+    V4<G>,
+    T2<G>,
+    EmptyMessage,
+    T2M<G>,
+    |s: VotingServer<V4<G>, G>, _: EmptyMessage| {
+        let BallotBox {
             L,
+            ballots: accepted_ballots,
+            ..
+        } = s.state.ballot_box;
+        let ballots: Vec<(&Ballot<G>, u128)> =
+            accepted_ballots.iter().map(|(b, wt)| (b, *wt)).collect();
+        let encrypted_tally = aggregate_ballots(&s.state.election.questions, &ballots);
+        let num_tallied = accepted_ballots.len();
+        let mut board = s.state.board;
+        board.append(Event::EncryptedTally(encrypted_tally.clone()));
+        let state = T2 {
+            election: s.state.election,
+            L,
+            trustee_keys: s.state.trustee_keys,
+            threshold: s.state.threshold,
             accepted_ballots,
+            encrypted_tally: encrypted_tally.clone(),
+            board,
         };
-        (state, ErrorM { check: Ok(()) })
+        let message = T2M {
+            num_tallied,
+            encrypted_tally,
+        };
+        (state, message)
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T2<G: Group = Ristretto> {
+    election: Election<G>,
+    L: Vec<(Point<G>, u128)>,
+    trustee_keys: Vec<Point<G>>,
+    threshold: Option<usize>,
+    accepted_ballots: Vec<(Ballot<G>, u128)>,
+    encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+    board: BulletinBoard<G>,
+}
+
+// T3 is done by the trustees.
+// T4: the server checks the partial decryptions.
+// Record the indices of any trustee whose partial decryption is malformed (or missing, for single
+// trustees), or otherwise whose proofs of correct decryption fail.
+// Only the valid partial decryptions are kept, e.g. none of them if there is not one per trustee.
+process_message_impl!(
+    <G>,
+    VotingServer,
+    T2<G>,
+    T4<G>,
+    T3M<G>,
+    ErrorM,
+    |s: VotingServer<T2<G>, G>, m: T3M<G>| {
+        let mut partial_decryptions = m.partial_decryptions;
+        let tally = &s.state.encrypted_tally;
+        let check = if partial_decryptions.len() != s.state.trustee_keys.len() {
+            partial_decryptions = vec![None; s.state.trustee_keys.len()];
+            Err(ProtocolError::IncorrectLenError)
+        } else {
+            let mut malformed = Vec::new();
+            let mut cheaters = Vec::new();
+            for (i, pd) in partial_decryptions.iter().enumerate() {
+                let pd = match pd {
+                    Some(pd) => pd,
+                    None if s.state.threshold.is_some() => continue,
+                    None => {
+                        malformed.push(i);
+                        continue;
+                    }
+                };
+                let pk = s.state.trustee_keys[i];
+                match verify_partial_decryption(&s.state.election.uuid, i, pk, tally, pd) {
+                    Err(ProtocolError::PartialDecryptionMalformedError(_)) => malformed.push(i),
+                    Err(_) => cheaters.push(i),
+                    Ok(()) => (),
+                }
+            }
+            for i in malformed.iter().chain(cheaters.iter()) {
+                partial_decryptions[*i] = None;
+            }
+            if !malformed.is_empty() {
+                Err(ProtocolError::PartialDecryptionMalformedError(malformed))
+            } else if !cheaters.is_empty() {
+                Err(ProtocolError::PartialDecryptionProofFailedError(cheaters))
+            } else {
+                Ok(())
+            }
+        };
+        let mut board = s.state.board;
+        board.append(Event::PartialDecryptions(partial_decryptions.clone()));
+        let state = T4 {
+            election: s.state.election,
+            L: s.state.L,
+            threshold: s.state.threshold,
+            accepted_ballots: s.state.accepted_ballots,
+            encrypted_tally: s.state.encrypted_tally,
+            partial_decryptions,
+            board,
+        };
+        (state, ErrorM { check })
+    }
+);
+
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T4<G: Group = Ristretto> {
+    election: Election<G>,
+    L: Vec<(Point<G>, u128)>,
+    threshold: Option<usize>,
+    accepted_ballots: Vec<(Ballot<G>, u128)>,
+    encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+    // The partial decryptions that passed the checks of step T4.
+    partial_decryptions: Vec<Option<T3Mi<G>>>,
+    board: BulletinBoard<G>,
+}
+
+/// Checks the partial decryption of `tally` by the i-th trustee, whose key is `pk`.
+/// Fails if it does not have the shape of `tally`, or if one of its proofs of correct decryption
+/// fails.
+fn verify_partial_decryption<G: Group>(
+    uuid: &UUID,
+    i: usize,
+    pk: Point<G>,
+    tally: &[Vec<Ciphertext<G>>],
+    pd: &T3Mi<G>,
+) -> Result<(), ProtocolError> {
+    let (factors, proofs) = (&pd.decryption_factors, &pd.decryption_proofs);
+    if !same_shape(factors, tally) || !same_shape(proofs, tally) {
+        return Err(ProtocolError::PartialDecryptionMalformedError(vec![i]));
+    }
+    let context = ProofContext::new(uuid, Some(i));
+    let verified = (0..tally.len()).all(|q| {
+        (0..tally[q].len()).all(|a| {
+            let instance = EqualDLog {
+                pk,
+                alpha: tally[q][a].alpha,
+                factor: factors[q][a],
+            };
+            instance.verify(&context, &proofs[q][a])
+        })
+    });
+    if !verified {
+        return Err(ProtocolError::PartialDecryptionProofFailedError(vec![i]));
+    }
+    Ok(())
+}
+
+/// Whether `matrix` has a value for each question and answer of `tally`.
+pub(crate) fn same_shape<T, U>(matrix: &[Vec<T>], tally: &[Vec<U>]) -> bool {
+    matrix.len() == tally.len() && matrix.iter().zip(tally).all(|(m, t)| m.len() == t.len())
+}
+
+/// Combines the valid partial decryptions of `tally` into a decryption factor x * alpha for each
+/// question and answer, where x is the election private key.
+///
+/// For single trustees, x is the sum of all trustees' private keys, so every partial decryption
+/// is needed.
+/// For "pedersen" trustees, x is recovered by Lagrange interpolation from the key shares of any
+/// `threshold` trustees.
+/// Fails with the indices of any partial decryptions which do not have the shape of `tally`.
+pub(crate) fn combine_partial_decryptions<G: Group>(
+    tally: &[Vec<Ciphertext<G>>],
+    partial_decryptions: &[Option<T3Mi<G>>],
+    threshold: Option<usize>,
+) -> Result<Vec<Vec<Point<G>>>, ProtocolError> {
+    let valid: Vec<(usize, &T3Mi<G>)> = partial_decryptions
+        .iter()
+        .enumerate()
+        .filter_map(|(i, pd)| pd.as_ref().map(|pd| (i, pd)))
+        .collect();
+    let malformed: Vec<usize> = valid
+        .iter()
+        .filter(|(_, pd)| !same_shape(&pd.decryption_factors, tally))
+        .map(|(i, _)| *i)
+        .collect();
+    if !malformed.is_empty() {
+        return Err(ProtocolError::PartialDecryptionMalformedError(malformed));
+    }
+    let (used, coefficients): (Vec<&T3Mi<G>>, Vec<Scalar<G>>) = match threshold {
+        None if valid.len() == partial_decryptions.len() => {
+            valid.into_iter().map(|(_, pd)| (pd, Scalar::one())).unzip()
+        }
+        Some(t) if valid.len() >= t => {
+            let xs: Vec<u128> = valid.iter().take(t).map(|(i, _)| *i as u128 + 1).collect();
+            valid
+                .into_iter()
+                .take(t)
+                .map(|(i, pd)| (pd, lagrange_coefficient(i as u128 + 1, &xs)))
+                .unzip()
+        }
+        _ => return Err(ProtocolError::NotEnoughPartialDecryptionsError),
+    };
+    if used.is_empty() {
+        return Err(ProtocolError::NotEnoughPartialDecryptionsError);
+    }
+    let mut combined: Vec<Vec<Point<G>>> = tally
+        .iter()
+        .map(|q| vec![Point::identity(); q.len()])
+        .collect();
+    for (pd, lambda) in used.into_iter().zip(coefficients) {
+        for (q, factors) in pd.decryption_factors.iter().enumerate() {
+            for (a, factor) in factors.iter().enumerate() {
+                combined[q][a] = combined[q][a] + (*factor * lambda);
+            }
+        }
+    }
+    Ok(combined)
+}
+
+// T5 - T7: the server combines the partial decryptions, recovers the (weighted) number of
+// votes for each answer, and publishes the result.
+//
+// Each tallied value is bounded by the total weight of all voters, and recovered with a
+// `DLogTable` shared across tallies.
+process_message_impl!(
+    <G>,
+    VotingServer,
+    T4<G>,
+    T7<G>,
+    EmptyMessage,
+    T7M<G>,
+    |s: VotingServer<T4<G>, G>, _: EmptyMessage| {
+        let table = s
+            .state
+            .L
+            .iter()
+            .try_fold(0u128, |sum, (_, wt)| sum.checked_add(*wt))
+            .ok_or(ProtocolError::DLogBoundTooLargeError)
+            .and_then(DLogTable::<G>::cached);
+        let tally = &s.state.encrypted_tally;
+        let result = table.and_then(|table| {
+            let factors =
+                combine_partial_decryptions(tally, &s.state.partial_decryptions, s.state.threshold)?;
+            tally
+                .iter()
+                .zip(factors.iter())
+                .map(|(question, factors)| {
+                    question
+                        .iter()
+                        .zip(factors.iter())
+                        .map(|(ctxt, factor)| table.solve(ctxt.beta - *factor))
+                        .collect::<Result<Vec<u128>, ProtocolError>>()
+                })
+                .collect::<Result<Vec<Vec<u128>>, ProtocolError>>()
+        });
+        let num_tallied = s.state.accepted_ballots.len();
+        let mut board = s.state.board;
+        if let Ok(result) = &result {
+            board.append(Event::Result(result.clone()));
+        }
+        let message = T7M {
+            num_tallied,
+            encrypted_tally: s.state.encrypted_tally.clone(),
+            partial_decryptions: s.state.partial_decryptions.clone(),
+            result,
+        };
+        let state = T7 {
+            election: s.state.election,
+            accepted_ballots: s.state.accepted_ballots,
+            encrypted_tally: s.state.encrypted_tally,
+            partial_decryptions: s.state.partial_decryptions,
+            board,
+        };
+        (state, message)
     }
 );
 
-pub struct V4 {
-    pub(crate) election: Election,
-    pub(crate) L: Vec<(Point, u128)>,
-    pub(crate) accepted_ballots: Vec<(Ballot, u128)>,
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct T7<G: Group = Ristretto> {
+    election: Election<G>,
+    accepted_ballots: Vec<(Ballot<G>, u128)>,
+    encrypted_tally: Vec<Vec<Ciphertext<G>>>,
+    partial_decryptions: Vec<Option<T3Mi<G>>>,
+    board: BulletinBoard<G>,
+}
+
+macro_rules! bulletin_board_impl {
+    ($($S:ident),+) => {
+        $(
+            impl<G: Group> VotingServer<$S<G>, G> {
+                /// The public log of everything the server published so far.
+                pub fn bulletin_board(&self) -> &BulletinBoard<G> {
+                    &self.state.board
+                }
+                pub fn election(&self) -> &Election<G> {
+                    &self.state.election
+                }
+            }
+        )+
+    };
+}
+bulletin_board_impl!(E11, V4, T2, T4, T7);
+
+macro_rules! ballot_box_impl {
+    ($($S:ident),+) => {
+        $(
+            impl<G: Group> VotingServer<$S<G>, G> {
+                /// The accepted ballots, e.g. those which count towards the tally.
+                pub fn ballots(&self) -> impl Iterator<Item = &Ballot<G>> {
+                    self.state.accepted_ballots.iter().map(|(ballot, _)| ballot)
+                }
+            }
+        )+
+    };
+}
+ballot_box_impl!(T2, T4, T7);
+
+impl<G: Group> VotingServer<T2<G>, G> {
+    /// Checks the partial decryption of the i-th trustee as step T4 does, e.g. to reject it as
+    /// soon as it is received.
+    pub fn verify_partial_decryption(
+        &self,
+        i: usize,
+        partial_decryption: &T3Mi<G>,
+    ) -> Result<(), ProtocolError> {
+        let pk = *self
+            .state
+            .trustee_keys
+            .get(i)
+            .ok_or(ProtocolError::IncorrectLenError)?;
+        let tally = &self.state.encrypted_tally;
+        verify_partial_decryption(&self.state.election.uuid, i, pk, tally, partial_decryption)
+    }
+}
+
+impl<G: Group> VotingServer<V4<G>, G> {
+    /// The public list L of credentials and their weights, as updated by credential recovery.
+    pub fn voters(&self) -> &[(Point<G>, u128)] {
+        self.state.ballot_box.voters()
+    }
+    /// The accepted ballots, e.g. those which count towards the tally.
+    pub fn ballots(&self) -> impl Iterator<Item = &Ballot<G>> {
+        self.state
+            .ballot_box
+            .ballots()
+            .iter()
+            .map(|(ballot, _)| ballot)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::datatypes::credentials::Password;
+    use crate::datatypes::questions::QuestionBuilder;
+    use crate::participants::credential_authority::{self, CredentialAuthority};
+    use crate::participants::pedersen_trustee::{self, PedersenTrustee};
+    use crate::participants::server_admin::ServerAdmin;
+    use crate::participants::trustee::{self, Trustee};
+    use crate::participants::voter::{self, Voter};
+    use crate::primitives::ff_group::Belenios2048;
+    use crate::verify::verify_election;
+    use ring::rand::SystemRandom;
+
+    /// Runs the Election Setup phase up to the generation of the trustee keys.
+    #[allow(clippy::type_complexity)]
+    fn setup_voters<G: Group>(
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        weights: Vec<u128>,
+    ) -> (
+        VotingServer<E8<G>, G>,
+        CredentialAuthority<credential_authority::E4<G>, G>,
+        Vec<Voter<voter::E5, G>>,
+    ) {
+        let election = E1MBuilder::default().voters(weights).build().unwrap();
+        let server_admin = ServerAdmin::new(rng.clone());
+        let voting_server = VotingServer::with_group(rng.clone());
+        let (_, message_SA) = server_admin.process_message(election.clone());
+        let (voting_server, message_VS) = voting_server.process_message(election);
+        let credential_authority = CredentialAuthority::with_group(rng.clone());
+        let (credential_authority, passwords) =
+            credential_authority.process_message(E3M::from((message_VS, message_SA)));
+        let passwords: Vec<E4Mi> = passwords.into();
+        let voters: Vec<_> = passwords
+            .into_iter()
+            .map(|pass| Voter::with_group(rng.clone()).process_message(pass).0)
+            .collect();
+        let (credential_authority, message) = credential_authority.process_message(EmptyMessage);
+        let (voting_server, message) = voting_server.process_message(message);
+        message.check.unwrap();
+        (voting_server, credential_authority, voters)
+    }
+
+    /// Sets up the i-th single trustee of the election of the voting server.
+    fn new_trustee<G: Group>(
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        voting_server: &VotingServer<E8<G>, G>,
+        index: usize,
+    ) -> (Trustee<trustee::E9<G>, G>, E9Mi<G>) {
+        let context = E8MiBuilder::default()
+            .uuid(voting_server.state.uuid.clone())
+            .index(index)
+            .build()
+            .unwrap();
+        Trustee::with_group(rng).process_message(context)
+    }
+
+    fn sample_question(blank: bool) -> Question {
+        QuestionBuilder::default()
+            .question("Which hardness assumption will be broken next?")
+            .answers(vec!["RLWE", "LWR", "RSA"])
+            .blank(blank)
+            .build()
+            .unwrap()
+    }
+
+    /// Runs the rest of the Election Setup phase once the trustee keys are known, returning the
+    /// voting server ready to accept ballots, along with the voters and the credential authority.
+    #[allow(clippy::type_complexity)]
+    fn finish_setup<G: Group>(
+        voting_server: VotingServer<E9<G>, G>,
+        credential_authority: CredentialAuthority<credential_authority::E4<G>, G>,
+        voters: Vec<Voter<voter::E5, G>>,
+        question: Question,
+    ) -> (
+        VotingServer<V4<G>, G>,
+        Vec<Voter<voter::V1<G>, G>>,
+        CredentialAuthority<credential_authority::E12<G>, G>,
+    ) {
+        let message = E10MBuilder::default()
+            .questions(vec![question])
+            .version(1)
+            .description("Sample".to_string())
+            .name("Sample".to_string())
+            .administrator("Sample".to_string())
+            .credential_authority("Sample".to_string())
+            .build()
+            .unwrap();
+        let (voting_server, election_message) = voting_server.process_message(message);
+        let (credential_authority, message) =
+            credential_authority.process_message(election_message.clone());
+        message.check.unwrap();
+        let voters = voters
+            .into_iter()
+            .map(|v| v.process_message(election_message.clone()).0)
+            .collect();
+        let (voting_server, _) = voting_server.process_message(EmptyMessage);
+        (voting_server, voters, credential_authority)
+    }
+
+    /// Runs the Election Setup phase for voters of the given weights, returning the voting server
+    /// ready to accept ballots, along with the voters and (single) trustees.
+    pub(crate) fn setup_election(
+        weights: Vec<u128>,
+        num_trustees: usize,
+    ) -> (
+        VotingServer<V4>,
+        Vec<Voter<voter::V1>>,
+        Vec<Trustee<trustee::E9>>,
+    ) {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (voting_server, credential_authority, voters) = setup_voters(rng.clone(), weights);
+        let (trustees, trustee_keys): (Vec<_>, Vec<E9Mi>) = (0..num_trustees)
+            .map(|i| new_trustee(rng.clone(), &voting_server, i))
+            .unzip();
+        let (voting_server, message) = voting_server.process_message(E9M::from(trustee_keys));
+        message.check.unwrap();
+        let (voting_server, voters, _) = finish_setup(
+            voting_server,
+            credential_authority,
+            voters,
+            sample_question(false),
+        );
+        (voting_server, voters, trustees)
+    }
+
+    /// Runs the Election Setup phase with "pedersen" trustees.
+    /// `tamper_dealings`, `tamper_complaints` and `tamper_responses` may modify the dealings, the
+    /// complaints and the answers to complaints, to simulate cheating dealers (or servers).
+    /// Returns the check of step E9 along with the usual outputs.
+    #[allow(clippy::type_complexity)]
+    pub(crate) fn setup_pedersen_election(
+        weights: Vec<u128>,
+        threshold: usize,
+        num_trustees: usize,
+        tamper_dealings: impl Fn(&mut [P2Mi]),
+        tamper_complaints: impl Fn(&mut [P4Mi]),
+        tamper_responses: impl Fn(&mut [P5Mi]),
+    ) -> (
+        VotingServer<V4>,
+        Vec<Voter<voter::V1>>,
+        Vec<PedersenTrustee<pedersen_trustee::E9>>,
+        ErrorM,
+    ) {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (voting_server, credential_authority, voters) = setup_voters(rng.clone(), weights);
+        let (trustees, keys): (Vec<_>, Vec<P1Mi>) = (0..num_trustees)
+            .map(|index| {
+                let params = P1MBuilder::default()
+                    .uuid(voting_server.state.uuid.clone())
+                    .index(index)
+                    .threshold(threshold)
+                    .num_trustees(num_trustees)
+                    .build()
+                    .unwrap();
+                PedersenTrustee::new(rng.clone()).process_message(params)
+            })
+            .unzip();
+        let keys = P1M_Keys::from(keys);
+        let (trustees, mut dealings): (Vec<_>, Vec<P2Mi>) = trustees
+            .into_iter()
+            .map(|t| t.process_message(keys.clone()))
+            .unzip();
+        tamper_dealings(&mut dealings);
+        let dealings = P2M::from(dealings);
+        let shares: Vec<P3Mi> = dealings.clone().into();
+        let (trustees, mut complaints): (Vec<_>, Vec<P4Mi>) = trustees
+            .into_iter()
+            .zip(shares)
+            .map(|(t, m)| t.process_message(m))
+            .unzip();
+        tamper_complaints(&mut complaints);
+        let complaints = P4M::from(complaints);
+        let (trustees, mut responses): (Vec<_>, Vec<P5Mi>) = trustees
+            .into_iter()
+            .map(|t| t.process_message(complaints.clone()))
+            .unzip();
+        tamper_responses(&mut responses);
+        let responses = P5M::from(responses);
+        let (trustees, keys): (Vec<_>, Vec<E9Mi_Pedersen>) = trustees
+            .into_iter()
+            .map(|t| t.process_message(responses.clone()))
+            .unzip();
+        let message = E9M_Pedersen::from((threshold, dealings, complaints, responses, keys));
+        let (voting_server, check) = voting_server.process_message(message);
+        let (voting_server, voters, _) = finish_setup(
+            voting_server,
+            credential_authority,
+            voters,
+            sample_question(false),
+        );
+        (voting_server, voters, trustees, check)
+    }
+
+    /// Has each voter vote for the corresponding choice, and closes the election.
+    pub(crate) fn vote<G: Group>(
+        voting_server: VotingServer<V4<G>, G>,
+        voters: Vec<Voter<voter::V1<G>, G>>,
+        choices: Vec<Vec<bool>>,
+    ) -> (VotingServer<T2<G>, G>, T2M<G>) {
+        let mut voting_server = voting_server;
+        for (voter, choice) in voters.into_iter().zip(choices) {
+            let (_, ballot) = voter.process_message(V2Mi {
+                choices: vec![choice],
+            });
+            let (new_server, message) = voting_server.process_message(ballot);
+            message.check.unwrap();
+            voting_server = new_server;
+        }
+        voting_server.process_message(EmptyMessage)
+    }
+
+    #[test]
+    fn test_weighted_tally() {
+        let weights = vec![1, 2, 3, 4];
+        let (voting_server, voters, trustees) = setup_election(weights, 3);
+        // Voters are handed passwords in order, so voter i has weight weights[i].
+        let choices = vec![
+            vec![true, false, false],
+            vec![false, true, false],
+            vec![false, true, false],
+            vec![false, false, false],
+        ];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        assert_eq!(encrypted_tally.num_tallied, 4);
+        let partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![1, 5, 0]]);
+    }
+
+    #[test]
+    fn test_total_weight_too_large() {
+        let (voting_server, _, mut trustees) = setup_election(vec![u128::MAX, 1], 1);
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let (_, partial_decryption) = trustees.remove(0).process_message(encrypted_tally);
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(vec![partial_decryption]));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert!(matches!(
+            message.result,
+            Err(ProtocolError::DLogBoundTooLargeError)
+        ));
+    }
+
+    #[test]
+    fn test_trustee_keys_bound_to_election() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (voting_server, _, _) = setup_voters::<Ristretto>(rng.clone(), vec![1]);
+        let (other_server, _, _) = setup_voters::<Ristretto>(rng.clone(), vec![1]);
+        // The key of the trustee 0 of another election, and a key sent at the wrong index.
+        let (_, other_election) = new_trustee(rng.clone(), &other_server, 0);
+        let (_, wrong_index) = new_trustee(rng.clone(), &voting_server, 0);
+        let (_, honest) = new_trustee(rng.clone(), &voting_server, 2);
+        let message = E9M::from(vec![other_election, wrong_index, honest]);
+        let (_, message) = voting_server.process_message(message);
+        match message.check {
+            Err(ProtocolError::TrusteePKProofFailedError(cheaters)) => {
+                assert_eq!(cheaters, vec![0, 1])
+            }
+            _ => panic!("The trustee keys of another context were accepted"),
+        }
+    }
+
+    #[test]
+    fn test_blank_tally() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (voting_server, credential_authority, voters) =
+            setup_voters::<Ristretto>(rng.clone(), vec![1, 2, 3, 4]);
+        let (trustee, trustee_key) = new_trustee(rng.clone(), &voting_server, 0);
+        let (voting_server, message) = voting_server.process_message(E9M::from(vec![trustee_key]));
+        message.check.unwrap();
+        let (voting_server, voters, _) = finish_setup(
+            voting_server,
+            credential_authority,
+            voters,
+            sample_question(true),
+        );
+        let choices = vec![
+            vec![false, false, false],
+            vec![false, true, false],
+            vec![false, false, true],
+            vec![false, false, false],
+        ];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        let (_, partial_decryption) = trustee.process_message(encrypted_tally);
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(vec![partial_decryption]));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        // The blank votes are counted first.
+        assert_eq!(message.result.unwrap(), vec![vec![5, 0, 2, 3]]);
+    }
+
+    #[test]
+    fn test_belenios_2048_tally() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (voting_server, credential_authority, voters) =
+            setup_voters::<Belenios2048>(rng.clone(), vec![1, 2]);
+        let (trustees, trustee_keys): (Vec<_>, Vec<E9Mi<Belenios2048>>) = (0..2)
+            .map(|i| new_trustee(rng.clone(), &voting_server, i))
+            .unzip();
+        let (voting_server, message) = voting_server.process_message(E9M::from(trustee_keys));
+        message.check.unwrap();
+        let (voting_server, voters, _) = finish_setup(
+            voting_server,
+            credential_authority,
+            voters,
+            sample_question(true),
+        );
+        assert_eq!(voting_server.state.election.group, "BELENIOS-2048");
+        let choices = vec![vec![false, true, false], vec![false, false, false]];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        let partial_decryptions: Vec<T3Mi<Belenios2048>> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![2, 0, 1, 0]]);
+    }
+
+    #[test]
+    fn test_malformed_partial_decryption() {
+        let (voting_server, _, trustees) = setup_election(vec![1, 1], 2);
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let mut partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        partial_decryptions[1].decryption_factors[0].pop();
+        let (_, message) = voting_server.process_message(T3M::from(partial_decryptions));
+        match message.check {
+            Err(ProtocolError::PartialDecryptionMalformedError(cheaters)) => {
+                assert_eq!(cheaters, vec![1])
+            }
+            _ => panic!("The malformed partial decryption was not detected"),
+        }
+    }
+
+    #[test]
+    fn test_combine_malformed_partial_decryptions() {
+        let (voting_server, _, trustees) = setup_election(vec![1, 1], 3);
+        let (_, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let tally = &encrypted_tally.encrypted_tally;
+        let mut partial_decryptions: Vec<Option<T3Mi>> = trustees
+            .into_iter()
+            .map(|t| Some(t.process_message(encrypted_tally.clone()).1))
+            .collect();
+        // Partial decryptions with an extra question, and with an answer missing.
+        let pd = partial_decryptions[0].as_mut().unwrap();
+        pd.decryption_factors.push(vec![Point::generator()]);
+        let pd = partial_decryptions[2].as_mut().unwrap();
+        pd.decryption_factors[0].pop();
+        for threshold in [None, Some(2)] {
+            match combine_partial_decryptions(tally, &partial_decryptions, threshold) {
+                Err(ProtocolError::PartialDecryptionMalformedError(malformed)) => {
+                    assert_eq!(malformed, vec![0, 2])
+                }
+                _ => panic!("The malformed partial decryptions were combined"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_partial_decryptions_incorrect_len() {
+        let (voting_server, _, trustees) = setup_election(vec![1, 1], 2);
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let mut partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        // A forged partial decryption for a third trustee, which is never checked.
+        let mut forged = partial_decryptions[0].clone();
+        forged.decryption_factors[0][0] = forged.decryption_factors[0][0] + Point::generator();
+        partial_decryptions.push(forged);
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        assert!(matches!(
+            message.check,
+            Err(ProtocolError::IncorrectLenError)
+        ));
+        // None of the unchecked partial decryptions are kept.
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert!(matches!(
+            message.result,
+            Err(ProtocolError::NotEnoughPartialDecryptionsError)
+        ));
+    }
+
+    #[test]
+    fn test_forged_partial_decryption() {
+        let (voting_server, _, trustees) = setup_election(vec![1, 1], 3);
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let mut partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        // Trustee 2 shifts the decrypted value of the first answer by one vote.
+        let factor = partial_decryptions[2].decryption_factors[0][0];
+        partial_decryptions[2].decryption_factors[0][0] = factor - Point::generator();
+        let (_, message) = voting_server.process_message(T3M::from(partial_decryptions));
+        match message.check {
+            Err(ProtocolError::PartialDecryptionProofFailedError(cheaters)) => {
+                assert_eq!(cheaters, vec![2])
+            }
+            _ => panic!("The forged partial decryption was not detected"),
+        }
+    }
+
+    #[test]
+    fn test_threshold_tally() {
+        let (voting_server, voters, trustees, check) =
+            setup_pedersen_election(vec![1, 1, 1], 3, 5, |_| {}, |_| {}, |_| {});
+        check.check.unwrap();
+        let choices = vec![
+            vec![false, true, false],
+            vec![false, true, false],
+            vec![true, false, false],
+        ];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        // Only trustees 0, 2 and 4 take part in the decryption.
+        let partial_decryptions: Vec<Option<T3Mi>> = trustees
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let (_, pd) = t.process_message(encrypted_tally.clone());
+                if i % 2 == 0 {
+                    Some(pd)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (voting_server, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![1, 2, 0]]);
+        // The election key is the interpolation of the trustees' key shares.
+        let report = verify_election(voting_server.bulletin_board().entries());
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_threshold_too_few_trustees() {
+        let (voting_server, voters, trustees, check) =
+            setup_pedersen_election(vec![1, 1], 3, 4, |_| {}, |_| {}, |_| {});
+        check.check.unwrap();
+        let choices = vec![vec![true, false, false], vec![false, false, true]];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        let partial_decryptions: Vec<Option<T3Mi>> = trustees
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let (_, pd) = t.process_message(encrypted_tally.clone());
+                if i < 2 {
+                    Some(pd)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert!(matches!(
+            message.result,
+            Err(ProtocolError::NotEnoughPartialDecryptionsError)
+        ));
+    }
+
+    #[test]
+    fn test_threshold_complaints() {
+        // Dealer 1 sends bad shares to trustees 0 and 2, but answers the complaints correctly.
+        // Dealer 3 sends a bad share to trustee 0, and does not answer the complaint correctly.
+        let tamper_dealings = |dealings: &mut [P2Mi]| {
+            dealings[1].shares[0].data[0] ^= 1;
+            dealings[1].shares[2].data[0] ^= 1;
+            dealings[3].shares[0].data[0] ^= 1;
+        };
+        let tamper_responses = |responses: &mut [P5Mi]| {
+            assert_eq!(responses[1].revealed.len(), 2);
+            assert_eq!(responses[3].revealed.len(), 1);
+            responses[3].revealed[0].1 = responses[3].revealed[0].1 + Scalar::one();
+        };
+        let (voting_server, voters, trustees, check) =
+            setup_pedersen_election(vec![2, 3], 2, 4, tamper_dealings, |_| {}, tamper_responses);
+        match check.check {
+            Err(ProtocolError::DealerDisqualifiedError(dealers)) => assert_eq!(dealers, vec![3]),
+            _ => panic!("The cheating dealer was not disqualified"),
+        }
+        // The remaining dealers still define a working key, including for the trustees that
+        // only got dealer 1's shares after complaining.
+        let choices = vec![vec![false, false, true], vec![false, false, true]];
+        let (voting_server, encrypted_tally) = vote(voting_server, voters, choices);
+        let partial_decryptions: Vec<Option<T3Mi>> = trustees
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| {
+                let (_, pd) = t.process_message(encrypted_tally.clone());
+                if i == 0 || i == 2 {
+                    Some(pd)
+                } else {
+                    None
+                }
+            })
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![0, 0, 5]]);
+    }
+
+    #[test]
+    fn test_threshold_dropped_complaint() {
+        // Dealer 1 sends a bad share to trustee 0, whose complaint is not relayed, so that
+        // dealer 1 stays qualified while trustee 0 has no valid share from it.
+        let tamper_dealings = |dealings: &mut [P2Mi]| dealings[1].shares[0].data[0] ^= 1;
+        let tamper_complaints = |complaints: &mut [P4Mi]| {
+            assert_eq!(complaints[0].complaints, vec![1]);
+            complaints[0].complaints.clear();
+        };
+        let (_, _, _, check) =
+            setup_pedersen_election(vec![1], 2, 3, tamper_dealings, tamper_complaints, |_| {});
+        match check.check {
+            Err(ProtocolError::TrusteePKProofFailedError(trustees)) => {
+                assert_eq!(trustees, vec![0])
+            }
+            _ => panic!("The trustee derived a key share without a share of dealer 1"),
+        }
+    }
+
+    #[test]
+    fn test_ballot_signatures() {
+        let (voting_server, voters, _) = setup_election(vec![1, 1], 1);
+        let mut ballots: Vec<V3Mi> = voters
+            .into_iter()
+            .map(|v| {
+                let choices = vec![vec![true, false, false]];
+                v.process_message(V2Mi { choices }).1
+            })
+            .collect();
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let expect_signature_error = |check: Result<(), ProtocolError>| match check {
+            Err(ProtocolError::BallotSignatureError) => (),
+            _ => panic!("A badly signed ballot was accepted"),
+        };
+        // An unsigned ballot.
+        let mut unsigned = ballots[0].vote.clone();
+        unsigned.signature = None;
+        let (voting_server, message) = voting_server.process_message(V3Mi { vote: unsigned });
+        expect_signature_error(message.check);
+        // A ballot submitted for someone else's credential, signed with another key.
+        let mut forged = ballots[0].vote.clone();
+        forged.sign(rng.clone(), Scalar::sample_uniform(rng.clone()));
+        let (voting_server, message) = voting_server.process_message(V3Mi { vote: forged });
+        expect_signature_error(message.check);
+        // A signed ballot whose answers were replaced after signing.
+        let mut modified = ballots[0].vote.clone();
+        modified.answers = ballots[1].vote.answers.clone();
+        let (voting_server, message) = voting_server.process_message(V3Mi { vote: modified });
+        expect_signature_error(message.check);
+        // The honest ballots are still accepted.
+        let (voting_server, message) = voting_server.process_message(ballots.remove(0));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(ballots.remove(0));
+        message.check.unwrap();
+    }
+
+    #[test]
+    fn test_revote_policies() {
+        for (policy, expected) in [
+            (RevotePolicy::Replace, vec![0, 1, 2]),
+            (RevotePolicy::KeepFirst, vec![1, 0, 2]),
+            (RevotePolicy::Reject, vec![1, 0, 2]),
+        ] {
+            let (mut voting_server, mut voters, mut trustees) = setup_election(vec![1, 2], 1);
+            voting_server.state.revote_policy = policy;
+            let voter = voters.remove(0);
+            let (voter, first) = voter.process_message(V2Mi {
+                choices: vec![vec![true, false, false]],
+            });
+            let (_, second) = voter.process_message(V2Mi {
+                choices: vec![vec![false, true, false]],
+            });
+            let (_, other) = voters.remove(0).process_message(V2Mi {
+                choices: vec![vec![false, false, true]],
+            });
+            let (voting_server, message) = voting_server.process_message(first);
+            message.check.unwrap();
+            let (voting_server, message) = voting_server.process_message(second);
+            match (policy, message.check) {
+                (RevotePolicy::Reject, Err(ProtocolError::CredentialUsedTwiceError)) => {
+                    assert_eq!(voting_server.state.discarded_ballots.len(), 0)
+                }
+                (RevotePolicy::Reject, _) => panic!("The second ballot was not rejected"),
+                (RevotePolicy::KeepFirst, Err(ProtocolError::BallotDiscardedError)) => {
+                    assert_eq!(voting_server.state.discarded_ballots.len(), 1)
+                }
+                (RevotePolicy::KeepFirst, _) => panic!("The second ballot was not discarded"),
+                (_, check) => {
+                    check.unwrap();
+                    assert_eq!(voting_server.state.discarded_ballots.len(), 1)
+                }
+            }
+            let (voting_server, message) = voting_server.process_message(other);
+            message.check.unwrap();
+            let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+            // Exactly one ballot per credential is tallied.
+            assert_eq!(encrypted_tally.num_tallied, 2);
+            let (_, partial_decryption) = trustees.remove(0).process_message(encrypted_tally);
+            let (voting_server, message) =
+                voting_server.process_message(T3M::from(vec![partial_decryption]));
+            message.check.unwrap();
+            let (_, message) = voting_server.process_message(EmptyMessage);
+            assert_eq!(message.result.unwrap(), vec![expected]);
+        }
+    }
+
+    #[test]
+    fn test_credential_recovery() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let election = E1MBuilder::default().voters(vec![1, 2]).build().unwrap();
+        let (server_admin, message_SA) =
+            ServerAdmin::new(rng.clone()).process_message(election.clone());
+        let (voting_server, message_VS) = VotingServer::new(rng.clone()).process_message(election);
+        let (credential_authority, passwords) = CredentialAuthority::new(rng.clone())
+            .process_message(E3M::from((message_VS, message_SA)));
+        // The server administrator only learns what it takes to check recovery codes.
+        let (server_admin, message) = server_admin.process_message(E4M_CA_to_SA::from(&passwords));
+        message.check.unwrap();
+        let passwords: Vec<E4Mi> = passwords.into();
+        let recovery_codes: Vec<Password> = passwords
+            .iter()
+            .map(|p| p.recovery_code().clone())
+            .collect();
+        let voters: Vec<_> = passwords
+            .into_iter()
+            .map(|pass| Voter::new(rng.clone()).process_message(pass).0)
+            .collect();
+        let (credential_authority, message) = credential_authority.process_message(EmptyMessage);
+        let (voting_server, message) = voting_server.process_message(message);
+        message.check.unwrap();
+        let (trustee, trustee_key) = new_trustee(rng.clone(), &voting_server, 0);
+        let (voting_server, message) = voting_server.process_message(E9M::from(vec![trustee_key]));
+        message.check.unwrap();
+        let (voting_server, mut voters, credential_authority) = finish_setup(
+            voting_server,
+            credential_authority,
+            voters,
+            sample_question(false),
+        );
+        // The first voter votes, and then loses their password.
+        let (voter, lost) = voters.remove(0).process_message(V2Mi {
+            choices: vec![vec![true, false, false]],
+        });
+        let lost = lost.vote;
+        let (voting_server, message) = voting_server.process_message(V3Mi { vote: lost.clone() });
+        message.check.unwrap();
+        // Only registered voters may recover their credential, with their own recovery code.
+        let request = |voter: usize, code: &Password| C1M {
+            voter,
+            recovery_code: code.clone(),
+        };
+        let (server_admin, message) = server_admin.process_message(request(2, &recovery_codes[0]));
+        assert!(matches!(
+            message.request,
+            Err(ProtocolError::UnknownVoterError)
+        ));
+        let (server_admin, message) = server_admin.process_message(request(0, &recovery_codes[1]));
+        assert!(matches!(
+            message.request,
+            Err(ProtocolError::RecoveryCodeError)
+        ));
+        let (credential_authority, refused) = credential_authority.process_message(message);
+        assert!(matches!(
+            refused.recovery,
+            Err(ProtocolError::RecoveryCodeError)
+        ));
+        // Nor does the credential authority replace a credential without the recovery code,
+        // e.g. if the server administrator is dishonest.
+        let forged = C2M {
+            request: Ok(request(0, &recovery_codes[1])),
+        };
+        let (credential_authority, refused) = credential_authority.process_message(forged);
+        assert!(matches!(
+            refused.recovery,
+            Err(ProtocolError::RecoveryCodeError)
+        ));
+        let replayed = request(0, &recovery_codes[0]);
+        let (server_admin, message) = server_admin.process_message(replayed.clone());
+        let (credential_authority, message) = credential_authority.process_message(message);
+        let (password, update, verifier) = message.recovery.unwrap();
+        assert_eq!(update.old_credential, lost.credential);
+        // The recovery code is used up, and the voter gets a new one.
+        assert_ne!(
+            password.recovery_code().to_string(),
+            recovery_codes[0].to_string()
+        );
+        let (server_admin, message) = server_admin.process_message(verifier);
+        message.check.unwrap();
+        let (server_admin, message) = server_admin.process_message(replayed.clone());
+        assert!(matches!(
+            message.request,
+            Err(ProtocolError::RecoveryCodeError)
+        ));
+        let forged = C2M {
+            request: Ok(replayed),
+        };
+        let (_, refused) = credential_authority.process_message(forged);
+        assert!(matches!(
+            refused.recovery,
+            Err(ProtocolError::RecoveryCodeError)
+        ));
+        // While the new recovery code is accepted in turn.
+        let (_, message) = server_admin.process_message(request(0, password.recovery_code()));
+        message.request.unwrap();
+        let (voting_server, message) = voting_server.process_message(update.clone());
+        message.check.unwrap();
+        // The replacement is logged, and the ballot of the old credential no longer counts.
+        assert_eq!(voting_server.state.credential_updates, vec![update.clone()]);
+        assert_eq!(voting_server.state.discarded_ballots.len(), 1);
+        let (voting_server, message) = voting_server.process_message(update);
+        assert!(matches!(
+            message.check,
+            Err(ProtocolError::DuplicateCredentialError)
+        ));
+        // The old credential is rejected, while the new one is accepted.
+        let (voting_server, message) = voting_server.process_message(V3Mi { vote: lost });
+        assert!(matches!(
+            message.check,
+            Err(ProtocolError::CredentialNotFoundError)
+        ));
+        let (voter, _) = voter.process_message(password);
+        let (_, ballot) = voter.process_message(V2Mi {
+            choices: vec![vec![false, true, false]],
+        });
+        let (voting_server, message) = voting_server.process_message(ballot);
+        message.check.unwrap();
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        assert_eq!(encrypted_tally.num_tallied, 1);
+        let (_, partial_decryption) = trustee.process_message(encrypted_tally);
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(vec![partial_decryption]));
+        message.check.unwrap();
+        let (_, message) = voting_server.process_message(EmptyMessage);
+        assert_eq!(message.result.unwrap(), vec![vec![0, 1, 0]]);
+    }
+
+    #[test]
+    fn test_bulletin_board() {
+        let (voting_server, mut voters, mut trustees) = setup_election(vec![1, 2], 1);
+        let (voter, first) = voters.remove(0).process_message(V2Mi {
+            choices: vec![vec![true, false, false]],
+        });
+        let (_, second) = voter.process_message(V2Mi {
+            choices: vec![vec![false, true, false]],
+        });
+        let (voting_server, _) = voting_server.process_message(first);
+        let (voting_server, _) = voting_server.process_message(second);
+        let voting_head = voting_server.bulletin_board().head().to_vec();
+        let (voting_server, encrypted_tally) =
+            vote(voting_server, voters, vec![vec![false, false, true]]);
+        let (_, partial_decryption) = trustees.remove(0).process_message(encrypted_tally);
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(vec![partial_decryption]));
+        message.check.unwrap();
+        let (voting_server, _) = voting_server.process_message(EmptyMessage);
+        let board = voting_server.bulletin_board();
+        let entries = BulletinBoard::import(&board.export()).unwrap();
+        assert_eq!(BulletinBoard::verify(&entries).unwrap(), board.head());
+        // Both ballots of the first voter are published, the latter replacing the former.
+        let events: Vec<&Event<Ristretto>> = entries.iter().map(|e| &e.event).collect();
+        assert!(matches!(
+            events[..],
+            [
+                Event::Election(_),
+                Event::Voters(_),
+                Event::TrusteeKeys { .. },
+                Event::Ballot(_),
+                Event::Ballot(_),
+                Event::Ballot(_),
+                Event::EncryptedTally(_),
+                Event::PartialDecryptions(_),
+                Event::Result(_),
+            ]
+        ));
+        // The log published during the voting phase is a prefix of the final one.
+        assert_eq!(BulletinBoard::verify(&entries[..5]).unwrap(), voting_head);
+    }
 }
@@ -0,0 +1,395 @@
+//! The BELENIOS-2048 group of the specification, e.g. the subgroup of order q of the
+//! multiplicative group of integers mod a 2048-bit prime p, which is the default group of the
+//! reference (OCaml) implementation.
+//!
+//! The `Belenios2048` instance of `Group` is built from the `FFElement` and `FFScalar` types,
+//! which write the group operation additively, e.g. `x + y` is the product of x and y mod p, and
+//! `x * g` is g to the power x.
+//!
+//! We check the parameters against the (decimal) ones published in the specification, see
+//! `tests/vectors/belenios-2048.json`, check that they are consistent (p and q are prime, q
+//! divides p - 1, and g has order q), and compare against values computed independently of this
+//! implementation.
+//! Proofs made with `belenios-tool` do not verify yet, as the challenges are not hashed as in
+//! the specification, see the README.
+
+use crate::primitives::group::Group;
+use crate::primitives::rng::SecureRandom;
+use crypto_bigint::modular::constant_mod::{Residue, ResidueParams};
+use crypto_bigint::{impl_modulus, Encoding, U2048, U256};
+use ring::digest;
+use serde::{Deserialize, Serialize};
+use std::convert::{TryFrom, TryInto};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::{Arc, Mutex};
+
+/// The name of the group, as in the `group` field of an election.
+pub const GROUP_NAME: &str = "BELENIOS-2048";
+
+impl_modulus!(
+    P,
+    U2048,
+    "a3ef2d011fefb1b6e39e568fccdffb0fb7dc7ef3a791b6862791811d21fc35f21e6d36856b428030421f514ed40bfd4c06e092a495af8d79a2f85e0fb11c0ede\
+     07dd973f9c53e50930c6e82ac36de1ae3fb8c3aabfff12ce27a5022ddd8912d107435012768a2cdd0a4091d8fa447f6c7ebc15a349fa318a6b4fe17d8d47e843\
+     a3e14dfa9b2162f4d7229abe93a188b46b006517d6f6747ebbb29c9ec3099b607f18fb1a8bc858279aa8599411d998d3e3fada9e010840d082d8c31911acdd4a\
+     0e7992d0f18cc759cb5de0acf66a3882555bc810941e021b6a4ccfa04ee4008f878affa2375e2999b44f6cef17d707301e59e0e6351e051ed9cec4157646e64f"
+);
+
+impl_modulus!(
+    Q,
+    U256,
+    "adb6066a73522565f4daa0f2579db7f2f0fa90c2168bc709e20263387d55c491"
+);
+
+const G: U2048 = U2048::from_be_hex(
+    "1307c1ab5f295885d7caa3a78a42415e31fd1c5807b61fb1e781878a52230a51988b98c259597f9c5a9dfd001d26ba31d9d8976f01d95a1c8262ee68a0e92cea\
+         541e5219e0d0d094301b894236823fe1d4bc04e29f3748aefd4f10d5b1a356a8643e673fd9d215fc8fe5fb9cf9fdb8ea88fdb1a3381a697fdf9c2458570b06d2\
+         6aef1be365fdd90165f397c6b870df8c1b9e519edcee8bb0f74b7a1615a274ba60a7ea574a61f552d2dde0ce514e0927c4ff49b1964c4a913731ae4268d60d7f\
+         22127b6cbba44d453ac8b72fd04e0991b7aeff04867d8724ac638f5f34100d2d47aa680d43771902743d663f42d853c041e1c91fe3823f4bcf284308f1d7e653",
+);
+
+type ResidueP = Residue<P, { U2048::LIMBS }>;
+type ResidueQ = Residue<Q, { U256::LIMBS }>;
+
+#[derive(Clone, PartialEq, Debug, Copy, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub struct FFElement(ResidueP);
+
+impl FFElement {
+    pub fn identity() -> Self {
+        Self(ResidueP::ONE)
+    }
+    pub fn generator() -> Self {
+        Self(ResidueP::new(&G))
+    }
+    /// The big-endian encoding of the element as an integer in [1, p - 1].
+    pub fn as_bytes(&self) -> [u8; 256] {
+        self.0.retrieve().to_be_bytes()
+    }
+    /// The inverse of `as_bytes`, which fails for integers that are not in the subgroup of order
+    /// q.
+    pub fn from_bytes(bytes: [u8; 256]) -> Option<Self> {
+        let x = U2048::from_be_bytes(bytes);
+        if x == U2048::ZERO || x >= P::MODULUS {
+            return None;
+        }
+        let x = ResidueP::new(&x);
+        if x.pow(&Q::MODULUS) != ResidueP::ONE {
+            return None;
+        }
+        Some(Self(x))
+    }
+    pub fn sample_uniform(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
+        FFScalar::sample_uniform(rng) * Self::generator()
+    }
+}
+
+impl TryFrom<Vec<u8>> for FFElement {
+    type Error = String;
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        <[u8; 256]>::try_from(bytes)
+            .ok()
+            .and_then(Self::from_bytes)
+            .ok_or_else(|| format!("not an element of {}", GROUP_NAME))
+    }
+}
+
+impl From<FFElement> for Vec<u8> {
+    fn from(x: FFElement) -> Self {
+        x.as_bytes().to_vec()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Copy, Serialize, Deserialize)]
+#[serde(try_from = "Vec<u8>", into = "Vec<u8>")]
+pub struct FFScalar(ResidueQ);
+
+impl FFScalar {
+    pub fn zero() -> Self {
+        Self(ResidueQ::ZERO)
+    }
+    pub fn one() -> Self {
+        Self(ResidueQ::ONE)
+    }
+    /// The big-endian encoding of the scalar as an integer in [0, q - 1].
+    pub fn as_bytes(&self) -> [u8; 32] {
+        self.0.retrieve().to_be_bytes()
+    }
+    /// Samples uniformly from [0, q - 1] by rejection sampling.
+    pub fn sample_uniform(rng: Arc<Mutex<dyn SecureRandom>>) -> FFScalar {
+        let mut buff = [0u8; 32];
+        loop {
+            rng.lock().unwrap().fill(&mut buff).unwrap();
+            if let Some(x) = Self::from_canonical_bytes(buff) {
+                return x;
+            }
+        }
+    }
+    /// Interprets the SHA256 hash of data as an integer mod q, as in the specification.
+    pub fn hash_to_scalar(data: &[u8]) -> FFScalar {
+        let hash = digest::digest(&digest::SHA256, data);
+        let mut collected_hash = [0; digest::SHA256_OUTPUT_LEN];
+        collected_hash.copy_from_slice(hash.as_ref());
+        Self::from_bytes_mod_order(collected_hash)
+    }
+    pub fn from_bytes_mod_order(bytes: [u8; 32]) -> FFScalar {
+        Self(ResidueQ::new(&U256::from_be_bytes(bytes)))
+    }
+    /// The inverse of `as_bytes`, which fails for integers that are not reduced mod q.
+    pub fn from_canonical_bytes(bytes: [u8; 32]) -> Option<FFScalar> {
+        let x = U256::from_be_bytes(bytes);
+        if x >= Q::MODULUS {
+            return None;
+        }
+        Some(Self(ResidueQ::new(&x)))
+    }
+    /// The multiplicative inverse, which is zero for zero.
+    pub fn invert(&self) -> FFScalar {
+        Self(self.0.invert().0)
+    }
+}
+
+impl TryFrom<Vec<u8>> for FFScalar {
+    type Error = String;
+    fn try_from(bytes: Vec<u8>) -> Result<Self, Self::Error> {
+        <[u8; 32]>::try_from(bytes)
+            .ok()
+            .and_then(Self::from_canonical_bytes)
+            .ok_or_else(|| format!("not a scalar of {}", GROUP_NAME))
+    }
+}
+
+impl From<FFScalar> for Vec<u8> {
+    fn from(x: FFScalar) -> Self {
+        x.as_bytes().to_vec()
+    }
+}
+
+impl From<u128> for FFScalar {
+    fn from(inp: u128) -> Self {
+        Self(ResidueQ::new(&U256::from_u128(inp)))
+    }
+}
+
+impl Neg for FFElement {
+    type Output = FFElement;
+    fn neg(self) -> Self::Output {
+        Self(self.0.invert().0)
+    }
+}
+
+// The group operation is written additively.
+#[allow(clippy::suspicious_arithmetic_impl)]
+impl Add<FFElement> for FFElement {
+    type Output = FFElement;
+    fn add(self, rhs: FFElement) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+impl Sub<FFElement> for FFElement {
+    type Output = FFElement;
+    fn sub(self, rhs: FFElement) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl Mul<FFScalar> for FFElement {
+    type Output = FFElement;
+    fn mul(self, rhs: FFScalar) -> Self::Output {
+        Self(self.0.pow(&rhs.0.retrieve()))
+    }
+}
+
+impl Mul<FFElement> for FFScalar {
+    type Output = FFElement;
+    fn mul(self, rhs: FFElement) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl Add<FFScalar> for FFScalar {
+    type Output = FFScalar;
+    fn add(self, rhs: FFScalar) -> Self::Output {
+        Self(self.0 + rhs.0)
+    }
+}
+
+impl Sub<FFScalar> for FFScalar {
+    type Output = FFScalar;
+    fn sub(self, rhs: FFScalar) -> Self::Output {
+        Self(self.0 - rhs.0)
+    }
+}
+
+impl Mul<FFScalar> for FFScalar {
+    type Output = FFScalar;
+    fn mul(self, rhs: FFScalar) -> Self::Output {
+        Self(self.0 * rhs.0)
+    }
+}
+
+impl Neg for FFScalar {
+    type Output = FFScalar;
+    fn neg(self) -> Self::Output {
+        Self(-self.0)
+    }
+}
+
+/// The BELENIOS-2048 group.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Belenios2048;
+
+impl Group for Belenios2048 {
+    type Element = FFElement;
+    type Exponent = FFScalar;
+    const NAME: &'static str = GROUP_NAME;
+
+    fn identity() -> Self::Element {
+        FFElement::identity()
+    }
+    fn generator() -> Self::Element {
+        FFElement::generator()
+    }
+    fn sample_element(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Element {
+        FFElement::sample_uniform(rng)
+    }
+    fn element_to_bytes(x: &Self::Element) -> Vec<u8> {
+        x.as_bytes().to_vec()
+    }
+    fn element_from_bytes(bytes: &[u8]) -> Option<Self::Element> {
+        FFElement::from_bytes(bytes.try_into().ok()?)
+    }
+
+    fn zero() -> Self::Exponent {
+        FFScalar::zero()
+    }
+    fn one() -> Self::Exponent {
+        FFScalar::one()
+    }
+    fn sample_scalar(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Exponent {
+        FFScalar::sample_uniform(rng)
+    }
+    // The trait encodes scalars in little-endian order.
+    fn scalar_to_bytes(x: &Self::Exponent) -> Vec<u8> {
+        x.as_bytes().iter().rev().cloned().collect()
+    }
+    fn scalar_from_bytes(bytes: &[u8]) -> Option<Self::Exponent> {
+        let mut bytes: [u8; 32] = bytes.try_into().ok()?;
+        bytes.reverse();
+        FFScalar::from_canonical_bytes(bytes)
+    }
+    fn scalar_from_bytes_mod_order(mut bytes: [u8; 32]) -> Self::Exponent {
+        bytes.reverse();
+        FFScalar::from_bytes_mod_order(bytes)
+    }
+    fn hash_to_scalar(data: &[u8]) -> Self::Exponent {
+        FFScalar::hash_to_scalar(data)
+    }
+    fn invert(x: &Self::Exponent) -> Self::Exponent {
+        x.invert()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    fn decode_hex<const N: usize>(s: &str) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&s[2 * i..2 * i + 2], 16).unwrap();
+        }
+        bytes
+    }
+
+    fn decode_decimal(s: &str) -> U2048 {
+        s.bytes().fold(U2048::ZERO, |x, d| {
+            x.wrapping_mul(&U2048::from_u8(10))
+                .wrapping_add(&U2048::from_u8(d - b'0'))
+        })
+    }
+
+    #[test]
+    fn test_specification_vectors() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../../tests/vectors/belenios-2048.json")).unwrap();
+        assert_eq!(vectors["group"], GROUP_NAME);
+        let p = decode_decimal(vectors["p"].as_str().unwrap());
+        assert_eq!(p, P::MODULUS);
+        let q = decode_decimal(vectors["q"].as_str().unwrap());
+        assert_eq!(q, U2048::ZERO.wrapping_add(&Q::MODULUS.resize()));
+        let g = decode_decimal(vectors["g"].as_str().unwrap());
+        assert_eq!(g, G);
+        assert_eq!(FFElement::generator().as_bytes(), g.to_be_bytes());
+    }
+
+    #[test]
+    fn test_parameters() {
+        // Fermat tests for p and q, with base 2.
+        let two = ResidueP::new(&U2048::from_u8(2));
+        assert_eq!(
+            two.pow(&P::MODULUS.wrapping_sub(&U2048::ONE)),
+            ResidueP::ONE
+        );
+        let two = ResidueQ::new(&U256::from_u8(2));
+        assert_eq!(two.pow(&Q::MODULUS.wrapping_sub(&U256::ONE)), ResidueQ::ONE);
+        // q divides p - 1.
+        let q = U2048::ZERO.wrapping_add(&Q::MODULUS.resize());
+        let (_, rem) = P::MODULUS
+            .wrapping_sub(&U2048::ONE)
+            .div_rem(&crypto_bigint::NonZero::new(q).unwrap());
+        assert_eq!(rem, U2048::ZERO);
+        // g has order q.
+        let g = FFElement::generator();
+        assert_ne!(g, FFElement::identity());
+        assert_eq!(FFElement::from_bytes(g.as_bytes()), Some(g));
+        assert_eq!(g.0.pow(&Q::MODULUS), ResidueP::ONE);
+    }
+
+    #[test]
+    fn test_known_answers() {
+        // Computed independently, with arbitrary precision integers.
+        let g_12345 = decode_hex::<256>(
+            "7d7390a3f388d71f5fb9ccfef5e8e4e208762f36f1b7929206afb9d9a74e95f8bbbbe48a306c1aa7372b8801f857ceec\
+             a214ada49c7a4ed1318452b03b71dfbc0f49df1418eb4c9492d198c1d80e242827a9866535c19b19532cdae98a04b3a2\
+             e4799d0b28382f212a6b9b6f4bfbde525e82779d6fecaa5751544a2d47bdff2b1703273ea2a868b351d486153e53d130\
+             609ba432586f58c0aeb808f9ccdc6a06c99b1cb4b71741f34e6fc1fc2db946ba257b6e8c8359d8d785f525e2d7640469\
+             0d40ab310f92b59b0cf772496e44b87068111a0e007a3af7243e836433872bc2614cbb1d1c1a2aa2e8943cb3394108b3\
+             5a76eb529adc87335dfc80e1c3db47b3",
+        );
+        let expected = FFElement::from_bytes(g_12345).unwrap();
+        assert_eq!(FFScalar::from(12345) * FFElement::generator(), expected);
+        let hash =
+            decode_hex::<32>("084518462a06bba4bc21643a6545b628bafed5d646b6047ad0dca57453a134af");
+        assert_eq!(FFScalar::hash_to_scalar(b"belenios").as_bytes(), hash);
+        let max = [0xff; 32];
+        let reduced =
+            decode_hex::<32>("5249f9958cadda9a0b255f0da862480d0f056f3de97438f61dfd9cc782aa3b6e");
+        assert_eq!(FFScalar::from_bytes_mod_order(max).as_bytes(), reduced);
+        assert_eq!(FFScalar::from_canonical_bytes(max), None);
+    }
+
+    #[test]
+    fn test_group_laws() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let g = FFElement::generator();
+        let a = FFScalar::sample_uniform(rng.clone());
+        let b = FFScalar::sample_uniform(rng.clone());
+        assert_eq!((a + b) * g, a * g + b * g);
+        assert_eq!((a * b) * g, a * (b * g));
+        assert_eq!(a * g - a * g, FFElement::identity());
+        assert_eq!((a - a) * g, FFElement::identity());
+        assert_eq!(a * a.invert(), FFScalar::one());
+        assert_eq!(-a + a, FFScalar::zero());
+        let x = FFElement::sample_uniform(rng.clone());
+        let decoded: FFElement = bincode::deserialize(&bincode::serialize(&x).unwrap()).unwrap();
+        assert_eq!(decoded, x);
+        let decoded: FFScalar = bincode::deserialize(&bincode::serialize(&a).unwrap()).unwrap();
+        assert_eq!(decoded, a);
+        // p - 1 has order 2, so is not in the group.
+        let minus_one = P::MODULUS.wrapping_sub(&U2048::ONE);
+        assert_eq!(FFElement::from_bytes(minus_one.to_be_bytes()), None);
+    }
+}
@@ -1,146 +1,599 @@
-//! A wrapper around the Ristretto group implementation.
+//! The group in which the protocol runs.
+//!
+//! The protocol is generic over the `Group` trait, of which we provide two instances, namely
+//!   * `Ristretto`, a wrapper around the Ristretto group implementation (the default), and
+//!   * `Belenios2048`, the finite-field group of the specification, see `ff_group.rs`.
+//!
+//! The `Point` and `Scalar` types wrap the elements and scalars of a group, and default to
+//! `Ristretto`.
 
-use curve25519_dalek::ristretto::RistrettoPoint;
+use crate::primitives::rng::SecureRandom;
+use crate::ProtocolError;
+use curve25519_dalek::constants::RISTRETTO_BASEPOINT_POINT;
+use curve25519_dalek::ristretto::{CompressedRistretto, RistrettoPoint};
 use curve25519_dalek::scalar;
-use curve25519_dalek::{constants::RISTRETTO_BASEPOINT_POINT, traits::Identity};
+use curve25519_dalek::traits::{Identity, IsIdentity, VartimeMultiscalarMul};
 use ring::digest;
-use ring::rand::SecureRandom;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
-use std::ops::{Add, AddAssign, Mul, Neg, Sub};
-use std::sync::{Arc, Mutex};
+use std::any::Any;
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// The terms of a multiscalar multiplication, see `Group::vartime_multiscalar_mul`.
+pub type Terms<G> = (Vec<<G as Group>::Exponent>, Vec<<G as Group>::Element>);
+
+/// A group of prime order q, with a fixed generator g, written additively.
+pub trait Group: 'static + Clone + Copy + Debug + Default + PartialEq + Send + Sync {
+    type Element: Clone
+        + Copy
+        + Debug
+        + PartialEq
+        + Send
+        + Sync
+        + Serialize
+        + DeserializeOwned
+        + Add<Output = Self::Element>
+        + Sub<Output = Self::Element>
+        + Neg<Output = Self::Element>
+        + Mul<Self::Exponent, Output = Self::Element>;
+    type Exponent: Clone
+        + Copy
+        + Debug
+        + PartialEq
+        + Send
+        + Sync
+        + Serialize
+        + DeserializeOwned
+        + Add<Output = Self::Exponent>
+        + Sub<Output = Self::Exponent>
+        + Mul<Output = Self::Exponent>
+        + Neg<Output = Self::Exponent>
+        + From<u128>;
+    /// The name of the group, as in the `group` field of an election.
+    const NAME: &'static str;
+
+    fn identity() -> Self::Element;
+    fn generator() -> Self::Element;
+    fn sample_element(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Element;
+    /// A canonical encoding of an element, e.g. for hashing.
+    fn element_to_bytes(x: &Self::Element) -> Vec<u8>;
+    /// The inverse of `element_to_bytes`, which fails for invalid encodings.
+    fn element_from_bytes(bytes: &[u8]) -> Option<Self::Element>;
+    /// The sum of the `scalars[i] * elements[i]`, computed in variable time, e.g. to verify
+    /// proofs, which involve no secrets.
+    fn vartime_multiscalar_mul(
+        scalars: &[Self::Exponent],
+        elements: &[Self::Element],
+    ) -> Self::Element {
+        scalars
+            .iter()
+            .zip(elements.iter())
+            .fold(Self::identity(), |sum, (s, x)| sum + (*x * *s))
+    }
+    /// The encodings of many sums computed by `vartime_multiscalar_mul`, which some groups encode
+    /// faster all at once than one by one.
+    fn vartime_multiscalar_mul_to_bytes_batch(terms: &[Terms<Self>]) -> Vec<Vec<u8>> {
+        terms
+            .iter()
+            .map(|(scalars, elements)| {
+                Self::element_to_bytes(&Self::vartime_multiscalar_mul(scalars, elements))
+            })
+            .collect()
+    }
+
+    fn zero() -> Self::Exponent;
+    fn one() -> Self::Exponent;
+    fn sample_scalar(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Exponent;
+    /// The little-endian encoding of a scalar, as an integer in [0, q - 1].
+    fn scalar_to_bytes(x: &Self::Exponent) -> Vec<u8>;
+    /// The inverse of `scalar_to_bytes`, which fails for integers that are not reduced mod q.
+    fn scalar_from_bytes(bytes: &[u8]) -> Option<Self::Exponent>;
+    /// Interprets 32 bytes as a little-endian integer mod q.
+    fn scalar_from_bytes_mod_order(bytes: [u8; 32]) -> Self::Exponent;
+    /// Interprets a SHA256 hash as an integer mod q.
+    fn hash_to_scalar(data: &[u8]) -> Self::Exponent;
+    /// The multiplicative inverse, which is zero for zero.
+    fn invert(x: &Self::Exponent) -> Self::Exponent;
+}
+
+/// The Ristretto group, on top of curve25519-dalek.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ristretto;
+
+impl Group for Ristretto {
+    type Element = RistrettoPoint;
+    type Exponent = scalar::Scalar;
+    const NAME: &'static str = "RISTRETTO-25519";
+
+    fn identity() -> Self::Element {
+        RistrettoPoint::identity()
+    }
+    fn generator() -> Self::Element {
+        RISTRETTO_BASEPOINT_POINT
+    }
+    fn sample_element(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Element {
+        let mut buff = [0u8; 64];
+        rng.lock().unwrap().fill(&mut buff).unwrap();
+        RistrettoPoint::from_uniform_bytes(&buff)
+    }
+    fn element_to_bytes(x: &Self::Element) -> Vec<u8> {
+        x.compress().as_bytes().to_vec()
+    }
+    fn element_from_bytes(bytes: &[u8]) -> Option<Self::Element> {
+        CompressedRistretto(bytes.try_into().ok()?).decompress()
+    }
+    fn vartime_multiscalar_mul(
+        scalars: &[Self::Exponent],
+        elements: &[Self::Element],
+    ) -> Self::Element {
+        RistrettoPoint::vartime_multiscalar_mul(scalars, elements)
+    }
+    fn vartime_multiscalar_mul_to_bytes_batch(terms: &[Terms<Self>]) -> Vec<Vec<u8>> {
+        // Encoding a point takes an inversion, but the doubles of many points are encoded with a
+        // single one, so we compute half of each sum.
+        let half = scalar::Scalar::from(2u64).invert();
+        let halves: Vec<RistrettoPoint> = terms
+            .iter()
+            .map(|(scalars, elements)| {
+                RistrettoPoint::vartime_multiscalar_mul(scalars.iter().map(|s| s * half), elements)
+            })
+            .collect();
+        // The identity would spoil the shared inversion for the whole batch, so is encoded on its
+        // own (a prover may well make some sum the identity).
+        let others: Vec<&RistrettoPoint> = halves.iter().filter(|h| !h.is_identity()).collect();
+        let mut encoded = RistrettoPoint::double_and_compress_batch(others).into_iter();
+        halves
+            .iter()
+            .map(|h| match h.is_identity() {
+                true => CompressedRistretto::identity().as_bytes().to_vec(),
+                false => encoded.next().unwrap().as_bytes().to_vec(),
+            })
+            .collect()
+    }
+
+    fn zero() -> Self::Exponent {
+        scalar::Scalar::zero()
+    }
+    fn one() -> Self::Exponent {
+        scalar::Scalar::one()
+    }
+    fn sample_scalar(rng: Arc<Mutex<dyn SecureRandom>>) -> Self::Exponent {
+        let mut buff = [0u8; 32];
+        rng.lock().unwrap().fill(&mut buff).unwrap();
+        scalar::Scalar::from_bytes_mod_order(buff)
+    }
+    fn scalar_to_bytes(x: &Self::Exponent) -> Vec<u8> {
+        x.as_bytes().to_vec()
+    }
+    fn scalar_from_bytes(bytes: &[u8]) -> Option<Self::Exponent> {
+        scalar::Scalar::from_canonical_bytes(bytes.try_into().ok()?)
+    }
+    fn scalar_from_bytes_mod_order(bytes: [u8; 32]) -> Self::Exponent {
+        scalar::Scalar::from_bytes_mod_order(bytes)
+    }
+    fn hash_to_scalar(data: &[u8]) -> Self::Exponent {
+        let hash = digest::digest(&digest::SHA256, data);
+        let mut collected_hash = [0; digest::SHA256_OUTPUT_LEN];
+        collected_hash.copy_from_slice(hash.as_ref());
+        scalar::Scalar::from_bytes_mod_order(collected_hash)
+    }
+    fn invert(x: &Self::Exponent) -> Self::Exponent {
+        x.invert()
+    }
+}
 
 #[derive(Clone, PartialEq, Debug, Copy, Serialize, Deserialize)]
-pub struct Point(pub(crate) RistrettoPoint);
+#[serde(bound = "")]
+pub struct Point<G: Group = Ristretto>(pub(crate) G::Element);
 
-impl Point {
+impl<G: Group> Point<G> {
     pub fn identity() -> Self {
-        Self(RistrettoPoint::identity())
+        Self(G::identity())
     }
-    pub fn as_bytes(&self) -> [u8; 32] {
-        *self.0.compress().as_bytes()
+    pub fn as_bytes(&self) -> Vec<u8> {
+        G::element_to_bytes(&self.0)
+    }
+    /// The inverse of `as_bytes`, which fails for invalid encodings.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        G::element_from_bytes(bytes).map(Self)
     }
     pub fn generator() -> Self {
-        Self(RISTRETTO_BASEPOINT_POINT)
+        Self(G::generator())
     }
     pub fn sample_uniform(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
-        let mut buff = [0 as u8; 64];
-        rng.lock().unwrap().fill(&mut buff).unwrap();
-        Self(RistrettoPoint::from_uniform_bytes(&buff))
+        Self(G::sample_element(rng))
     }
 }
 
 // Would be generically good to remove the Copy
 // derive, but thats a later optimization.
 #[derive(Clone, PartialEq, Debug, Copy, Serialize, Deserialize)]
-pub struct Scalar(pub(crate) scalar::Scalar);
+#[serde(bound = "")]
+pub struct Scalar<G: Group = Ristretto>(pub(crate) G::Exponent);
 
-impl Scalar {
+impl<G: Group> Scalar<G> {
     pub fn zero() -> Self {
-        Self(scalar::Scalar::zero())
+        Self(G::zero())
     }
     pub fn one() -> Self {
-        Self(scalar::Scalar::one())
+        Self(G::one())
     }
-    pub fn as_bytes(&self) -> &[u8; 32] {
-        scalar::Scalar::as_bytes(&self.0)
+    pub fn as_bytes(&self) -> Vec<u8> {
+        G::scalar_to_bytes(&self.0)
     }
-    pub fn sample_uniform(rng: Arc<Mutex<dyn SecureRandom>>) -> Scalar {
-        let mut buff = [0 as u8; 32];
-        rng.lock().unwrap().fill(&mut buff).unwrap();
-        Self(scalar::Scalar::from_bytes_mod_order(buff))
+    pub fn sample_uniform(rng: Arc<Mutex<dyn SecureRandom>>) -> Self {
+        Self(G::sample_scalar(rng))
     }
-    pub fn hash_to_scalar(data: &[u8]) -> Scalar {
-        let hash = digest::digest(&digest::SHA256, data);
-        let mut collected_hash = [0; digest::SHA256_OUTPUT_LEN];
-        for idx in 0..digest::SHA256_OUTPUT_LEN {
-            collected_hash[idx] = hash.as_ref()[idx];
-        }
-        Self(scalar::Scalar::from_bytes_mod_order(collected_hash))
+    pub fn from_bytes_mod_order(bytes: [u8; 32]) -> Self {
+        Self(G::scalar_from_bytes_mod_order(bytes))
+    }
+    pub fn hash_to_scalar(data: &[u8]) -> Self {
+        Self(G::hash_to_scalar(data))
     }
-    pub fn from_bytes_mod_order(bytes: [u8; 32]) -> Scalar {
-        Self(scalar::Scalar::from_bytes_mod_order(bytes))
+    /// The inverse of `as_bytes`, which fails for bytes that are not reduced mod the group order.
+    pub fn from_canonical_bytes(bytes: &[u8]) -> Option<Self> {
+        G::scalar_from_bytes(bytes).map(Self)
+    }
+    /// The multiplicative inverse, which is zero for zero.
+    pub fn invert(&self) -> Self {
+        Self(G::invert(&self.0))
     }
 }
 
-impl Neg for Point {
-    type Output = Point;
+impl<G: Group> Neg for Point<G> {
+    type Output = Point<G>;
     fn neg(self) -> Self::Output {
-        Self(self.0.neg())
+        Self(-self.0)
     }
 }
 
-impl Sub<Point> for Point {
-    type Output = Point;
-    fn sub(self, rhs: Point) -> Self::Output {
-        self + (-rhs)
+impl<G: Group> Sub<Point<G>> for Point<G> {
+    type Output = Point<G>;
+    fn sub(self, rhs: Point<G>) -> Self::Output {
+        Self(self.0 - rhs.0)
     }
 }
 
-impl Add<Point> for Point {
-    type Output = Point;
-    fn add(self, rhs: Point) -> Self::Output {
+impl<G: Group> Add<Point<G>> for Point<G> {
+    type Output = Point<G>;
+    fn add(self, rhs: Point<G>) -> Self::Output {
         Self(self.0 + rhs.0)
     }
 }
 
-impl Sub<Scalar> for Scalar {
-    type Output = Scalar;
-    fn sub(self, rhs: Scalar) -> Self::Output {
+impl<G: Group> Sub<Scalar<G>> for Scalar<G> {
+    type Output = Scalar<G>;
+    fn sub(self, rhs: Scalar<G>) -> Self::Output {
         Self(self.0 - rhs.0)
     }
 }
 
-impl Mul<Scalar> for Scalar {
-    type Output = Scalar;
-    fn mul(self, rhs: Scalar) -> Self::Output {
+impl<G: Group> Mul<Scalar<G>> for Scalar<G> {
+    type Output = Scalar<G>;
+    fn mul(self, rhs: Scalar<G>) -> Self::Output {
         Self(self.0 * rhs.0)
     }
 }
 
-impl Mul<&Scalar> for Scalar {
-    type Output = Scalar;
-    fn mul(self, rhs: &Scalar) -> Self::Output {
+impl<G: Group> Mul<&Scalar<G>> for Scalar<G> {
+    type Output = Scalar<G>;
+    fn mul(self, rhs: &Scalar<G>) -> Self::Output {
         Self(self.0 * rhs.0)
     }
 }
 
-impl Mul<Scalar> for &Scalar {
-    type Output = Scalar;
-    fn mul(self, rhs: Scalar) -> Self::Output {
+impl<G: Group> Mul<Scalar<G>> for &Scalar<G> {
+    type Output = Scalar<G>;
+    fn mul(self, rhs: Scalar<G>) -> Self::Output {
         Scalar(self.0 * rhs.0)
     }
 }
 
-impl From<u128> for Scalar {
+impl<G: Group> From<u128> for Scalar<G> {
     fn from(inp: u128) -> Self {
-        Self(scalar::Scalar::from(inp))
+        Self(G::Exponent::from(inp))
     }
 }
 
-impl Add<Scalar> for Scalar {
-    type Output = Scalar;
-    fn add(self, rhs: Scalar) -> Self::Output {
+impl<G: Group> Add<Scalar<G>> for Scalar<G> {
+    type Output = Scalar<G>;
+    fn add(self, rhs: Scalar<G>) -> Self::Output {
         Self(self.0 + rhs.0)
     }
 }
 
-impl Mul<Scalar> for Point {
-    type Output = Point;
-    fn mul(self, rhs: Scalar) -> Self::Output {
+impl<G: Group> Mul<Scalar<G>> for Point<G> {
+    type Output = Point<G>;
+    fn mul(self, rhs: Scalar<G>) -> Self::Output {
         Self(self.0 * rhs.0)
     }
 }
 
-impl Mul<Point> for Scalar {
-    type Output = Point;
-    fn mul(self, rhs: Point) -> Self::Output {
-        Point(self.0 * rhs.0)
+impl<G: Group> Mul<Point<G>> for Scalar<G> {
+    type Output = Point<G>;
+    fn mul(self, rhs: Point<G>) -> Self::Output {
+        Point(rhs.0 * self.0)
     }
 }
 
-impl Neg for Scalar {
-    type Output = Scalar;
+impl<G: Group> Neg for Scalar<G> {
+    type Output = Scalar<G>;
     fn neg(self) -> Self::Output {
         Scalar(-self.0)
     }
 }
+
+/// A precomputed table for recovering m from m * g for all m in [0, bound], using the
+/// baby-step giant-step algorithm.
+///
+/// Homomorphic tallies decrypt to m * g, where m is bounded by the total weight of all voters.
+/// Building the table takes O(sqrt(bound)) time and space, after which each discrete logarithm
+/// takes O(sqrt(bound)) time.
+/// The table only depends on `bound`, so it may be serialized and reused across elections whose
+/// total weight is at most `bound`, or shared within a process with `DLogTable::cached`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "", try_from = "DLogTableData<G>")]
+pub struct DLogTable<G: Group = Ristretto> {
+    bound: u128,
+    step: u64,
+    // Maps the encoding of j * g to j, for 0 <= j < step.
+    baby_steps: HashMap<Vec<u8>, u64>,
+    group: PhantomData<G>,
+}
+
+/// The largest bound of a `DLogTable`, for which the table has about 2^20 entries.
+pub const MAX_DLOG_BOUND: u128 = 1 << 40;
+
+/// A deserialized `DLogTable`, before it is checked to be the table of its bound.
+#[derive(Deserialize)]
+#[serde(bound = "")]
+struct DLogTableData<G: Group> {
+    bound: u128,
+    step: u64,
+    baby_steps: HashMap<Vec<u8>, u64>,
+    group: PhantomData<G>,
+}
+
+impl<G: Group> TryFrom<DLogTableData<G>> for DLogTable<G> {
+    type Error = ProtocolError;
+    fn try_from(data: DLogTableData<G>) -> Result<Self, Self::Error> {
+        if data.step != dlog_step(data.bound)? || data.baby_steps.len() as u64 != data.step {
+            return Err(ProtocolError::MalformedDLogTableError);
+        }
+        Ok(DLogTable {
+            bound: data.bound,
+            step: data.step,
+            baby_steps: data.baby_steps,
+            group: data.group,
+        })
+    }
+}
+
+/// The number of baby steps of the table for `bound`, e.g. ceil(sqrt(bound + 1)), so that
+/// step * step > bound.
+fn dlog_step(bound: u128) -> Result<u64, ProtocolError> {
+    if bound > MAX_DLOG_BOUND {
+        return Err(ProtocolError::DLogBoundTooLargeError);
+    }
+    // The square of a u64 always fits in a u128.
+    let square = |step: u64| step as u128 * step as u128;
+    let mut step = (bound as f64).sqrt() as u64;
+    while square(step) <= bound {
+        step += 1;
+    }
+    while step > 1 && square(step - 1) > bound {
+        step -= 1;
+    }
+    Ok(step)
+}
+
+/// The largest table built so far for each group (by name).
+type DLogTables = Mutex<HashMap<&'static str, Arc<dyn Any + Send + Sync>>>;
+
+/// The tables built by `DLogTable::cached`.
+static DLOG_TABLES: OnceLock<DLogTables> = OnceLock::new();
+
+impl<G: Group> DLogTable<G> {
+    /// Builds the table for `bound`, which must be at most `MAX_DLOG_BOUND`.
+    pub fn new(bound: u128) -> Result<Self, ProtocolError> {
+        let step = dlog_step(bound)?;
+        let mut baby_steps = HashMap::with_capacity(step as usize);
+        let mut current = Point::<G>::identity();
+        for j in 0..step {
+            baby_steps.insert(current.as_bytes(), j);
+            current = current + Point::generator();
+        }
+        Ok(DLogTable {
+            bound,
+            step,
+            baby_steps,
+            group: PhantomData,
+        })
+    }
+    /// A table for at least `bound`, shared by all callers within the process.
+    /// The largest table built so far is kept, and only rebuilt for a larger bound.
+    pub fn cached(bound: u128) -> Result<Arc<Self>, ProtocolError> {
+        Self::cached_in(DLOG_TABLES.get_or_init(Default::default), bound)
+    }
+    /// `cached`, for the tables in `tables`.
+    /// The table is built without holding the lock, so that other callers are not kept waiting.
+    fn cached_in(tables: &DLogTables, bound: u128) -> Result<Arc<Self>, ProtocolError> {
+        let lookup = |tables: &HashMap<&'static str, Arc<dyn Any + Send + Sync>>| {
+            tables
+                .get(G::NAME)
+                .and_then(|table| table.clone().downcast::<Self>().ok())
+        };
+        if let Some(table) = lookup(&tables.lock().unwrap()) {
+            if table.bound >= bound {
+                return Ok(table);
+            }
+        }
+        let table = Arc::new(Self::new(bound)?);
+        let mut tables = tables.lock().unwrap();
+        // Another caller may have cached a larger table meanwhile.
+        match lookup(&tables) {
+            Some(cached) if cached.bound >= table.bound => Ok(cached),
+            _ => {
+                tables.insert(G::NAME, table.clone());
+                Ok(table)
+            }
+        }
+    }
+    /// The largest discrete logarithm this table can recover.
+    pub fn bound(&self) -> u128 {
+        self.bound
+    }
+    /// Finds m in [0, bound] such that pt = m * g.
+    /// As a deserialized table is only checked to have the size of its bound, m is checked
+    /// against pt, failing if the table gave a wrong one.
+    pub fn solve(&self, pt: Point<G>) -> Result<u128, ProtocolError> {
+        let step = self.step as u128;
+        let giant_step = -(Point::generator() * Scalar::from(step));
+        let mut current = pt;
+        for i in 0..=(self.bound / step) {
+            if let Some(j) = self.baby_steps.get(&current.as_bytes()) {
+                let m = i * step + (*j as u128);
+                if m <= self.bound {
+                    if Point::generator() * Scalar::from(m) != pt {
+                        return Err(ProtocolError::MalformedDLogTableError);
+                    }
+                    return Ok(m);
+                }
+            }
+            current = current + giant_step;
+        }
+        Err(ProtocolError::DLogOutOfRangeError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    type Point = super::Point<Ristretto>;
+    type Scalar = super::Scalar<Ristretto>;
+    type DLogTable = super::DLogTable<Ristretto>;
+
+    #[test]
+    fn dlog_table_recovers_values_in_range() {
+        for bound in [0, 1, 2, 15, 16, 17, 1000] {
+            let table = DLogTable::new(bound).unwrap();
+            for m in 0..=bound {
+                let pt = Point::generator() * Scalar::from(m);
+                assert_eq!(table.solve(pt).unwrap(), m);
+            }
+        }
+    }
+    #[test]
+    fn dlog_table_rejects_values_out_of_range() {
+        let bound = 1000;
+        let table = DLogTable::new(bound).unwrap();
+        for m in [bound + 1, bound + 2, 2 * bound, 1 << 40] {
+            let pt = Point::generator() * Scalar::from(m);
+            assert!(matches!(
+                table.solve(pt),
+                Err(ProtocolError::DLogOutOfRangeError)
+            ));
+        }
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let pt = Point::sample_uniform(rng);
+        assert!(table.solve(pt).is_err());
+    }
+    #[test]
+    fn dlog_table_serialization() {
+        let table = DLogTable::new(5000).unwrap();
+        let bytes = bincode::serialize(&table).unwrap();
+        let table: DLogTable = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(table.bound(), 5000);
+        let pt = Point::generator() * Scalar::from(4321_u128);
+        assert_eq!(table.solve(pt).unwrap(), 4321);
+        // A table whose step does not match its bound is rejected, e.g. a step of 0.
+        let mut malformed = table.clone();
+        malformed.step = 0;
+        let bytes = bincode::serialize(&malformed).unwrap();
+        assert!(bincode::deserialize::<DLogTable>(&bytes).is_err());
+        let mut malformed = table.clone();
+        malformed.bound = u128::MAX;
+        let bytes = bincode::serialize(&malformed).unwrap();
+        assert!(bincode::deserialize::<DLogTable>(&bytes).is_err());
+        // A table of the right size with swapped entries never gives a wrong value.
+        let mut forged = table;
+        let (one, two) = (
+            Point::generator().as_bytes(),
+            (Point::generator() * Scalar::from(2_u128)).as_bytes(),
+        );
+        forged.baby_steps.insert(one, 2);
+        forged.baby_steps.insert(two, 1);
+        let bytes = bincode::serialize(&forged).unwrap();
+        let forged: DLogTable = bincode::deserialize(&bytes).unwrap();
+        assert!(matches!(
+            forged.solve(Point::generator()),
+            Err(ProtocolError::MalformedDLogTableError)
+        ));
+        assert_eq!(
+            forged
+                .solve(Point::generator() * Scalar::from(3_u128))
+                .unwrap(),
+            3
+        );
+    }
+    #[test]
+    fn dlog_table_bounds() {
+        assert!(matches!(
+            DLogTable::new(MAX_DLOG_BOUND + 1),
+            Err(ProtocolError::DLogBoundTooLargeError)
+        ));
+        assert!(DLogTable::new(u128::MAX).is_err());
+        // Cached tables are only rebuilt for larger bounds (in a cache of this test's own, as
+        // tallies share the global one).
+        let tables = DLogTables::default();
+        let table = DLogTable::cached_in(&tables, 100).unwrap();
+        assert_eq!(table.bound(), 100);
+        assert!(Arc::ptr_eq(
+            &table,
+            &DLogTable::cached_in(&tables, 50).unwrap()
+        ));
+        let larger = DLogTable::cached_in(&tables, 101).unwrap();
+        assert_eq!(larger.bound(), 101);
+        assert!(Arc::ptr_eq(
+            &larger,
+            &DLogTable::cached_in(&tables, 100).unwrap()
+        ));
+        assert!(DLogTable::cached(100).unwrap().bound() >= 100);
+    }
+
+    /// Checks the encodings of `Group` against the arithmetic, for both instances.
+    fn check_encodings<G: Group>() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let x = super::Scalar::<G>::sample_uniform(rng.clone());
+        let pt = x * super::Point::<G>::generator();
+        assert_eq!(super::Point::<G>::from_bytes(&pt.as_bytes()), Some(pt));
+        assert_eq!(
+            super::Scalar::<G>::from_canonical_bytes(&x.as_bytes()),
+            Some(x)
+        );
+        // Scalars are little-endian, and are rejected unless reduced.
+        let mut bytes = [0u8; 32];
+        bytes[0] = 1;
+        assert_eq!(
+            super::Scalar::<G>::from_bytes_mod_order(bytes),
+            super::Scalar::one()
+        );
+        assert!(super::Scalar::<G>::from_canonical_bytes(&[0xff; 32]).is_none());
+        assert_eq!(x * x.invert(), super::Scalar::one());
+        let mut corrupted = pt.as_bytes();
+        corrupted.pop();
+        assert!(super::Point::<G>::from_bytes(&corrupted).is_none());
+    }
+    #[test]
+    fn group_encodings() {
+        check_encodings::<Ristretto>();
+        check_encodings::<crate::primitives::ff_group::Belenios2048>();
+    }
+}
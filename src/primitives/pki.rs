@@ -1,26 +1,31 @@
 //! The Public Key Infrastructure that Belenios uses, described in [section 4.5 of the
 //! specification](https://www.belenios.org/specification.pdf).
 
+use std::ops::{Add, Mul};
 use std::sync::{Arc, Mutex};
 
 use ring::{
+    aead::{self, Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM},
     digest::{self, digest, SHA256, SHA256_OUTPUT_LEN},
-    rand::SecureRandom,
 };
 
 use crate::datatypes::credentials::Password;
-use crate::primitives::group::{Point, Scalar};
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::rng::SecureRandom;
+use crate::primitives::transcript::Transcript;
 use crate::primitives::zkp::{DLog, Proof, ProofSystem};
+use crate::ProtocolError;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug, Copy, Serialize, Deserialize)]
-pub struct Ciphertext {
-    pub(crate) alpha: Point,
-    pub(crate) beta: Point,
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Ciphertext<G: Group = Ristretto> {
+    pub(crate) alpha: Point<G>,
+    pub(crate) beta: Point<G>,
 }
 
-impl From<(Point, Point)> for Ciphertext {
-    fn from(pair: (Point, Point)) -> Self {
+impl<G: Group> From<(Point<G>, Point<G>)> for Ciphertext<G> {
+    fn from(pair: (Point<G>, Point<G>)) -> Self {
         Self {
             alpha: pair.0,
             beta: pair.1,
@@ -28,26 +33,50 @@ impl From<(Point, Point)> for Ciphertext {
     }
 }
 
-impl Into<(Point, Point)> for Ciphertext {
-    fn into(self) -> (Point, Point) {
-        (self.alpha, self.beta)
+impl<G: Group> From<Ciphertext<G>> for (Point<G>, Point<G>) {
+    fn from(ctxt: Ciphertext<G>) -> Self {
+        (ctxt.alpha, ctxt.beta)
     }
 }
 
-struct ElGamalKeys {
-    public: Point,
-    private: Scalar,
+impl<G: Group> Ciphertext<G> {
+    /// The trivial encryption of 0 with randomness 0, the neutral element for `+`.
+    pub fn identity() -> Self {
+        (Point::identity(), Point::identity()).into()
+    }
+}
+
+/// El Gamal is additively homomorphic: the sum of encryptions of m1 and m2 is an encryption of
+/// m1 + m2.
+impl<G: Group> Add<Ciphertext<G>> for Ciphertext<G> {
+    type Output = Ciphertext<G>;
+    fn add(self, rhs: Ciphertext<G>) -> Self::Output {
+        (self.alpha + rhs.alpha, self.beta + rhs.beta).into()
+    }
+}
+
+/// Scaling an encryption of m by w yields an encryption of w * m, used for weighting ballots.
+impl<G: Group> Mul<Scalar<G>> for Ciphertext<G> {
+    type Output = Ciphertext<G>;
+    fn mul(self, rhs: Scalar<G>) -> Self::Output {
+        (self.alpha * rhs, self.beta * rhs).into()
+    }
+}
+
+struct ElGamalKeys<G: Group> {
+    public: Point<G>,
+    private: Scalar<G>,
 }
 
 /// Used for domain-separating hash function calls
 struct DomainSeparator(String);
 
-impl From<(&Password, DomainSeparator)> for ElGamalKeys {
+impl<G: Group> From<(&Password, DomainSeparator)> for ElGamalKeys<G> {
     fn from((pass, sep): (&Password, DomainSeparator)) -> Self {
         let mut data = sep.0.clone();
         let underlying_string = &pass.0 .0;
         data.extend(underlying_string.as_bytes().iter().map(|c| *c as char));
-        let private = Scalar::hash_to_scalar(&data.as_bytes());
+        let private = Scalar::hash_to_scalar(data.as_bytes());
         let public = private * Point::generator();
         ElGamalKeys { public, private }
     }
@@ -56,95 +85,114 @@ impl From<(&Password, DomainSeparator)> for ElGamalKeys {
 /// A 256-bit symmetric key
 struct SymKey([u8; SHA256_OUTPUT_LEN]);
 /// A 96-bit nonce.
-struct IV([u8; 96 / 8]);
+struct IV([u8; aead::NONCE_LEN]);
 
 impl IV {
     fn hash_to_iv(data: &[u8]) -> Self {
-        const SIZE: usize = 96 / 8;
-        let hash = digest(&SHA256, &data);
-        let mut buff = [0 as u8; SIZE];
-        for i in 0..SIZE {
-            buff[i] = hash.as_ref()[i];
-        }
+        const SIZE: usize = aead::NONCE_LEN;
+        let hash = digest(&SHA256, data);
+        let mut buff = [0u8; SIZE];
+        buff.copy_from_slice(&hash.as_ref()[..SIZE]);
         Self(buff)
     }
 }
 
-/// A `trivial` symmetric encryption scheme.
-/// Was having issues getting an AES crate to work, will revisit if I have time.
+/// AES-256-GCM, an authenticated encryption scheme.
+/// Each key is derived from fresh randomness, so is only used with a single IV.
 impl SymKey {
     fn hash_to_key(data: &[u8]) -> Self {
-        let hash = digest(&SHA256, &data);
-        let mut buff = [0 as u8; SHA256_OUTPUT_LEN];
-        for i in 0..SHA256_OUTPUT_LEN {
-            buff[i] = hash.as_ref()[i];
-        }
+        let hash = digest(&SHA256, data);
+        let mut buff = [0u8; SHA256_OUTPUT_LEN];
+        buff.copy_from_slice(hash.as_ref());
         Self(buff)
     }
+    fn aead_key(&self) -> LessSafeKey {
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.0).unwrap())
+    }
+    /// Encrypts data, appending the authentication tag to the ciphertext.
     fn encrypt(&self, iv: IV, data: &[u8]) -> Vec<u8> {
-        data.to_owned()
+        let mut in_out = data.to_owned();
+        self.aead_key()
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(iv.0),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .unwrap();
+        in_out
     }
-    fn decrypt(&self, iv: IV, ctxt: &[u8]) -> Vec<u8> {
-        ctxt.to_owned()
+    fn decrypt(&self, iv: IV, ctxt: &[u8]) -> Result<Vec<u8>, ProtocolError> {
+        let mut in_out = ctxt.to_owned();
+        let len = self
+            .aead_key()
+            .open_in_place(
+                Nonce::assume_unique_for_key(iv.0),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| ProtocolError::DecryptionError)?
+            .len();
+        in_out.truncate(len);
+        Ok(in_out)
     }
 }
 
-impl Into<EncryptionKey> for VerificationKey {
-    fn into(self) -> EncryptionKey {
-        EncryptionKey(self.0)
+impl<G: Group> From<VerificationKey<G>> for EncryptionKey<G> {
+    fn from(key: VerificationKey<G>) -> Self {
+        EncryptionKey(key.0)
     }
 }
 
-impl Into<VerificationKey> for EncryptionKey {
-    fn into(self) -> VerificationKey {
-        VerificationKey(self.0)
+impl<G: Group> From<EncryptionKey<G>> for VerificationKey<G> {
+    fn from(key: EncryptionKey<G>) -> Self {
+        VerificationKey(key.0)
     }
 }
 
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct EncryptionKey(Point);
+pub(crate) struct EncryptionKey<G: Group = Ristretto>(Point<G>);
 #[derive(Clone, Copy, Debug)]
-pub(crate) struct DecryptionKey(Scalar);
+pub(crate) struct DecryptionKey<G: Group = Ristretto>(Scalar<G>);
 
-pub(crate) struct EncryptionKeys {
-    pub(crate) public: EncryptionKey,
-    pub(crate) private: DecryptionKey,
+pub(crate) struct EncryptionKeys<G: Group = Ristretto> {
+    pub(crate) public: EncryptionKey<G>,
+    pub(crate) private: DecryptionKey<G>,
 }
 
-impl DecryptionKey {
-    pub(crate) fn as_bytes(&self) -> &[u8; 32] {
+impl<G: Group> DecryptionKey<G> {
+    pub(crate) fn as_bytes(&self) -> Vec<u8> {
         self.0.as_bytes()
     }
 }
 
-impl EncryptionKey {
-    pub(crate) fn as_bytes(&self) -> [u8; 32] {
+impl<G: Group> EncryptionKey<G> {
+    pub(crate) fn as_bytes(&self) -> Vec<u8> {
         self.0.as_bytes()
     }
 }
 
-impl Into<Point> for EncryptionKey {
-    fn into(self) -> Point {
-        self.0
+impl<G: Group> From<EncryptionKey<G>> for Point<G> {
+    fn from(key: EncryptionKey<G>) -> Self {
+        key.0
     }
 }
 
-impl From<Point> for EncryptionKey {
-    fn from(p: Point) -> Self {
+impl<G: Group> From<Point<G>> for EncryptionKey<G> {
+    fn from(p: Point<G>) -> Self {
         Self(p)
     }
 }
 
-impl EncryptionKey {
+impl<G: Group> EncryptionKey<G> {
     /// El-gamal encrypts m while returning the encryption randomness r.
     /// Used for ZK proofs later.
     pub(crate) fn enc_leak_randomness(
         &self,
         rng: Arc<Mutex<dyn SecureRandom>>,
-        m: Scalar,
-    ) -> (Ciphertext, Scalar) {
+        m: Scalar<G>,
+    ) -> (Ciphertext<G>, Scalar<G>) {
         let rng = rng.clone();
-        let y: Point = (*self).into();
+        let y: Point<G> = (*self).into();
         let r = Scalar::sample_uniform(rng.clone());
         let alpha = r * Point::generator();
         let beta = (y * r) + (m * Point::generator());
@@ -152,19 +200,22 @@ impl EncryptionKey {
     }
 }
 
-pub(crate) struct EncryptedMessage {
-    ctxt: Ciphertext,
-    data: Vec<u8>,
+/// A hybrid encryption of some data: the symmetric key is derived from the El Gamal plaintext
+/// g^s of `ctxt`, and the IV from its randomness g^r.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct EncryptedMessage<G: Group = Ristretto> {
+    pub(crate) ctxt: Ciphertext<G>,
+    pub(crate) data: Vec<u8>,
 }
 
-/*
-impl EncryptionKeys {
+impl<G: Group> EncryptionKeys<G> {
     // Need the randomness for certain proofs
     pub(crate) fn encrypt_leaking_randomness(
         rng: Arc<Mutex<dyn SecureRandom>>,
-        encryption_key: &EncryptionKey,
+        encryption_key: &EncryptionKey<G>,
         m: Vec<u8>,
-    ) -> (EncryptedMessage, (Scalar, Scalar)) {
+    ) -> (EncryptedMessage<G>, (Scalar<G>, Scalar<G>)) {
         let r = Scalar::sample_uniform(rng.clone());
         let s = Scalar::sample_uniform(rng.clone());
         let alpha = Point::generator() * r;
@@ -188,29 +239,27 @@ impl EncryptionKeys {
         let enc_m = EncryptedMessage { ctxt, data };
         (enc_m, (r, s))
     }
-    pub fn encrypt(
+    pub(crate) fn encrypt(
         rng: Arc<Mutex<dyn SecureRandom>>,
-        encryption_key: &EncryptionKey,
+        encryption_key: &EncryptionKey<G>,
         m: Vec<u8>,
-    ) -> EncryptedMessage {
+    ) -> EncryptedMessage<G> {
         Self::encrypt_leaking_randomness(rng.clone(), encryption_key, m).0
     }
-    pub fn decrypt(self, dk: DecryptionKey, c: EncryptedMessage) -> Vec<u8> {
-        let (ctxt, data) = (c.ctxt, c.data);
-        let (alpha, beta) = ctxt.into();
-        let pt = beta - (dk.0 * alpha);
+    /// Decrypts c, failing if it was not encrypted to our key or was tampered with.
+    pub(crate) fn decrypt(&self, c: &EncryptedMessage<G>) -> Result<Vec<u8>, ProtocolError> {
+        let (alpha, beta) = c.ctxt.into();
+        let pt = beta - (self.private.0 * alpha);
         // Computing Symmetric key as SHA256("key"| pt);
         let key_data = ["key".to_string().as_bytes(), &pt.as_bytes()[..]].concat();
         let key = SymKey::hash_to_key(&key_data);
         let iv_data = ["iv".to_string().as_bytes(), &alpha.as_bytes()[..]].concat();
         let iv = IV::hash_to_iv(&iv_data);
-        key.decrypt(iv, &data)
+        key.decrypt(iv, &c.data)
     }
 }
-*/
 
-/*
-impl From<&Password> for EncryptionKeys {
+impl<G: Group> From<&Password> for EncryptionKeys<G> {
     fn from(secret: &Password) -> Self {
         let enc_sep = DomainSeparator(String::from("dk"));
         let keys = ElGamalKeys::from((secret, enc_sep));
@@ -220,26 +269,26 @@ impl From<&Password> for EncryptionKeys {
         }
     }
 }
-*/
 
 #[derive(Debug, Clone)]
-pub(crate) struct SigningKey(pub(crate) Scalar);
-#[derive(Debug, Clone)]
-pub(crate) struct VerificationKey(pub(crate) Point);
+pub(crate) struct SigningKey<G: Group = Ristretto>(pub(crate) Scalar<G>);
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct VerificationKey<G: Group = Ristretto>(pub(crate) Point<G>);
 
-impl From<VerificationKey> for Point {
-    fn from(vk: VerificationKey) -> Self {
+impl<G: Group> From<VerificationKey<G>> for Point<G> {
+    fn from(vk: VerificationKey<G>) -> Self {
         vk.0
     }
 }
 
 #[derive(Debug, Clone)]
-pub(crate) struct SigningKeys {
-    pub(crate) public: VerificationKey,
-    pub(crate) private: SigningKey,
+pub(crate) struct SigningKeys<G: Group = Ristretto> {
+    pub(crate) public: VerificationKey<G>,
+    pub(crate) private: SigningKey<G>,
 }
 
-impl From<&Password> for SigningKeys {
+impl<G: Group> From<&Password> for SigningKeys<G> {
     fn from(secret: &Password) -> Self {
         let sign_sep = DomainSeparator(String::from("sk"));
         let keys = ElGamalKeys::from((secret, sign_sep));
@@ -250,27 +299,101 @@ impl From<&Password> for SigningKeys {
     }
 }
 
-impl SigningKeys {
-    fn sign(
-        signing_key: SigningKey,
+impl<G: Group> SigningKeys<G> {
+    pub(crate) fn sign(
+        signing_key: SigningKey<G>,
         rng: Arc<Mutex<dyn SecureRandom>>,
         hash: &[u8],
-    ) -> (&[u8], Proof) {
+    ) -> (&[u8], Proof<G>) {
         let w = Scalar::sample_uniform(rng);
         let commitment = Point::generator() * w;
-        // The domain separtion constant for signing
-        let separator = String::from("sigmsg");
-        let data = [separator.as_bytes(), hash, &commitment.as_bytes()].concat();
-        let challenge = Scalar::hash_to_scalar(&data);
+        let challenge = Self::challenge(hash, &commitment);
         let response = w - signing_key.0 * challenge;
         let proof = (challenge, response).into();
         (hash, proof)
     }
-    fn verify(verif_key: Point, (hash, pf): (&[u8], Proof)) -> bool {
+    pub(crate) fn verify(verif_key: Point<G>, (hash, pf): (&[u8], Proof<G>)) -> bool {
         let (challenge, response) = pf.into();
         let commitment = (response * Point::generator()) + (verif_key * challenge);
-        let separator = String::from("sigmsg");
-        let data = [separator.as_bytes(), hash, &commitment.as_bytes()].concat();
-        challenge == Scalar::hash_to_scalar(&data)
+        challenge == Self::challenge(hash, &commitment)
+    }
+    fn challenge(hash: &[u8], commitment: &Point<G>) -> Scalar<G> {
+        // The domain separation constant for signing
+        let mut transcript = Transcript::new("sigmsg");
+        transcript.append_message("message", hash);
+        transcript.append_point("commitment", commitment);
+        transcript.challenge_scalar("challenge")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    fn gen_keys(rng: Arc<Mutex<dyn SecureRandom>>) -> EncryptionKeys<Ristretto> {
+        EncryptionKeys::from(&Password::gen(rng))
+    }
+
+    #[test]
+    fn signatures() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let keys = SigningKeys::<Ristretto>::from(&Password::gen(rng.clone()));
+        let other_keys = SigningKeys::<Ristretto>::from(&Password::gen(rng.clone()));
+        let hash = digest(&SHA256, b"a ballot");
+        let (_, pf) =
+            SigningKeys::<Ristretto>::sign(keys.private.clone(), rng.clone(), hash.as_ref());
+        assert!(SigningKeys::<Ristretto>::verify(
+            keys.public.0,
+            (hash.as_ref(), pf.clone())
+        ));
+        assert!(!SigningKeys::<Ristretto>::verify(
+            other_keys.public.0,
+            (hash.as_ref(), pf.clone())
+        ));
+        let other_hash = digest(&SHA256, b"another ballot");
+        assert!(!SigningKeys::<Ristretto>::verify(
+            keys.public.0,
+            (other_hash.as_ref(), pf)
+        ));
+    }
+
+    #[test]
+    fn hybrid_encryption_round_trip() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let keys = gen_keys(rng.clone());
+        for len in [0, 1, 16, 17, 1000] {
+            let m: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let c = EncryptionKeys::encrypt(rng.clone(), &keys.public, m.clone());
+            assert_eq!(c.data.len(), m.len() + AES_256_GCM.tag_len());
+            assert_eq!(keys.decrypt(&c).unwrap(), m);
+        }
+    }
+
+    #[test]
+    fn hybrid_encryption_detects_tampering() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let keys = gen_keys(rng.clone());
+        let m = b"a share for another trustee".to_vec();
+        let c = EncryptionKeys::encrypt(rng.clone(), &keys.public, m);
+        // Flipping any bit of the symmetric ciphertext (or tag) is detected.
+        for idx in 0..c.data.len() {
+            let mut tampered = c.clone();
+            tampered.data[idx] ^= 1;
+            assert!(matches!(
+                keys.decrypt(&tampered),
+                Err(ProtocolError::DecryptionError)
+            ));
+        }
+        // As is modifying the El Gamal ciphertext, which changes the derived key and IV.
+        let mut tampered = c.clone();
+        tampered.ctxt.beta = tampered.ctxt.beta + Point::generator();
+        assert!(keys.decrypt(&tampered).is_err());
+        let mut tampered = c.clone();
+        tampered.ctxt.alpha = tampered.ctxt.alpha + Point::generator();
+        assert!(keys.decrypt(&tampered).is_err());
+        // Or decrypting with the wrong key.
+        let other_keys = gen_keys(rng);
+        assert!(other_keys.decrypt(&c).is_err());
     }
 }
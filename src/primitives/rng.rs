@@ -0,0 +1,94 @@
+//! The randomness participants draw from.
+//!
+//! Participants share an `Arc<Mutex<dyn SecureRandom>>`, where `SecureRandom` is implemented by
+//! every `ring::rand::SecureRandom` (in practice, `SystemRandom`).
+//! As ring seals its trait, no other generator may implement it, hence this one.
+//!
+//! With the `deterministic-rng` feature (and in the tests of this crate), `DeterministicRandom`
+//! derives all its output from a seed, so that a whole run of the protocol may be reproduced,
+//! e.g. to check the known-answer tests of `tests/vectors/kat.json`.
+//! It must never be used in an actual election.
+
+use ring::error::Unspecified;
+
+pub trait SecureRandom {
+    /// Fills `dest` with random bytes.
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Unspecified>;
+}
+
+impl<T: ring::rand::SecureRandom> SecureRandom for T {
+    fn fill(&self, dest: &mut [u8]) -> Result<(), Unspecified> {
+        ring::rand::SecureRandom::fill(self, dest)
+    }
+}
+
+#[cfg(any(test, feature = "deterministic-rng"))]
+pub use deterministic::DeterministicRandom;
+
+#[cfg(any(test, feature = "deterministic-rng"))]
+mod deterministic {
+    use std::cell::Cell;
+
+    use ring::digest::{digest, SHA256, SHA256_OUTPUT_LEN};
+    use ring::error::Unspecified;
+
+    use super::SecureRandom;
+
+    /// A generator whose output is SHA256(separator || seed || counter) for successive values of
+    /// a 64-bit counter.
+    /// **Not secure**: only meant for reproducible tests.
+    #[derive(Debug)]
+    pub struct DeterministicRandom {
+        seed: [u8; 32],
+        counter: Cell<u64>,
+    }
+
+    impl DeterministicRandom {
+        pub fn new(seed: [u8; 32]) -> Self {
+            DeterministicRandom {
+                seed,
+                counter: Cell::new(0),
+            }
+        }
+    }
+
+    impl SecureRandom for DeterministicRandom {
+        fn fill(&self, dest: &mut [u8]) -> Result<(), Unspecified> {
+            for chunk in dest.chunks_mut(SHA256_OUTPUT_LEN) {
+                let counter = self.counter.get();
+                let data = [
+                    "belenios-deterministic-rng".as_bytes(),
+                    &self.seed,
+                    &counter.to_le_bytes(),
+                ]
+                .concat();
+                chunk.copy_from_slice(&digest(&SHA256, &data).as_ref()[..chunk.len()]);
+                self.counter.set(counter + 1);
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deterministic_random_is_reproducible() {
+        let fill = |seed, len| {
+            let rng = DeterministicRandom::new(seed);
+            let mut buff = vec![0; len];
+            rng.fill(&mut buff).unwrap();
+            buff
+        };
+        assert_eq!(fill([1; 32], 80), fill([1; 32], 80));
+        assert_ne!(fill([1; 32], 80), fill([2; 32], 80));
+        // Successive draws differ.
+        let rng = DeterministicRandom::new([1; 32]);
+        let (mut a, mut b) = ([0; 16], [0; 16]);
+        rng.fill(&mut a).unwrap();
+        rng.fill(&mut b).unwrap();
+        assert_ne!(a, b);
+    }
+}
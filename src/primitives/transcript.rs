@@ -0,0 +1,93 @@
+//! Transcripts for the Fiat-Shamir transform, in the spirit of [Merlin](https://merlin.cool).
+//!
+//! Rather than concatenating byte strings by hand, a proof (or signature) appends each part of
+//! its statement and commitments to a transcript as a labeled message, and derives its challenge
+//! from the transcript.
+//! As each label and message is prefixed by its length, the encoding of a transcript is
+//! injective: the same bytes split differently between messages (or given other labels) lead to
+//! different challenges.
+//!
+//! Unlike Merlin, which is built on STROBE, the transcript is simply hashed with SHA256 (as in
+//! `Group::hash_to_scalar`) whenever a challenge is needed.
+
+use crate::primitives::group::{Group, Point, Scalar};
+
+#[derive(Clone, Debug)]
+pub(crate) struct Transcript {
+    data: Vec<u8>,
+}
+
+impl Transcript {
+    /// A transcript for the protocol `domain_sep`.
+    pub(crate) fn new(domain_sep: &'static str) -> Self {
+        let mut transcript = Transcript { data: Vec::new() };
+        transcript.append_message("dom-sep", domain_sep.as_bytes());
+        transcript
+    }
+    pub(crate) fn append_message(&mut self, label: &'static str, message: &[u8]) {
+        self.data.extend((label.len() as u64).to_le_bytes());
+        self.data.extend(label.as_bytes());
+        self.data.extend((message.len() as u64).to_le_bytes());
+        self.data.extend(message);
+    }
+    pub(crate) fn append_point<G: Group>(&mut self, label: &'static str, point: &Point<G>) {
+        self.append_message(label, &point.as_bytes());
+    }
+    pub(crate) fn append_scalar<G: Group>(&mut self, label: &'static str, scalar: &Scalar<G>) {
+        self.append_message(label, &scalar.as_bytes());
+    }
+    /// Derives a challenge from everything appended so far.
+    /// The challenge is appended in turn, so that any later challenge depends on it.
+    pub(crate) fn challenge_scalar<G: Group>(&mut self, label: &'static str) -> Scalar<G> {
+        self.append_message(label, &[]);
+        let challenge = Scalar::hash_to_scalar(&self.data);
+        self.append_scalar(label, &challenge);
+        challenge
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives::group::Ristretto;
+
+    type Scalar = super::Scalar<Ristretto>;
+
+    fn challenge(messages: &[(&'static str, &[u8])]) -> Scalar {
+        let mut transcript = Transcript::new("test");
+        for (label, message) in messages.iter() {
+            transcript.append_message(label, message);
+        }
+        transcript.challenge_scalar("challenge")
+    }
+
+    #[test]
+    fn encoding_is_injective() {
+        let expected = challenge(&[("S", b"election"), ("m", b"hash")]);
+        assert_eq!(expected, challenge(&[("S", b"election"), ("m", b"hash")]));
+        // The same bytes, split differently between the messages.
+        assert_ne!(expected, challenge(&[("S", b"electionh"), ("m", b"ash")]));
+        assert_ne!(
+            expected,
+            challenge(&[("S", b"election"), ("m", b"h"), ("m", b"ash")])
+        );
+        assert_ne!(expected, challenge(&[("S", b"electionhash")]));
+        // ... or between labels and messages.
+        assert_ne!(expected, challenge(&[("Se", b"lection"), ("m", b"hash")]));
+        assert_ne!(expected, challenge(&[("S", b"election"), ("mh", b"ash")]));
+        // Domain separation.
+        let mut transcript = Transcript::new("other");
+        transcript.append_message("S", b"election");
+        transcript.append_message("m", b"hash");
+        assert_ne!(expected, transcript.challenge_scalar("challenge"));
+    }
+
+    #[test]
+    fn challenges_depend_on_earlier_ones() {
+        let mut transcript = Transcript::new("test");
+        transcript.append_message("m", b"message");
+        let first: Scalar = transcript.challenge_scalar("c");
+        let second: Scalar = transcript.challenge_scalar("c");
+        assert_ne!(first, second);
+    }
+}
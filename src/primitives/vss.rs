@@ -0,0 +1,238 @@
+//! Verifiable Secret Sharing, used by threshold ("pedersen") trustees to jointly generate the
+//! election key such that any `threshold` of them can decrypt.
+//!
+//! Each trustee i acts as a dealer, sampling a random polynomial
+//! f_i(z) = a_i0 + a_i1 z + ... + a_i(t-1) z^(t-1)
+//! and publishing commitments A_ik = a_ik * g to its coefficients (Feldman's scheme), along with a
+//! proof of knowledge of a_i0.
+//! The j-th trustee (for j in 1, ..., n) privately receives the share f_i(j), which it can check
+//! against the public commitments.
+//!
+//! Dealers that publish bad commitments, or fail to answer a complaint about one of their shares
+//! by revealing a correct share, are disqualified.
+//! For the set QUAL of remaining dealers,
+//!   * the election public key is the sum of the A_i0,
+//!   * the j-th trustee's key share is x_j = sum of the f_i(j), and
+//!   * its verification key x_j * g is publicly computable from the commitments.
+//!
+//! The secret key sum of the a_i0 is then recovered (in the exponent) from any t key shares by
+//! Lagrange interpolation at 0.
+
+use std::sync::{Arc, Mutex};
+
+use crate::primitives::rng::SecureRandom;
+use serde::{Deserialize, Serialize};
+
+use crate::datatypes::credentials::UUID;
+use crate::primitives::group::{Group, Point, Ristretto, Scalar};
+use crate::primitives::zkp::{DLog, Proof, ProofContext, ProofSystem};
+
+/// A polynomial of degree `threshold - 1` over the scalars.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub(crate) struct Polynomial<G: Group = Ristretto> {
+    coefficients: Vec<Scalar<G>>,
+}
+
+impl<G: Group> Polynomial<G> {
+    pub(crate) fn sample(rng: Arc<Mutex<dyn SecureRandom>>, threshold: usize) -> Self {
+        let coefficients = (0..threshold)
+            .map(|_| Scalar::sample_uniform(rng.clone()))
+            .collect();
+        Polynomial { coefficients }
+    }
+    /// Evaluates the polynomial at x using Horner's method.
+    pub(crate) fn eval(&self, x: u128) -> Scalar<G> {
+        let x = Scalar::from(x);
+        let mut res = Scalar::zero();
+        for a in self.coefficients.iter().rev() {
+            res = res * x + *a;
+        }
+        res
+    }
+    /// Commits to each coefficient, and proves knowledge of the constant term (in the context of
+    /// the dealer).
+    /// The empty polynomial (of a threshold of 0) is taken to have a constant term of 0, and its
+    /// commitment never verifies.
+    pub(crate) fn commit(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+    ) -> PolynomialCommitment<G> {
+        let coefficients: Vec<Point<G>> = self
+            .coefficients
+            .iter()
+            .map(|a| *a * Point::generator())
+            .collect();
+        let constant = self
+            .coefficients
+            .first()
+            .copied()
+            .unwrap_or_else(Scalar::zero);
+        let instance = DLog {
+            pt: constant * Point::generator(),
+        };
+        let proof = instance.prove(rng, context, &constant);
+        PolynomialCommitment {
+            coefficients,
+            proof,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PolynomialCommitment<G: Group = Ristretto> {
+    pub(crate) coefficients: Vec<Point<G>>,
+    pub(crate) proof: Proof<G>,
+}
+
+impl<G: Group> PolynomialCommitment<G> {
+    /// Computes f(x) * g from the commitments to the coefficients of f.
+    pub(crate) fn eval(&self, x: u128) -> Point<G> {
+        let x = Scalar::from(x);
+        let mut res = Point::identity();
+        for A in self.coefficients.iter().rev() {
+            res = res * x + *A;
+        }
+        res
+    }
+    /// Checks that `share` is f(x).
+    pub(crate) fn verify_share(&self, x: u128, share: Scalar<G>) -> bool {
+        share * Point::generator() == self.eval(x)
+    }
+    /// Checks that the committed polynomial has the right degree, and that the dealer knows its
+    /// constant term.
+    pub(crate) fn verify(&self, context: &ProofContext, threshold: usize) -> bool {
+        if self.coefficients.is_empty() || self.coefficients.len() != threshold {
+            return false;
+        }
+        let instance = DLog {
+            pt: self.coefficients[0],
+        };
+        instance.verify(context, &self.proof)
+    }
+}
+
+/// Computes the set QUAL of dealers (as indices into `commitments`) which are not disqualified.
+///
+/// * `complaints[j]` is the list of dealers the j-th trustee complained about, and
+/// * `responses[i]` is the list of (j, f_i(j)) the i-th dealer revealed in response.
+///
+/// A dealer is qualified if its commitment verifies (for its index in the election), and each
+/// complaint against it was answered by revealing a correct share.
+pub(crate) fn qualified_dealers<G: Group>(
+    election: &UUID,
+    threshold: usize,
+    commitments: &[PolynomialCommitment<G>],
+    complaints: &[Vec<usize>],
+    responses: &[Vec<(usize, Scalar<G>)>],
+) -> Vec<usize> {
+    let mut qualified = Vec::new();
+    for (i, commitment) in commitments.iter().enumerate() {
+        let context = ProofContext::new(election, Some(i));
+        if !commitment.verify(&context, threshold) {
+            continue;
+        }
+        let revealed = responses.get(i).map(|r| &r[..]).unwrap_or(&[]);
+        let answered_all = complaints
+            .iter()
+            .enumerate()
+            .filter(|(_, accused)| accused.contains(&i))
+            .all(|(j, _)| {
+                revealed
+                    .iter()
+                    .any(|(k, share)| *k == j && commitment.verify_share(j as u128 + 1, *share))
+            });
+        if answered_all {
+            qualified.push(i);
+        }
+    }
+    qualified
+}
+
+/// The Lagrange coefficient of the point x_j for interpolating at 0 from the points `xs`, e.g.
+/// the product of x_k / (x_k - x_j) over all x_k != x_j in `xs`.
+pub(crate) fn lagrange_coefficient<G: Group>(x_j: u128, xs: &[u128]) -> Scalar<G> {
+    let mut num = Scalar::one();
+    let mut den = Scalar::one();
+    for x_k in xs.iter().filter(|x_k| **x_k != x_j) {
+        num = num * Scalar::from(*x_k);
+        den = den * (Scalar::from(*x_k) - Scalar::from(x_j));
+    }
+    num * den.invert()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Point = super::Point<Ristretto>;
+    type Scalar = super::Scalar<Ristretto>;
+    type Polynomial = super::Polynomial<Ristretto>;
+    type PolynomialCommitment = super::PolynomialCommitment<Ristretto>;
+    use ring::rand::SystemRandom;
+
+    #[test]
+    fn shares_verify_and_interpolate() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let (threshold, n) = (3, 5);
+        let f = Polynomial::sample(rng.clone(), threshold);
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let commitment = f.commit(rng.clone(), &context);
+        assert!(commitment.verify(&context, threshold));
+        assert!(!commitment.verify(&context, threshold + 1));
+        // The proof is bound to the dealer.
+        let other = ProofContext::new(&context.election, Some(1));
+        assert!(!commitment.verify(&other, threshold));
+        let shares: Vec<Scalar> = (1..=n).map(|j| f.eval(j)).collect();
+        for j in 1..=n {
+            assert!(commitment.verify_share(j, shares[j as usize - 1]));
+            assert!(!commitment.verify_share(j, shares[j as usize - 1] + Scalar::one()));
+        }
+        // Any `threshold` shares recover f(0) * g.
+        for xs in [vec![1, 2, 3], vec![2, 4, 5], vec![1, 3, 5]] {
+            let mut secret = Scalar::zero();
+            for x in xs.iter() {
+                secret = secret + lagrange_coefficient(*x, &xs) * shares[*x as usize - 1];
+            }
+            assert_eq!(secret * Point::generator(), commitment.coefficients[0]);
+        }
+    }
+
+    #[test]
+    fn unanswered_complaints_disqualify() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let threshold = 2;
+        let fs: Vec<Polynomial> = (0..3)
+            .map(|_| Polynomial::sample(rng.clone(), threshold))
+            .collect();
+        let uuid = UUID::gen(rng.clone());
+        let commitments: Vec<PolynomialCommitment> = fs
+            .iter()
+            .enumerate()
+            .map(|(i, f)| f.commit(rng.clone(), &ProofContext::new(&uuid, Some(i))))
+            .collect();
+        // Trustee 0 complains about dealers 1 and 2.
+        let complaints = vec![vec![1, 2], vec![], vec![]];
+        // Dealer 1 answers correctly, dealer 2 reveals a wrong share.
+        let responses = vec![
+            vec![],
+            vec![(0, fs[1].eval(1))],
+            vec![(0, fs[2].eval(1) + Scalar::one())],
+        ];
+        let qualified = qualified_dealers(&uuid, threshold, &commitments, &complaints, &responses);
+        assert_eq!(qualified, vec![0, 1]);
+    }
+
+    #[test]
+    fn empty_commitments_do_not_verify() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let commitment = Polynomial::sample(rng.clone(), 0).commit(rng, &context);
+        assert!(commitment.coefficients.is_empty());
+        assert!(!commitment.verify(&context, 0));
+        let qualified = qualified_dealers(&context.election, 0, &[commitment], &[], &[]);
+        assert!(qualified.is_empty());
+    }
+}
@@ -1,13 +1,16 @@
 //! Zero-knowledge Proofs needed for Belenios.
 //! These are detailed in the [Zero-knowledge Proofs Document](https://hal.inria.fr/hal-01576379/document).
 //!
-//! We model ZKPs via three structs:
-//!   * Public parameters, which in most cases is simply a RNG,
+//! We model ZKPs via two structs:
 //!   * an Instance of a statement to prove, and
 //!   * a Witness to the statement.
+//!
+//! Only the prover needs randomness, so an RNG is passed to `prove`, while `verify` is a pure
+//! function of the instance and the proof.
 //! We implement our ZKPs through the Sigma Protocols described in the linked document.
 //! These are then generically transformed to NIZKs using the Fiat Shamir transform.
-//! We ensure our NIZKs use domain-separated hashes.
+//! We ensure our NIZKs use domain-separated hashes, computed over a `Transcript` of labeled
+//! (and length-prefixed) messages.
 //!
 //! Note that our Sigma protocols aren't *really* Sigma protocols, as their `challenge` is not
 //! random --- we `hard code` the Fiat Shamir transform at this step.
@@ -18,22 +21,27 @@
 
 use array_init::array_init;
 use std::convert::{TryFrom, TryInto};
-use std::ops::{Add, AddAssign, Mul};
+use std::ops::{Add, AddAssign, Mul, Range};
 use std::sync::{Arc, Mutex};
 
-use ring::rand::SecureRandom;
+use crate::primitives::rng::SecureRandom;
+use serde::{Deserialize, Serialize};
 
-use crate::primitives::group::{Point, Scalar};
+use crate::datatypes::credentials::UUID;
+use crate::parallel;
+use crate::primitives::group::{Group, Point, Ristretto, Scalar, Terms};
 use crate::primitives::pki::Ciphertext;
+use crate::primitives::transcript::Transcript;
 
-#[derive(Debug, Clone)]
-pub struct Proof {
-    pub(crate) challenge: Scalar,
-    pub(crate) response: Scalar,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Proof<G: Group = Ristretto> {
+    pub(crate) challenge: Scalar<G>,
+    pub(crate) response: Scalar<G>,
 }
 
-impl From<(Scalar, Scalar)> for Proof {
-    fn from(pair: (Scalar, Scalar)) -> Self {
+impl<G: Group> From<(Scalar<G>, Scalar<G>)> for Proof<G> {
+    fn from(pair: (Scalar<G>, Scalar<G>)) -> Self {
         Self {
             challenge: pair.0,
             response: pair.1,
@@ -41,106 +49,224 @@ impl From<(Scalar, Scalar)> for Proof {
     }
 }
 
-impl Into<(Scalar, Scalar)> for Proof {
-    fn into(self) -> (Scalar, Scalar) {
-        (self.challenge, self.response)
+impl<G: Group> From<Proof<G>> for (Scalar<G>, Scalar<G>) {
+    fn from(proof: Proof<G>) -> Self {
+        (proof.challenge, proof.response)
+    }
+}
+
+/// The context a proof is made in, which is hashed along with its statement so that the proof
+/// verifies in no other context: the UUID of the election, the group (see `Group::NAME`), and the
+/// index of the participant making the proof, e.g. of a trustee.
+/// The proofs of voters name no participant, as that would link their ballot to them, and are
+/// bound to their credential (in `S`) instead.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProofContext {
+    pub(crate) election: UUID,
+    pub(crate) participant: Option<usize>,
+}
+
+impl ProofContext {
+    pub(crate) fn new(election: &UUID, participant: Option<usize>) -> Self {
+        ProofContext {
+            election: election.clone(),
+            participant,
+        }
+    }
+    /// A transcript for the protocol `domain_sep`, starting with the context.
+    fn transcript<G: Group>(&self, domain_sep: &'static str) -> Transcript {
+        let mut transcript = Transcript::new(domain_sep);
+        transcript.append_message("election", self.election.as_bytes());
+        transcript.append_message("group", G::NAME.as_bytes());
+        let participant = self.participant.map(|i| (i as u64).to_le_bytes());
+        transcript.append_message("participant", participant.as_ref().map_or(&[], |i| &i[..]));
+        transcript
     }
 }
 
-pub trait ProofSystem {
+pub trait ProofSystem<G: Group = Ristretto> {
     type Witness;
     type Proof;
     type Transcript;
     const DOMAIN_SEP: &'static str;
-    fn hash(trans: Self::Transcript) -> Scalar;
-    fn prove(&self, w: &Self::Witness) -> Self::Proof;
-    // A domain-separated hash
-    fn verify(&self, p: &Self::Proof) -> bool;
+    // A domain-separated hash, which binds the proof to its context.
+    fn hash(context: &ProofContext, trans: Self::Transcript) -> Scalar<G>;
+    // Proves the statement of the instance, drawing the commitments (and the simulated
+    // transcripts of disjunctions) from `rng`.
+    fn prove(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        w: &Self::Witness,
+    ) -> Self::Proof;
+    fn verify(&self, context: &ProofContext, p: &Self::Proof) -> bool;
 }
 
 /// For proving knowledge of a point x such that
 /// x = dlog(pt)
-pub(crate) struct DLog {
-    pub(crate) pt: Point,
-    pub(crate) rng: Arc<Mutex<dyn SecureRandom>>,
+pub(crate) struct DLog<G: Group = Ristretto> {
+    pub(crate) pt: Point<G>,
 }
 
-impl ProofSystem for DLog {
-    type Witness = Scalar;
-    type Proof = Proof;
-    type Transcript = Ciphertext;
+impl<G: Group> ProofSystem<G> for DLog<G> {
+    type Witness = Scalar<G>;
+    type Proof = Proof<G>;
+    type Transcript = Ciphertext<G>;
     const DOMAIN_SEP: &'static str = "pok";
-    fn hash(trans: Self::Transcript) -> Scalar {
-        let data = [
-            Self::DOMAIN_SEP.as_bytes(),
-            &trans.alpha.as_bytes(),
-            &trans.beta.as_bytes(),
-        ]
-        .concat();
-        Scalar::hash_to_scalar(&data)
-    }
-    fn prove(&self, wit: &Self::Witness) -> Self::Proof {
-        let w = Scalar::sample_uniform(self.rng.clone());
+    fn hash(context: &ProofContext, trans: Self::Transcript) -> Scalar<G> {
+        let mut transcript = context.transcript::<G>(Self::DOMAIN_SEP);
+        transcript.append_point("pt", &trans.alpha);
+        transcript.append_point("A", &trans.beta);
+        transcript.challenge_scalar("challenge")
+    }
+    fn prove(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> Self::Proof {
+        let w = Scalar::sample_uniform(rng.clone());
         let A = w * Point::generator();
-        let challenge = Self::hash((self.pt.clone(), A).into());
-        let response = (w - wit * challenge).into();
+        let challenge = Self::hash(context, (self.pt, A).into());
+        let response = w - wit * challenge;
         Proof {
             challenge,
             response,
         }
     }
-    fn verify(&self, p: &Self::Proof) -> bool {
+    fn verify(&self, context: &ProofContext, p: &Self::Proof) -> bool {
         let A = (p.response * Point::generator()) + (p.challenge * self.pt);
-        p.challenge == Self::hash((self.pt.clone(), A).into())
+        p.challenge == Self::hash(context, (self.pt, A).into())
+    }
+}
+
+/// For proving knowledge of a scalar x such that
+/// x = dlog(pk) = dlog_alpha(factor),
+/// e.g. that `factor = x * alpha` is a correct decryption factor of a ciphertext (alpha, beta)
+/// for the private key x of the public key pk (a Chaum-Pedersen proof).
+pub(crate) struct EqualDLog<G: Group = Ristretto> {
+    pub(crate) pk: Point<G>,
+    pub(crate) alpha: Point<G>,
+    pub(crate) factor: Point<G>,
+}
+
+impl<G: Group> ProofSystem<G> for EqualDLog<G> {
+    type Witness = Scalar<G>;
+    type Proof = Proof<G>;
+    // Transcript is the statement (pk, alpha, factor), along with the commitment (A, B).
+    // Keeping the statement in the transcript (rather than in the instance) lets each proof of a
+    // partial decryption be hashed independently of all other proofs of that trustee.
+    type Transcript = ([Point<G>; 3], Ciphertext<G>);
+    const DOMAIN_SEP: &'static str = "decrypt";
+    fn hash(context: &ProofContext, trans: Self::Transcript) -> Scalar<G> {
+        let (statement, commitment) = trans;
+        let mut transcript = context.transcript::<G>(Self::DOMAIN_SEP);
+        transcript.append_point("pk", &statement[0]);
+        transcript.append_point("alpha", &statement[1]);
+        transcript.append_point("factor", &statement[2]);
+        transcript.append_point("A", &commitment.alpha);
+        transcript.append_point("B", &commitment.beta);
+        transcript.challenge_scalar("challenge")
+    }
+    fn prove(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> Self::Proof {
+        let w = Scalar::sample_uniform(rng.clone());
+        let A = w * Point::generator();
+        let B = w * self.alpha;
+        let statement = [self.pk, self.alpha, self.factor];
+        let challenge = Self::hash(context, (statement, (A, B).into()));
+        let response = w - wit * challenge;
+        Proof {
+            challenge,
+            response,
+        }
+    }
+    fn verify(&self, context: &ProofContext, p: &Self::Proof) -> bool {
+        let A = (p.response * Point::generator()) + (p.challenge * self.pk);
+        let B = (p.response * self.alpha) + (p.challenge * self.factor);
+        let statement = [self.pk, self.alpha, self.factor];
+        p.challenge == Self::hash(context, (statement, (A, B).into()))
     }
 }
 
 /// Proof of Section 4.11.
-pub(crate) struct IntervalMembership {
-    pub(crate) ctxt: Ciphertext,
+pub(crate) struct IntervalMembership<G: Group = Ristretto> {
+    pub(crate) ctxt: Ciphertext<G>,
     // y is election public key, see section 4.10.1
-    pub(crate) y: Point,
-    pub(crate) rng: Arc<Mutex<dyn SecureRandom>>,
-    pub(crate) finite_set: Vec<Scalar>,
+    pub(crate) y: Point<G>,
+    pub(crate) finite_set: Vec<Scalar<G>>,
     // Not strictly needed for the proof, but prepended to hash calls.
     pub(crate) S: Vec<u8>,
 }
 
-pub(crate) struct IntervalMembershipWitness {
-    pub(crate) r: Scalar,
+pub(crate) struct IntervalMembershipWitness<G: Group = Ristretto> {
+    pub(crate) r: Scalar<G>,
     pub(crate) i: usize,
 }
 
-impl ProofSystem for IntervalMembership {
-    type Witness = IntervalMembershipWitness;
-    type Proof = Vec<Proof>;
+impl<G: Group> ProofSystem<G> for IntervalMembership<G> {
+    type Witness = IntervalMembershipWitness<G>;
+    type Proof = Vec<Proof<G>>;
     // Transcript is S, (alpha, beta), along with (A0, B0),..., (Ak-1, Bk-1).
-    type Transcript = (Vec<u8>, Ciphertext, Vec<Ciphertext>);
+    type Transcript = (Vec<u8>, Ciphertext<G>, Vec<Ciphertext<G>>);
     const DOMAIN_SEP: &'static str = "prove";
-    fn hash(trans: Self::Transcript) -> Scalar {
+    fn hash(context: &ProofContext, trans: Self::Transcript) -> Scalar<G> {
         let (s, ctxt, rest) = trans;
-        let first_data = [
-            Self::DOMAIN_SEP.as_bytes(),
-            &s,
-            &ctxt.alpha.as_bytes(),
-            &ctxt.beta.as_bytes(),
-        ]
-        .concat();
-        let second_data: Vec<u8> = rest
-            .into_iter()
-            .map(|c| c.into())
-            .map(|(a, b)| [a.as_bytes(), b.as_bytes()].concat())
-            .flatten()
-            .collect();
-        let data = [&first_data[..], &second_data[..]].concat();
-        Scalar::hash_to_scalar(&data)
+        let mut transcript = Self::statement_transcript(context, &s, &ctxt);
+        for c in rest.iter() {
+            append_commitment(&mut transcript, c);
+        }
+        transcript.challenge_scalar("challenge")
+    }
+    fn prove(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> Self::Proof {
+        self.prove_with_commitments(rng, context, wit).0
+    }
+    fn verify(&self, context: &ProofContext, p: &Self::Proof) -> bool {
+        verify_alone(self, context, p)
+    }
+}
+
+impl<G: Group> IntervalMembership<G> {
+    fn statement_transcript(context: &ProofContext, S: &[u8], ctxt: &Ciphertext<G>) -> Transcript {
+        let mut transcript = context.transcript::<G>(Self::DOMAIN_SEP);
+        transcript.append_message("S", S);
+        transcript.append_point("alpha", &ctxt.alpha);
+        transcript.append_point("beta", &ctxt.beta);
+        transcript
+    }
+}
+
+impl<G: Group> Batchable<G> for IntervalMembership<G> {
+    fn transcript(&self, context: &ProofContext) -> Transcript {
+        Self::statement_transcript(context, &self.S, &self.ctxt)
     }
-    fn prove(&self, wit: &Self::Witness) -> Self::Proof {
-        let mut proof: Vec<Proof> = Vec::new();
+    fn commitments(&self, p: &[Proof<G>]) -> Option<Vec<Terms<G>>> {
+        if p.len() != self.finite_set.len() {
+            return None;
+        }
+        let statements = self.finite_set.iter().map(|m| (&self.ctxt, m));
+        Some(simulated_commitments(self.y, statements, p))
+    }
+    fn prove_with_commitments(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> (Vec<Proof<G>>, Vec<Ciphertext<G>>) {
+        let mut proof: Vec<Proof<G>> = Vec::new();
         let mut ctxts = Vec::new();
         for j in 0..self.finite_set.len() {
-            let challenge = Scalar::sample_uniform(self.rng.clone());
-            let response = Scalar::sample_uniform(self.rng.clone());
+            let challenge = Scalar::sample_uniform(rng.clone());
+            let response = Scalar::sample_uniform(rng.clone());
             proof.push((challenge, response).into());
             let (alpha, beta) = self.ctxt.into();
             let A_j = (response * Point::generator()) + (challenge * alpha);
@@ -149,35 +275,393 @@ impl ProofSystem for IntervalMembership {
             ctxts.push((A_j, B_j).into());
         }
         // Fixing the case of j = wit.i
-        let w = Scalar::sample_uniform(self.rng.clone());
+        let w = Scalar::sample_uniform(rng.clone());
         let A_i = Point::generator() * w;
         let B_i = self.y * w;
         ctxts[wit.i] = (A_i, B_i).into();
-        let trans = (self.S.clone(), self.ctxt, ctxts);
-        let mut challenge_i = Self::hash(trans);
-        for j in 0..self.finite_set.len() {
+        let trans = (self.S.clone(), self.ctxt, ctxts.clone());
+        let mut challenge_i = Self::hash(context, trans);
+        for (j, pf) in proof.iter().enumerate() {
             if j != wit.i {
-                challenge_i = challenge_i - proof[j].challenge;
+                challenge_i = challenge_i - pf.challenge;
             }
         }
         let response_i = w - wit.r * challenge_i;
         proof[wit.i] = (challenge_i, response_i).into();
-        proof
+        (proof, ctxts)
     }
-    fn verify(&self, p: &Self::Proof) -> bool {
-        let mut ctxts: Vec<Ciphertext> = Vec::new();
-        let mut chal_sum = Scalar::zero();
-        for j in 0..self.finite_set.len() {
-            let (challenge, response) = (p[j].challenge, p[j].response);
-            let (alpha, beta) = self.ctxt.into();
-            let A_j = (response * Point::generator()) + (challenge * alpha);
-            let B_j = (response * self.y)
-                + (beta + (Point::generator() * -self.finite_set[j])) * challenge;
-            ctxts.push((A_j, B_j).into());
-            chal_sum = chal_sum + challenge;
+}
+
+/// For proving that for some j, `statements[j].0` is an encryption of `statements[j].1`.
+/// This generalizes `IntervalMembership` (where every ciphertext is the same) to disjunctions over
+/// different ciphertexts, which are needed for blank votes (see section 4.11 of the
+/// specification), e.g. to prove that either the blank choice is 0, or the sum of the other
+/// choices is 0.
+pub(crate) struct Disjunction<G: Group = Ristretto> {
+    pub(crate) statements: Vec<(Ciphertext<G>, Scalar<G>)>,
+    // y is election public key, see section 4.10.1
+    pub(crate) y: Point<G>,
+    // Not strictly needed for the proof, but prepended to hash calls.
+    pub(crate) S: Vec<u8>,
+}
+
+impl<G: Group> Disjunction<G> {
+    /// The commitment (A_j, B_j) of the j-th statement that makes (challenge, response) an
+    /// accepting transcript.
+    fn simulate(&self, j: usize, challenge: Scalar<G>, response: Scalar<G>) -> Ciphertext<G> {
+        let ((alpha, beta), m) = (self.statements[j].0.into(), self.statements[j].1);
+        let A_j = (response * Point::generator()) + (challenge * alpha);
+        let B_j = (response * self.y) + (beta + (Point::generator() * -m)) * challenge;
+        (A_j, B_j).into()
+    }
+}
+
+impl<G: Group> ProofSystem<G> for Disjunction<G> {
+    type Witness = IntervalMembershipWitness<G>;
+    type Proof = Vec<Proof<G>>;
+    // Transcript is S, the statements, along with (A0, B0),..., (Ak-1, Bk-1).
+    type Transcript = (Vec<u8>, Vec<(Ciphertext<G>, Scalar<G>)>, Vec<Ciphertext<G>>);
+    const DOMAIN_SEP: &'static str = "disj";
+    fn hash(context: &ProofContext, trans: Self::Transcript) -> Scalar<G> {
+        let (s, statements, rest) = trans;
+        let mut transcript = Self::statement_transcript(context, &s, &statements);
+        for c in rest.iter() {
+            append_commitment(&mut transcript, c);
+        }
+        transcript.challenge_scalar("challenge")
+    }
+    fn prove(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> Self::Proof {
+        self.prove_with_commitments(rng, context, wit).0
+    }
+    fn verify(&self, context: &ProofContext, p: &Self::Proof) -> bool {
+        verify_alone(self, context, p)
+    }
+}
+
+impl<G: Group> Disjunction<G> {
+    fn statement_transcript(
+        context: &ProofContext,
+        S: &[u8],
+        statements: &[(Ciphertext<G>, Scalar<G>)],
+    ) -> Transcript {
+        let mut transcript = context.transcript::<G>(Self::DOMAIN_SEP);
+        transcript.append_message("S", S);
+        for (ctxt, m) in statements.iter() {
+            transcript.append_point("alpha", &ctxt.alpha);
+            transcript.append_point("beta", &ctxt.beta);
+            transcript.append_scalar("m", m);
+        }
+        transcript
+    }
+}
+
+impl<G: Group> Batchable<G> for Disjunction<G> {
+    fn transcript(&self, context: &ProofContext) -> Transcript {
+        Self::statement_transcript(context, &self.S, &self.statements)
+    }
+    fn commitments(&self, p: &[Proof<G>]) -> Option<Vec<Terms<G>>> {
+        if p.len() != self.statements.len() {
+            return None;
+        }
+        let statements = self.statements.iter().map(|(ctxt, m)| (ctxt, m));
+        Some(simulated_commitments(self.y, statements, p))
+    }
+    fn prove_with_commitments(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> (Vec<Proof<G>>, Vec<Ciphertext<G>>) {
+        let mut proof: Vec<Proof<G>> = Vec::new();
+        let mut ctxts = Vec::new();
+        for j in 0..self.statements.len() {
+            let challenge = Scalar::sample_uniform(rng.clone());
+            let response = Scalar::sample_uniform(rng.clone());
+            proof.push((challenge, response).into());
+            ctxts.push(self.simulate(j, challenge, response));
+        }
+        // Fixing the case of j = wit.i
+        let w = Scalar::sample_uniform(rng.clone());
+        ctxts[wit.i] = (Point::generator() * w, self.y * w).into();
+        let trans = (self.S.clone(), self.statements.clone(), ctxts.clone());
+        let mut challenge_i = Self::hash(context, trans);
+        for (j, pf) in proof.iter().enumerate() {
+            if j != wit.i {
+                challenge_i = challenge_i - pf.challenge;
+            }
+        }
+        let response_i = w - wit.r * challenge_i;
+        proof[wit.i] = (challenge_i, response_i).into();
+        (proof, ctxts)
+    }
+}
+
+/// Proofs made of a (challenge, response) pair for each statement of a disjunction, which verify
+/// when the hash of the statements and of the commitments (A_j, B_j) recomputed from each pair is
+/// the sum of the challenges.
+///
+/// The prover may also hand out its commitments, so that a `BatchVerifier` checks the hashes
+/// against them, and the equations giving each commitment from its pair all at once, as a single
+/// random linear combination.
+pub(crate) trait Batchable<G: Group>: ProofSystem<G, Proof = Vec<Proof<G>>> {
+    /// The transcript of the statement, to which the commitments are appended.
+    fn transcript(&self, context: &ProofContext) -> Transcript;
+    /// The multiscalar multiplications computing A_0, B_0, A_1, B_1, ... from a proof, or None if
+    /// it has the wrong number of pairs.
+    fn commitments(&self, p: &[Proof<G>]) -> Option<Vec<Terms<G>>>;
+    /// Proves the statement as `prove` does, also returning the commitments (A_j, B_j).
+    fn prove_with_commitments(
+        &self,
+        rng: Arc<Mutex<dyn SecureRandom>>,
+        context: &ProofContext,
+        wit: &Self::Witness,
+    ) -> (Vec<Proof<G>>, Vec<Ciphertext<G>>);
+}
+
+/// The commitments A_j = r_j * g + c_j * alpha_j and B_j = r_j * y + c_j * (beta_j - m_j * g)
+/// which make each (c_j, r_j) an accepting transcript for the statement (alpha_j, beta_j), m_j.
+fn simulated_commitments<'a, G: Group>(
+    y: Point<G>,
+    statements: impl Iterator<Item = (&'a Ciphertext<G>, &'a Scalar<G>)>,
+    p: &[Proof<G>],
+) -> Vec<Terms<G>> {
+    let g = Point::<G>::generator().0;
+    let mut terms = Vec::with_capacity(2 * p.len());
+    for ((ctxt, m), pf) in statements.zip(p.iter()) {
+        let (c, r) = (pf.challenge, pf.response);
+        terms.push((vec![r.0, c.0], vec![g, ctxt.alpha.0]));
+        terms.push((vec![r.0, c.0, (-(c * *m)).0], vec![y.0, ctxt.beta.0, g]));
+    }
+    terms
+}
+
+fn append_commitment<G: Group>(transcript: &mut Transcript, commitment: &Ciphertext<G>) {
+    transcript.append_point("A", &commitment.alpha);
+    transcript.append_point("B", &commitment.beta);
+}
+
+/// Appends the encodings of A_0, B_0, A_1, B_1, ... as `append_commitment` would.
+fn append_encoded_commitments(transcript: &mut Transcript, encoded: &[Vec<u8>]) {
+    for (bytes, label) in encoded.iter().zip(["A", "B"].iter().cycle()) {
+        transcript.append_message(label, bytes);
+    }
+}
+
+fn challenge_sum<G: Group>(p: &[Proof<G>]) -> Scalar<G> {
+    p.iter().fold(Scalar::zero(), |sum, pf| sum + pf.challenge)
+}
+
+fn verify_alone<G: Group, P: Batchable<G>>(
+    instance: &P,
+    context: &ProofContext,
+    p: &[Proof<G>],
+) -> bool {
+    let terms = match instance.commitments(p) {
+        Some(terms) => terms,
+        None => return false,
+    };
+    let encoded: Vec<Vec<u8>> = terms
+        .iter()
+        .map(|(scalars, elements)| {
+            G::element_to_bytes(&G::vartime_multiscalar_mul(scalars, elements))
+        })
+        .collect();
+    let mut transcript = instance.transcript(context);
+    append_encoded_commitments(&mut transcript, &encoded);
+    transcript.challenge_scalar::<G>("challenge") == challenge_sum(p)
+}
+
+/// The number of commitments encoded together (and on the same thread, with the `parallel`
+/// feature) by a `BatchVerifier`.
+const ENCODING_CHUNK: usize = 64;
+
+/// The number of commitments whose equations are summed in one multiscalar multiplication (on
+/// the same thread, with the `parallel` feature) by a `BatchVerifier`.
+const COMBINATION_CHUNK: usize = 256;
+
+/// A proof waiting in a `BatchVerifier`.
+struct Pending<G: Group> {
+    group: usize,
+    // The transcript of the statement, to which the commitments are appended.
+    transcript: Transcript,
+    challenge_sum: Scalar<G>,
+    // The range of the commitments of the proof in `BatchVerifier::commitments`, and of the ones
+    // the prover claims, if any, in `BatchVerifier::claims`.
+    range: Range<usize>,
+    claims: Option<Range<usize>>,
+}
+
+impl<G: Group> Pending<G> {
+    fn verifies(&self, encoded: &[Vec<u8>]) -> bool {
+        let mut transcript = self.transcript.clone();
+        append_encoded_commitments(&mut transcript, encoded);
+        transcript.challenge_scalar::<G>("challenge") == self.challenge_sum
+    }
+}
+
+/// Verifies many `Batchable` proofs at once, sorted into groups (e.g. the proofs of a ballot),
+/// with the same results as verifying each of them on its own.
+///
+/// For the proofs which come with the commitments of the prover, the hashes are checked against
+/// them, and that each commitment C_k is the sum T_k given by the pairs of its proof is checked
+/// for all of them at once, as the sum of the z_k * (T_k - C_k) being the identity.
+/// The weights z_k are derived from a hash of the proofs, so that (as long as the hash is random)
+/// a wrong commitment makes this fail but with negligible probability.
+/// Any proof whose claimed commitments fail either check, or which came without them, is checked
+/// by recomputing its commitments, so that a prover gains nothing from claiming wrong ones.
+/// With the `parallel` feature, the commitments are computed and the challenges hashed across
+/// threads.
+pub(crate) struct BatchVerifier<G: Group = Ristretto> {
+    // Whether every proof of each group verified so far.
+    valid: Vec<bool>,
+    pending: Vec<Pending<G>>,
+    commitments: Vec<Terms<G>>,
+    claims: Vec<Point<G>>,
+}
+
+impl<G: Group> BatchVerifier<G> {
+    pub(crate) fn new(num_groups: usize) -> Self {
+        BatchVerifier {
+            valid: vec![true; num_groups],
+            pending: Vec::new(),
+            commitments: Vec::new(),
+            claims: Vec::new(),
+        }
+    }
+    /// Adds a proof to a group, along with the commitments (A_j, B_j) the prover claims, if any.
+    /// Claimed commitments of the wrong number are ignored.
+    pub(crate) fn push<P: Batchable<G>>(
+        &mut self,
+        group: usize,
+        context: &ProofContext,
+        instance: &P,
+        p: &[Proof<G>],
+        claimed: Option<&[Ciphertext<G>]>,
+    ) {
+        if !self.valid[group] {
+            return;
+        }
+        let terms = match instance.commitments(p) {
+            Some(terms) => terms,
+            None => return self.reject(group),
+        };
+        let claims = match claimed {
+            Some(claimed) if 2 * claimed.len() == terms.len() => {
+                let start = self.claims.len();
+                for commitment in claimed.iter() {
+                    self.claims.extend([commitment.alpha, commitment.beta]);
+                }
+                Some(start..self.claims.len())
+            }
+            _ => None,
+        };
+        let start = self.commitments.len();
+        self.pending.push(Pending {
+            group,
+            transcript: instance.transcript(context),
+            challenge_sum: challenge_sum(p),
+            range: start..start + terms.len(),
+            claims,
+        });
+        self.commitments.extend(terms);
+    }
+    /// Marks a group as invalid, e.g. for a malformed ballot.
+    pub(crate) fn reject(&mut self, group: usize) {
+        self.valid[group] = false;
+    }
+    /// Whether all the proofs of each group verify.
+    pub(crate) fn verify(mut self) -> Vec<bool> {
+        let (claimed, mut recomputed): (Vec<usize>, Vec<usize>) =
+            (0..self.pending.len()).partition(|k| self.pending[*k].claims.is_some());
+        let consistent = parallel::map(&claimed, |k| {
+            let pending = &self.pending[*k];
+            let claims = &self.claims[pending.claims.clone().unwrap()];
+            let encoded: Vec<Vec<u8>> = claims.iter().map(|claim| claim.as_bytes()).collect();
+            pending.verifies(&encoded)
+        });
+        let mut consistent = consistent.into_iter();
+        let (claimed, inconsistent): (Vec<usize>, Vec<usize>) = claimed
+            .into_iter()
+            .partition(|_| consistent.next().unwrap());
+        recomputed.extend(inconsistent);
+        if !self.claims_combine(&claimed) {
+            recomputed.extend(claimed);
+        }
+
+        recomputed.sort_unstable();
+        let mut terms = Vec::new();
+        let mut ranges = Vec::new();
+        for k in recomputed.iter() {
+            let range = self.pending[*k].range.clone();
+            ranges.push(terms.len()..terms.len() + range.len());
+            terms.extend(self.commitments[range].iter().cloned());
+        }
+        let encoded = parallel::flat_map_chunks(
+            &terms,
+            ENCODING_CHUNK,
+            G::vartime_multiscalar_mul_to_bytes_batch,
+        );
+        let pending: Vec<(&Pending<G>, Range<usize>)> = recomputed
+            .iter()
+            .map(|k| &self.pending[*k])
+            .zip(ranges)
+            .collect();
+        let verified = parallel::map(&pending, |(pending, range)| {
+            pending.verifies(&encoded[range.clone()])
+        });
+        let failed: Vec<usize> = pending
+            .iter()
+            .zip(verified)
+            .filter(|(_, verified)| !verified)
+            .map(|((pending, _), _)| pending.group)
+            .collect();
+        for group in failed {
+            self.valid[group] = false;
+        }
+        self.valid
+    }
+    /// Whether the claimed commitments of the `pending` proofs (whose hashes were checked against
+    /// them) are the ones their pairs give, as a random linear combination.
+    fn claims_combine(&self, pending: &[usize]) -> bool {
+        if pending.is_empty() {
+            return true;
+        }
+        // The weights depend on the challenges, which are the hashes of the statements and of
+        // the claimed commitments, and on the pairs.
+        let mut transcript = Transcript::new("batch");
+        let mut equations = Vec::new();
+        for k in pending.iter() {
+            let pending = &self.pending[*k];
+            transcript.append_scalar("challenge", &pending.challenge_sum);
+            for (scalars, _) in self.commitments[pending.range.clone()].iter() {
+                for s in scalars.iter() {
+                    transcript.append_scalar("s", &Scalar::<G>(*s));
+                }
+            }
+            equations.extend(pending.range.clone().zip(pending.claims.clone().unwrap()));
         }
-        let trans = (self.S.clone(), self.ctxt, ctxts);
-        Self::hash(trans) == chal_sum
+        let seed = transcript.challenge_scalar::<G>("seed").as_bytes();
+        let sums = parallel::flat_map_chunks(&equations, COMBINATION_CHUNK, |chunk| {
+            let mut scalars = Vec::new();
+            let mut elements = Vec::new();
+            for (t, c) in chunk.iter() {
+                let z =
+                    Scalar::<G>::hash_to_scalar(&[&seed[..], &(*t as u64).to_le_bytes()].concat())
+                        .0;
+                let (term_scalars, term_elements) = &self.commitments[*t];
+                scalars.extend(term_scalars.iter().map(|s| z * *s));
+                elements.extend(term_elements.iter().copied());
+                scalars.push(-z);
+                elements.push(self.claims[*c].0);
+            }
+            vec![G::vartime_multiscalar_mul(&scalars, &elements)]
+        });
+        sums.into_iter().fold(G::identity(), |sum, x| sum + x) == G::identity()
     }
 }
 
@@ -188,40 +672,121 @@ mod tests {
     use ring::rand::SystemRandom;
 
     use super::*;
+
+    type Point = super::Point<Ristretto>;
+    type Scalar = super::Scalar<Ristretto>;
+    type Ciphertext = super::Ciphertext<Ristretto>;
     const TRIALS: usize = 100;
 
     #[test]
     fn dlog_completeness() {
         let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
         for _ in 0..TRIALS {
             let w = Scalar::sample_uniform(rng.clone());
             let pt = Point::generator() * w;
-            let instance = DLog {
-                pt,
-                rng: rng.clone(),
-            };
-            let proof = instance.prove(&w);
-            assert!(instance.verify(&proof));
+            let instance = DLog { pt };
+            let proof = instance.prove(rng.clone(), &context, &w);
+            assert!(instance.verify(&context, &proof));
         }
     }
     #[test]
     fn dlog_soundness() {
         let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
         for _ in 0..TRIALS {
             let w = Scalar::sample_uniform(rng.clone());
             let pt = Point::generator() * w;
             let w = Scalar::sample_uniform(rng.clone());
-            let instance = DLog {
-                pt,
-                rng: rng.clone(),
+            let instance = DLog { pt };
+            let proof = instance.prove(rng.clone(), &context, &w);
+            assert!(!instance.verify(&context, &proof));
+        }
+    }
+    #[test]
+    fn equal_dlog_completeness() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        for _ in 0..TRIALS {
+            let x = Scalar::sample_uniform(rng.clone());
+            let alpha = Point::sample_uniform(rng.clone());
+            let instance = EqualDLog {
+                pk: Point::generator() * x,
+                alpha,
+                factor: alpha * x,
+            };
+            let proof = instance.prove(rng.clone(), &context, &x);
+            assert!(instance.verify(&context, &proof));
+        }
+    }
+    #[test]
+    fn equal_dlog_soundness() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        for _ in 0..TRIALS {
+            let x = Scalar::sample_uniform(rng.clone());
+            let alpha = Point::sample_uniform(rng.clone());
+            // A decryption factor computed with a different private key.
+            let y = Scalar::sample_uniform(rng.clone());
+            let instance = EqualDLog {
+                pk: Point::generator() * x,
+                alpha,
+                factor: alpha * y,
             };
-            let proof = instance.prove(&w);
-            assert!(!instance.verify(&proof));
+            assert!(!instance.verify(&context, &instance.prove(rng.clone(), &context, &x)));
+            assert!(!instance.verify(&context, &instance.prove(rng.clone(), &context, &y)));
         }
     }
     #[test]
+    fn proofs_are_bound_to_their_context() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let uuid = UUID::gen(rng.clone());
+        let context = ProofContext::new(&uuid, Some(0));
+        let other_election = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let other_participant = ProofContext::new(&uuid, Some(1));
+        // The proof of knowledge of a trustee's private key.
+        let x = Scalar::sample_uniform(rng.clone());
+        let instance = DLog {
+            pt: Point::generator() * x,
+        };
+        let proof = instance.prove(rng.clone(), &context, &x);
+        assert!(instance.verify(&context, &proof));
+        assert!(!instance.verify(&other_election, &proof));
+        assert!(!instance.verify(&other_participant, &proof));
+        // The proof of a decryption factor.
+        let alpha = Point::sample_uniform(rng.clone());
+        let instance = EqualDLog {
+            pk: Point::generator() * x,
+            alpha,
+            factor: alpha * x,
+        };
+        let proof = instance.prove(rng.clone(), &context, &x);
+        assert!(instance.verify(&context, &proof));
+        assert!(!instance.verify(&other_election, &proof));
+        assert!(!instance.verify(&other_participant, &proof));
+        // The (anonymous) proof that a voter encrypted 0 or 1.
+        let y = Point::sample_uniform(rng.clone());
+        let r = Scalar::sample_uniform(rng.clone());
+        let instance = IntervalMembership {
+            ctxt: (Point::generator() * r, y * r).into(),
+            y,
+            finite_set: vec![Scalar::zero(), Scalar::one()],
+            S: Vec::new(),
+        };
+        let anonymous = ProofContext::new(&uuid, None);
+        let proof = instance.prove(
+            rng.clone(),
+            &anonymous,
+            &IntervalMembershipWitness { r, i: 0 },
+        );
+        assert!(instance.verify(&anonymous, &proof));
+        assert!(!instance.verify(&ProofContext::new(&other_election.election, None), &proof));
+        assert!(!instance.verify(&context, &proof));
+    }
+    #[test]
     fn interval_completeness() {
         let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
         const N: usize = 2;
         let finite_set = vec![Scalar::zero(), Scalar::one()];
         let S: Vec<u8> = String::from_str("words").unwrap().into();
@@ -236,19 +801,19 @@ mod tests {
                 let instance = IntervalMembership {
                     ctxt,
                     y,
-                    rng: rng.clone(),
                     finite_set: finite_set.clone(),
                     S: S.clone(),
                 };
                 let w = IntervalMembershipWitness { r, i };
-                let proof = instance.prove(&w);
-                assert!(instance.verify(&proof));
+                let proof = instance.prove(rng.clone(), &context, &w);
+                assert!(instance.verify(&context, &proof));
             }
         }
     }
     #[test]
     fn interval_soundness() {
         let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
         const N: usize = 2;
         let finite_set = vec![Scalar::zero(), Scalar::one()];
         let S: Vec<u8> = String::from_str("words").unwrap().into();
@@ -263,15 +828,173 @@ mod tests {
                 let instance = IntervalMembership {
                     ctxt,
                     y,
-                    rng: rng.clone(),
                     finite_set: finite_set.clone(),
                     S: S.clone(),
                 };
                 let r = Scalar::sample_uniform(rng.clone());
                 let w = IntervalMembershipWitness { r, i };
-                let proof = instance.prove(&w);
-                assert!(!instance.verify(&proof));
+                let proof = instance.prove(rng.clone(), &context, &w);
+                assert!(!instance.verify(&context, &proof));
+            }
+        }
+    }
+    #[test]
+    fn disjunction_completeness() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let S: Vec<u8> = String::from_str("words").unwrap().into();
+        for _ in 0..TRIALS {
+            let y = Point::sample_uniform(rng.clone());
+            let r = Scalar::sample_uniform(rng.clone());
+            let ctxt: Ciphertext = (Point::generator() * r, y * r + Point::generator()).into();
+            let other = Ciphertext::from((
+                Point::sample_uniform(rng.clone()),
+                Point::sample_uniform(rng.clone()),
+            ));
+            // ctxt is an encryption of 1, and other is an encryption of nothing in particular.
+            let statements = vec![(other, Scalar::zero()), (ctxt, Scalar::one())];
+            let instance = Disjunction {
+                statements,
+                y,
+                S: S.clone(),
+            };
+            let proof = instance.prove(
+                rng.clone(),
+                &context,
+                &IntervalMembershipWitness { r, i: 1 },
+            );
+            assert!(instance.verify(&context, &proof));
+        }
+    }
+    #[test]
+    fn disjunction_soundness() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let S: Vec<u8> = String::from_str("words").unwrap().into();
+        for _ in 0..TRIALS {
+            let y = Point::sample_uniform(rng.clone());
+            let r = Scalar::sample_uniform(rng.clone());
+            let s = Scalar::sample_uniform(rng.clone());
+            // Encryptions of 1 and 0, claimed to be encryptions of 0 and 1.
+            let one: Ciphertext = (Point::generator() * r, y * r + Point::generator()).into();
+            let zero: Ciphertext = (Point::generator() * s, y * s).into();
+            let statements = vec![(one, Scalar::zero()), (zero, Scalar::one())];
+            let instance = Disjunction {
+                statements,
+                y,
+                S: S.clone(),
+            };
+            for (r, i) in [(r, 0), (r, 1), (s, 0), (s, 1)] {
+                let proof =
+                    instance.prove(rng.clone(), &context, &IntervalMembershipWitness { r, i });
+                assert!(!instance.verify(&context, &proof));
             }
         }
     }
+    #[test]
+    fn batch_verification_matches_verify() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), Some(0));
+        let S: Vec<u8> = String::from_str("words").unwrap().into();
+        let y = Point::sample_uniform(rng.clone());
+        let finite_set = vec![Scalar::zero(), Scalar::one()];
+        let interval = |ctxt| IntervalMembership {
+            ctxt,
+            y,
+            finite_set: finite_set.clone(),
+            S: S.clone(),
+        };
+        let r = Scalar::sample_uniform(rng.clone());
+        let one = interval((Point::generator() * r, y * r + Point::generator()).into());
+        let valid = one.prove(
+            rng.clone(),
+            &context,
+            &IntervalMembershipWitness { r, i: 1 },
+        );
+        let invalid = one.prove(
+            rng.clone(),
+            &context,
+            &IntervalMembershipWitness { r, i: 0 },
+        );
+        // A transcript whose first commitments are the identity, for the encryption (g, y) of 0.
+        let zero = interval((Point::generator(), y).into());
+        let c = Scalar::sample_uniform(rng.clone());
+        let mut identity = zero.prove(
+            rng.clone(),
+            &context,
+            &IntervalMembershipWitness {
+                r: Scalar::one(),
+                i: 0,
+            },
+        );
+        identity[0] = Proof {
+            challenge: c,
+            response: -c,
+        };
+        let disjunction = Disjunction {
+            statements: vec![(one.ctxt, Scalar::zero()), (one.ctxt, Scalar::one())],
+            y,
+            S: S.clone(),
+        };
+        let disjunction_proof = disjunction.prove(
+            rng.clone(),
+            &context,
+            &IntervalMembershipWitness { r, i: 1 },
+        );
+
+        let mut batch = BatchVerifier::new(6);
+        batch.push(0, &context, &one, &valid, None);
+        batch.push(0, &context, &disjunction, &disjunction_proof, None);
+        batch.push(1, &context, &one, &valid, None);
+        batch.push(1, &context, &one, &invalid, None);
+        batch.push(2, &context, &zero, &identity, None);
+        batch.push(3, &context, &one, &valid[..1], None);
+        batch.push(4, &context, &one, &valid, None);
+        batch.reject(4);
+        let expected = vec![
+            one.verify(&context, &valid) && disjunction.verify(&context, &disjunction_proof),
+            one.verify(&context, &valid) && one.verify(&context, &invalid),
+            zero.verify(&context, &identity),
+            one.verify(&context, &valid[..1].to_vec()),
+            false,
+            true,
+        ];
+        assert_eq!(expected, vec![true, false, false, false, false, true]);
+        assert_eq!(batch.verify(), expected);
+    }
+
+    #[test]
+    fn claimed_commitments_combine() {
+        let rng = Arc::new(Mutex::new(SystemRandom::new()));
+        let context = ProofContext::new(&UUID::gen(rng.clone()), None);
+        let y = Point::sample_uniform(rng.clone());
+        let r = Scalar::sample_uniform(rng.clone());
+        let one = IntervalMembership {
+            ctxt: (Point::generator() * r, y * r + Point::generator()).into(),
+            y,
+            finite_set: vec![Scalar::zero(), Scalar::one()],
+            S: vec![],
+        };
+        let prove = |i| {
+            one.prove_with_commitments(rng.clone(), &context, &IntervalMembershipWitness { r, i })
+        };
+        let (valid, commitments) = prove(1);
+        let (other, other_commitments) = prove(1);
+        // With the wrong witness, the hash matches the claimed commitments, but B_0 is not the one
+        // the pair gives.
+        let (invalid, invalid_commitments) = prove(0);
+
+        let mut batch = BatchVerifier::new(5);
+        batch.push(0, &context, &one, &valid, Some(&commitments));
+        batch.push(1, &context, &one, &other, Some(&commitments));
+        batch.push(2, &context, &one, &other, Some(&other_commitments[..1]));
+        batch.push(3, &context, &one, &invalid, Some(&invalid_commitments));
+        batch.push(4, &context, &one, &other, Some(&other_commitments));
+        // Claims of the wrong number are ignored, and proofs 1 and 3 (whose claims do not match
+        // their hash, or their pairs) are checked again by recomputing their commitments.
+        assert!(batch.pending[2].claims.is_none());
+        assert!(batch.claims_combine(&[0, 4]));
+        assert!(!batch.claims_combine(&[0, 3, 4]));
+        assert_eq!(batch.verify(), vec![true, true, true, false, true]);
+    }
 }
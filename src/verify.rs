@@ -0,0 +1,677 @@
+//! Universal verification, e.g. the checks anybody can run on the public record of an election
+//! (the log of its bulletin board) to convince themselves its result is correct, without
+//! trusting the voting server.
+//!
+//! Every check is run, and the report lists all of the failing ones rather than only the first.
+
+use std::collections::{HashMap, HashSet};
+
+use ring::digest::SHA256_OUTPUT_LEN;
+
+use crate::datatypes::ballot::Ballot;
+use crate::datatypes::bulletin_board::{Entry, Event};
+use crate::datatypes::election::Election;
+use crate::parallel;
+use crate::participants::messages::{valid_threshold, C3M_CA_to_VS, T3Mi};
+use crate::participants::voting_server::{
+    aggregate_ballots, combine_partial_decryptions, same_shape,
+};
+use crate::primitives::group::{Group, Point, Scalar};
+use crate::primitives::pki::Ciphertext;
+use crate::primitives::vss::{lagrange_coefficient, PolynomialCommitment};
+use crate::primitives::zkp::{DLog, EqualDLog, Proof, ProofContext, ProofSystem};
+
+/// A failing check. Indices of entries are into the log, indices of trustees are into the keys
+/// of the `TrusteeKeys` event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum VerificationFailure {
+    /// The entry does not chain to the one before it.
+    BrokenChain(usize),
+    /// The entry is out of the order of the steps of the protocol, or repeats a step which only
+    /// happens once. It is ignored by the other checks.
+    UnexpectedEvent(usize),
+    /// The election, the list L or the trustee keys are missing, so nothing else is checked.
+    IncompleteRecord,
+    /// The credential at this index of L already appears earlier in L.
+    DuplicateCredential(usize),
+    /// The proof of knowledge of the private key of the trustee is missing or fails.
+    TrusteeProof(usize),
+    /// The published commitments of this qualified dealer (of "pedersen" trustees) fail to
+    /// verify.
+    Dealing(usize),
+    /// The verification key of this "pedersen" trustee is not the one the commitments of the
+    /// qualified dealers give.
+    VerificationKey(usize),
+    /// The election public key is not the combination of the trustee keys (or, for "pedersen"
+    /// trustees, of the commitments of the qualified dealers).
+    ElectionKey,
+    /// The ballot (or credential update) of the entry is for a credential which is not in L.
+    UnknownCredential(usize),
+    /// The credential update of the entry introduces a credential which is already in L.
+    CredentialUpdate(usize),
+    /// The ballot of the entry is not signed under its credential.
+    BallotSignature(usize),
+    /// The ballot of the entry is not for this election, or its proofs fail.
+    BallotProof(usize),
+    /// The encrypted tally is not the weighted sum of the counted ballots.
+    EncryptedTally,
+    /// The partial decryption of the trustee is missing, malformed, or its proofs fail.
+    PartialDecryption(usize),
+    /// Too few trustees gave a valid partial decryption to decrypt the tally.
+    NotEnoughPartialDecryptions,
+    /// The result is not the decryption of the encrypted tally, or comes without the encrypted
+    /// tally or the partial decryptions.
+    Result,
+}
+
+pub struct VerificationReport {
+    /// The head of the log, which should be compared with the published one to detect entries
+    /// removed from its end.
+    pub head: Vec<u8>,
+    pub failures: Vec<VerificationFailure>,
+}
+
+impl VerificationReport {
+    pub fn is_valid(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// The position of each kind of event in the log. Ballots and credential updates are
+/// interleaved, other events happen once.
+fn step<G: Group>(event: &Event<G>) -> usize {
+    match event {
+        Event::Election(_) => 0,
+        Event::Voters(_) => 1,
+        Event::TrusteeKeys { .. } => 2,
+        Event::Ballot(_) | Event::CredentialUpdate(_) => 3,
+        Event::EncryptedTally(_) => 4,
+        Event::PartialDecryptions(_) => 5,
+        Event::Result(_) => 6,
+    }
+}
+
+/// Verifies an election from the log of its bulletin board, e.g. as returned by
+/// `BulletinBoard::import`.
+///
+/// The log may stop before the end of the election, in which case only the steps it contains
+/// are checked.
+pub fn verify_election<G: Group>(entries: &[Entry<G>]) -> VerificationReport {
+    let mut failures = Vec::new();
+    let mut head = vec![0; SHA256_OUTPUT_LEN];
+    for (i, entry) in entries.iter().enumerate() {
+        if entry.previous != head {
+            failures.push(VerificationFailure::BrokenChain(i));
+        }
+        head = entry.hash();
+    }
+
+    let mut election = None;
+    let mut voters = None;
+    let mut trustees = None;
+    let mut updates = Vec::new();
+    let mut tally = None;
+    let mut partial_decryptions = None;
+    let mut result = None;
+    let mut last_step = None;
+    for (i, entry) in entries.iter().enumerate() {
+        let step = step(&entry.event);
+        let in_order = match last_step {
+            None => true,
+            Some(last) => step > last || (step == last && step == 3),
+        };
+        if !in_order {
+            failures.push(VerificationFailure::UnexpectedEvent(i));
+            continue;
+        }
+        last_step = Some(step);
+        match &entry.event {
+            Event::Election(e) => election = Some(e),
+            Event::Voters(L) => voters = Some(L),
+            Event::TrusteeKeys {
+                trustee_keys,
+                proofs,
+                threshold,
+                commitments,
+            } => trustees = Some((trustee_keys, proofs, *threshold, commitments)),
+            Event::Ballot(_) | Event::CredentialUpdate(_) => updates.push((i, &entry.event)),
+            Event::EncryptedTally(t) => tally = Some(t),
+            Event::PartialDecryptions(pds) => partial_decryptions = Some(pds),
+            Event::Result(r) => result = Some(r),
+        }
+    }
+    let (election, L, (trustee_keys, proofs, threshold, commitments)) =
+        match (election, voters, trustees) {
+            (Some(election), Some(L), Some(trustees)) => (election, L, trustees),
+            _ => {
+                failures.push(VerificationFailure::IncompleteRecord);
+                return VerificationReport { head, failures };
+            }
+        };
+
+    verify_trustee_keys(election, trustee_keys, proofs, threshold, &mut failures);
+    if let Some(threshold) = threshold {
+        verify_dealings(
+            election,
+            trustee_keys,
+            threshold,
+            commitments,
+            &mut failures,
+        );
+    }
+    let mut credentials = HashSet::with_capacity(L.len());
+    for (i, (pt, _)) in L.iter().enumerate() {
+        if !credentials.insert(pt.as_bytes()) {
+            failures.push(VerificationFailure::DuplicateCredential(i));
+        }
+    }
+    let counted = replay_ballots(election, L, &updates, &mut failures);
+
+    let tally = match tally {
+        Some(tally) => tally,
+        None => {
+            // There is no result without an encrypted tally to decrypt.
+            if result.is_some() {
+                failures.push(VerificationFailure::Result);
+            }
+            return VerificationReport { head, failures };
+        }
+    };
+    let expected = aggregate_ballots(&election.questions, &counted);
+    if *tally != expected {
+        failures.push(VerificationFailure::EncryptedTally);
+    }
+    match partial_decryptions {
+        Some(partial_decryptions) => verify_decryption(
+            election,
+            tally,
+            trustee_keys,
+            threshold,
+            partial_decryptions,
+            result,
+            &mut failures,
+        ),
+        None if result.is_some() => failures.push(VerificationFailure::Result),
+        None => (),
+    }
+    VerificationReport { head, failures }
+}
+
+/// For "pedersen" trustees, checks the commitments of the qualified dealers, and recomputes the
+/// election public key (the sum of their constant terms) and the verification key of each
+/// trustee from them.
+fn verify_dealings<G: Group>(
+    election: &Election<G>,
+    trustee_keys: &[Point<G>],
+    threshold: usize,
+    commitments: &[Option<PolynomialCommitment<G>>],
+    failures: &mut Vec<VerificationFailure>,
+) {
+    let mut qualified = Vec::new();
+    for (i, commitment) in commitments.iter().enumerate() {
+        if let Some(commitment) = commitment {
+            let context = ProofContext::new(&election.uuid, Some(i));
+            if commitment.verify(&context, threshold) {
+                qualified.push(commitment);
+            } else {
+                failures.push(VerificationFailure::Dealing(i));
+            }
+        }
+    }
+    let election_key = qualified
+        .iter()
+        .fold(Point::identity(), |sum, c| sum + c.coefficients[0]);
+    if qualified.is_empty() || election_key != election.public_key {
+        failures.push(VerificationFailure::ElectionKey);
+    }
+    for (j, vk) in trustee_keys.iter().enumerate() {
+        let expected = qualified
+            .iter()
+            .fold(Point::identity(), |sum, c| sum + c.eval(j as u128 + 1));
+        if *vk != expected {
+            failures.push(VerificationFailure::VerificationKey(j));
+        }
+    }
+}
+
+/// Checks the proof of each trustee key (for its index), and that the election public key is the
+/// sum of the keys of single trustees, or the interpolation at 0 of the key shares of "pedersen"
+/// trustees.
+fn verify_trustee_keys<G: Group>(
+    election: &Election<G>,
+    trustee_keys: &[Point<G>],
+    proofs: &[Option<Proof<G>>],
+    threshold: Option<usize>,
+    failures: &mut Vec<VerificationFailure>,
+) {
+    for (i, pk) in trustee_keys.iter().enumerate() {
+        let instance = DLog { pt: *pk };
+        let context = ProofContext::new(&election.uuid, Some(i));
+        let verified = match proofs.get(i) {
+            Some(Some(proof)) => instance.verify(&context, proof),
+            _ => false,
+        };
+        if !verified {
+            failures.push(VerificationFailure::TrusteeProof(i));
+        }
+    }
+    let election_key = match threshold {
+        None => trustee_keys
+            .iter()
+            .fold(Point::identity(), |sum, pk| sum + *pk),
+        Some(t) if valid_threshold(t, trustee_keys.len()) => {
+            let xs: Vec<u128> = (1..=t as u128).collect();
+            xs.iter()
+                .zip(trustee_keys.iter())
+                .fold(Point::identity(), |sum, (x, vk)| {
+                    sum + (*vk * lagrange_coefficient::<G>(*x, &xs))
+                })
+        }
+        Some(_) => {
+            failures.push(VerificationFailure::ElectionKey);
+            return;
+        }
+    };
+    if election_key != election.public_key {
+        failures.push(VerificationFailure::ElectionKey);
+    }
+}
+
+/// Replays the ballots and credential updates as the voting server processed them, checking
+/// each ballot along the way (the signatures and proofs of all ballots being verified together,
+/// across threads with the `parallel` feature).
+/// Returns the counted ballots, e.g. the latest one of each credential, with their weights.
+fn replay_ballots<'a, G: Group>(
+    election: &Election<G>,
+    L: &[(Point<G>, u128)],
+    updates: &[(usize, &'a Event<G>)],
+    failures: &mut Vec<VerificationFailure>,
+) -> Vec<(&'a Ballot<G>, u128)> {
+    let fingerprint = election.fingerprint();
+    // The weight of each credential (by its encoding) as updated so far, and the index in
+    // `counted` of its latest ballot, as in a `BallotBox`.
+    let mut weights: HashMap<Vec<u8>, u128> = HashMap::with_capacity(L.len());
+    for (pt, wt) in L.iter() {
+        weights.entry(pt.as_bytes()).or_insert(*wt);
+    }
+    let mut latest: HashMap<Vec<u8>, usize> = HashMap::new();
+    let mut counted: Vec<Option<(&Ballot<G>, u128)>> = Vec::new();
+    let ballots: Vec<&Ballot<G>> = updates
+        .iter()
+        .filter_map(|(_, event)| match event {
+            Event::Ballot(ballot) => Some(ballot),
+            _ => None,
+        })
+        .collect();
+    let proofs_valid = Ballot::verify_batch(&ballots, &election.public_key, &election.questions);
+    let mut proofs_valid = proofs_valid.into_iter();
+    let signatures_valid = parallel::map(&ballots, |ballot| ballot.verify_signature());
+    let mut signatures_valid = signatures_valid.into_iter();
+    for (i, event) in updates.iter() {
+        match event {
+            Event::Ballot(ballot) => {
+                let credential = ballot.credential.as_bytes();
+                let weight = match weights.get(&credential) {
+                    Some(wt) => *wt,
+                    None => {
+                        failures.push(VerificationFailure::UnknownCredential(*i));
+                        1
+                    }
+                };
+                if !signatures_valid.next().unwrap() {
+                    failures.push(VerificationFailure::BallotSignature(*i));
+                }
+                let well_formed = ballot.election_uuid == election.uuid
+                    && ballot.election_hash == fingerprint
+                    && ballot.answers.len() == election.questions.len();
+                if !proofs_valid.next().unwrap() || !well_formed {
+                    failures.push(VerificationFailure::BallotProof(*i));
+                }
+                match latest.get(&credential) {
+                    Some(j) => counted[*j] = Some((ballot, weight)),
+                    None => {
+                        latest.insert(credential, counted.len());
+                        counted.push(Some((ballot, weight)));
+                    }
+                }
+            }
+            Event::CredentialUpdate(C3M_CA_to_VS {
+                old_credential,
+                new_credential,
+            }) => {
+                let (old, new) = (old_credential.as_bytes(), new_credential.as_bytes());
+                if weights.contains_key(&new) {
+                    failures.push(VerificationFailure::CredentialUpdate(*i));
+                    continue;
+                }
+                match weights.remove(&old) {
+                    Some(wt) => weights.insert(new, wt),
+                    None => {
+                        failures.push(VerificationFailure::UnknownCredential(*i));
+                        continue;
+                    }
+                };
+                if let Some(j) = latest.remove(&old) {
+                    counted[j] = None;
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+    counted.into_iter().flatten().collect()
+}
+
+/// Checks the proofs of the partial decryptions, and that the result is the decryption of the
+/// encrypted tally.
+#[allow(clippy::too_many_arguments)]
+fn verify_decryption<G: Group>(
+    election: &Election<G>,
+    tally: &[Vec<Ciphertext<G>>],
+    trustee_keys: &[Point<G>],
+    threshold: Option<usize>,
+    partial_decryptions: &[Option<T3Mi<G>>],
+    result: Option<&Vec<Vec<u128>>>,
+    failures: &mut Vec<VerificationFailure>,
+) {
+    let mut valid: Vec<Option<T3Mi<G>>> = vec![None; trustee_keys.len()];
+    for i in trustee_keys.len()..partial_decryptions.len() {
+        failures.push(VerificationFailure::PartialDecryption(i));
+    }
+    for (i, pk) in trustee_keys.iter().enumerate() {
+        let pd = match partial_decryptions.get(i) {
+            Some(Some(pd)) => pd,
+            // "pedersen" trustees need not all take part in the decryption.
+            _ if threshold.is_some() => continue,
+            _ => {
+                failures.push(VerificationFailure::PartialDecryption(i));
+                continue;
+            }
+        };
+        let (factors, proofs) = (&pd.decryption_factors, &pd.decryption_proofs);
+        let context = ProofContext::new(&election.uuid, Some(i));
+        let verified = same_shape(factors, tally)
+            && same_shape(proofs, tally)
+            && (0..tally.len()).all(|q| {
+                (0..tally[q].len()).all(|a| {
+                    let instance = EqualDLog {
+                        pk: *pk,
+                        alpha: tally[q][a].alpha,
+                        factor: factors[q][a],
+                    };
+                    instance.verify(&context, &proofs[q][a])
+                })
+            });
+        if verified {
+            valid[i] = Some(pd.clone());
+        } else {
+            failures.push(VerificationFailure::PartialDecryption(i));
+        }
+    }
+    let result = match result {
+        Some(result) => result,
+        None => return,
+    };
+    let factors = match combine_partial_decryptions(tally, &valid, threshold) {
+        Ok(factors) => factors,
+        Err(_) => {
+            failures.push(VerificationFailure::NotEnoughPartialDecryptions);
+            return;
+        }
+    };
+    // Rather than solving discrete logarithms, check that beta - x * alpha = result * g.
+    let correct = result.len() == tally.len()
+        && (0..tally.len()).all(|q| {
+            result[q].len() == tally[q].len()
+                && (0..tally[q].len()).all(|a| {
+                    tally[q][a].beta - factors[q][a]
+                        == Scalar::from(result[q][a]) * Point::generator()
+                })
+        });
+    if !correct {
+        failures.push(VerificationFailure::Result);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::datatypes::bulletin_board::BulletinBoard;
+    use crate::participants::messages::*;
+    use crate::participants::participant_template::Participant;
+    use crate::participants::voting_server::tests::{setup_election, setup_pedersen_election};
+    use crate::primitives::group::Ristretto;
+    use ring::rand::SystemRandom;
+
+    /// Runs an election with two voters, the first of which votes twice.
+    fn election_record() -> BulletinBoard<Ristretto> {
+        let (voting_server, mut voters, trustees) = setup_election(vec![1, 2], 2);
+        let mut voting_server = voting_server;
+        let mut first = voters.remove(0);
+        for choices in [vec![true, false, false], vec![false, true, false]] {
+            let (voter, ballot) = first.process_message(V2Mi {
+                choices: vec![choices],
+            });
+            first = voter;
+            let (new_server, message) = voting_server.process_message(ballot);
+            message.check.unwrap();
+            voting_server = new_server;
+        }
+        let (_, ballot) = voters.remove(0).process_message(V2Mi {
+            choices: vec![vec![false, false, true]],
+        });
+        let (voting_server, message) = voting_server.process_message(ballot);
+        message.check.unwrap();
+        let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+        let partial_decryptions: Vec<T3Mi> = trustees
+            .into_iter()
+            .map(|t| t.process_message(encrypted_tally.clone()).1)
+            .collect();
+        let (voting_server, message) =
+            voting_server.process_message(T3M::from(partial_decryptions));
+        message.check.unwrap();
+        let (voting_server, _) = voting_server.process_message(EmptyMessage);
+        voting_server.bulletin_board().clone()
+    }
+
+    /// Publishes the (modified) events again, so that the chain of hashes is valid.
+    fn rechain(events: Vec<Event>) -> Vec<Entry> {
+        let mut board = BulletinBoard::new();
+        for event in events {
+            board.append(event);
+        }
+        board.entries().to_vec()
+    }
+
+    #[test]
+    fn test_verify_election() {
+        let board = election_record();
+        let entries = BulletinBoard::import(&board.export()).unwrap();
+        let report = verify_election(&entries);
+        assert!(report.is_valid(), "{:?}", report.failures);
+        assert_eq!(report.head, board.head());
+        let events: Vec<Event> = entries.iter().map(|e| e.event.clone()).collect();
+
+        // Modifying an entry in place breaks the chain after it, or changes the head of the log.
+        let mut modified = entries.clone();
+        modified[4].event = events[3].clone();
+        let report = verify_election(&modified);
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::BrokenChain(5),
+                VerificationFailure::EncryptedTally
+            ]
+        );
+        let mut modified = entries.clone();
+        modified[8].event = Event::Result(vec![vec![0, 3, 0]]);
+        let report = verify_election(&modified);
+        assert_eq!(report.failures, vec![VerificationFailure::Result]);
+        assert_ne!(report.head, board.head());
+
+        // A server dropping the second ballot of the first voter, after tallying it.
+        let mut dropped = events.clone();
+        dropped.remove(4);
+        let report = verify_election(&rechain(dropped));
+        assert_eq!(report.failures, vec![VerificationFailure::EncryptedTally]);
+
+        // A server counting the first ballot of the first voter, rather than the latest one.
+        let mut reordered = events.clone();
+        reordered.swap(3, 4);
+        let report = verify_election(&rechain(reordered));
+        assert_eq!(report.failures, vec![VerificationFailure::EncryptedTally]);
+
+        // A server accepting a partial decryption with a wrong decryption factor.
+        let mut forged = events.clone();
+        if let Event::PartialDecryptions(pds) = &mut forged[7] {
+            let pd = pds[1].as_mut().unwrap();
+            pd.decryption_factors[0][0] = pd.decryption_factors[0][0] + Point::generator();
+        }
+        let report = verify_election(&rechain(forged));
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::PartialDecryption(1),
+                VerificationFailure::NotEnoughPartialDecryptions
+            ]
+        );
+
+        // A server publishing the list L before the election.
+        let mut reordered = events.clone();
+        reordered.swap(0, 1);
+        let report = verify_election(&rechain(reordered));
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::UnexpectedEvent(1),
+                VerificationFailure::IncompleteRecord
+            ]
+        );
+    }
+
+    #[test]
+    fn test_verify_credentials() {
+        let events: Vec<Event> = election_record()
+            .entries()
+            .iter()
+            .map(|e| e.event.clone())
+            .collect();
+        let L = match &events[1] {
+            Event::Voters(L) => L.clone(),
+            _ => unreachable!(),
+        };
+        let first = match &events[3] {
+            Event::Ballot(ballot) => ballot.credential,
+            _ => unreachable!(),
+        };
+        let other = L.iter().find(|(pt, _)| *pt != first).unwrap().0;
+        // A list L with the same credential twice, so that the other voter's is unknown (and the
+        // weights of the tally are not the ones of L).
+        let mut duplicated = events.clone();
+        duplicated[1] = Event::Voters(vec![(first, 1), (first, 1)]);
+        let report = verify_election(&rechain(duplicated));
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::DuplicateCredential(1),
+                VerificationFailure::UnknownCredential(5),
+                VerificationFailure::EncryptedTally
+            ]
+        );
+        // A server replacing the credential of the first voter after they voted, which drops
+        // their ballot, but keeping it in the tally.
+        let mut updated = events.clone();
+        let update = C3M_CA_to_VS {
+            old_credential: first,
+            new_credential: Point::generator(),
+        };
+        updated.insert(5, Event::CredentialUpdate(update));
+        let report = verify_election(&rechain(updated));
+        assert_eq!(report.failures, vec![VerificationFailure::EncryptedTally]);
+        // Or replacing it with a credential already in use.
+        let mut updated = events.clone();
+        let update = C3M_CA_to_VS {
+            old_credential: first,
+            new_credential: other,
+        };
+        updated.insert(5, Event::CredentialUpdate(update));
+        let report = verify_election(&rechain(updated));
+        assert_eq!(
+            report.failures,
+            vec![VerificationFailure::CredentialUpdate(5)]
+        );
+    }
+
+    #[test]
+    fn test_verify_result_without_tally() {
+        let events: Vec<Event> = election_record()
+            .entries()
+            .iter()
+            .map(|e| e.event.clone())
+            .collect();
+        // A server publishing a result without the partial decryptions.
+        let mut unproven = events.clone();
+        unproven.remove(7);
+        let report = verify_election(&rechain(unproven));
+        assert_eq!(report.failures, vec![VerificationFailure::Result]);
+        // Or without the encrypted tally either.
+        let mut unproven = events.clone();
+        unproven.drain(6..8);
+        let report = verify_election(&rechain(unproven));
+        assert_eq!(report.failures, vec![VerificationFailure::Result]);
+    }
+
+    #[test]
+    fn test_verify_pedersen_trustee_keys() {
+        // Dealer 1 does not answer the complaint of trustee 0, and is disqualified.
+        let tamper_dealings = |dealings: &mut [P2Mi]| dealings[1].shares[0].data[0] ^= 1;
+        let tamper_responses = |responses: &mut [P5Mi]| responses[1].revealed.clear();
+        let (voting_server, _, _, check) =
+            setup_pedersen_election(vec![1], 2, 3, tamper_dealings, |_| {}, tamper_responses);
+        assert!(check.check.is_err());
+        let entries = voting_server.bulletin_board().entries().to_vec();
+        let report = verify_election(&entries);
+        assert!(report.is_valid(), "{:?}", report.failures);
+        let events: Vec<Event> = entries.iter().map(|e| e.event.clone()).collect();
+
+        // A server publishing a verification key which the commitments do not give (for which
+        // the trustee's proof fails too).
+        let mut forged = events.clone();
+        if let Event::TrusteeKeys { trustee_keys, .. } = &mut forged[2] {
+            trustee_keys[2] = trustee_keys[2] + Point::generator();
+        }
+        let report = verify_election(&rechain(forged));
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::TrusteeProof(2),
+                VerificationFailure::VerificationKey(2)
+            ]
+        );
+
+        // A server counting the commitments of the disqualified dealer.
+        let mut forged = events.clone();
+        if let Event::TrusteeKeys { commitments, .. } = &mut forged[2] {
+            assert!(commitments[1].is_none());
+            commitments[1] = commitments[0].clone();
+        }
+        // Its proof is bound to dealer 0, so that it is not counted.
+        let report = verify_election(&rechain(forged));
+        assert_eq!(report.failures, vec![VerificationFailure::Dealing(1)]);
+
+        // A server modifying a commitment.
+        let mut forged = events.clone();
+        if let Event::TrusteeKeys { commitments, .. } = &mut forged[2] {
+            let commitment = commitments[2].as_mut().unwrap();
+            commitment.coefficients[1] = commitment.coefficients[1] + Point::generator();
+        }
+        let report = verify_election(&rechain(forged));
+        assert_eq!(
+            report.failures,
+            vec![
+                VerificationFailure::VerificationKey(0),
+                VerificationFailure::VerificationKey(1),
+                VerificationFailure::VerificationKey(2),
+            ]
+        );
+    }
+}
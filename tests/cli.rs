@@ -0,0 +1,217 @@
+//! Runs a whole election with the `belenios` command-line tool, each participant working in its
+//! own directory.
+#![cfg(feature = "cli")]
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+fn belenios(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_belenios"))
+        .args(args)
+        .output()
+        .unwrap();
+    output
+}
+
+fn run(args: &[&str]) -> String {
+    let output = belenios(args);
+    assert!(
+        output.status.success(),
+        "belenios {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn dir(root: &Path, name: &str) -> PathBuf {
+    let dir = root.join(name);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn path(dir: &Path, file: &str) -> String {
+    dir.join(file).to_str().unwrap().to_string()
+}
+
+/// Runs an election in the group named `group`, or the default one.
+fn run_election(group: Option<&str>) {
+    let name = group.unwrap_or("default");
+    let root = std::env::temp_dir().join(format!("belenios-cli-{}-{}", name, std::process::id()));
+    let (admin, ca) = (dir(&root, "admin"), dir(&root, "ca"));
+    let trustees = [dir(&root, "trustee-0"), dir(&root, "trustee-1")];
+    let voters = dir(&root, "voters");
+
+    let group = group
+        .map(|group| format!(r#""group": "{}","#, group))
+        .unwrap_or_default();
+    let template = r#"{
+        GROUP
+        "voters": [1, 2, 3],
+        "election": {
+            "version": 1,
+            "name": "Test",
+            "description": "Test election",
+            "administrator": "Admin",
+            "credential_authority": "CA",
+            "questions": [
+                {"question": "Q1", "answers": ["A", "B", "C"], "blank": false, "min": 0, "max": 1}
+            ]
+        }
+    }"#
+    .replace("GROUP", &group);
+    std::fs::write(admin.join("template.json"), template).unwrap();
+    let template = path(&admin, "template.json");
+    let admin_dir = admin.to_str().unwrap();
+
+    run(&["setup", "--template", &template, "--dir", admin_dir]);
+    let request = path(&admin, "credential-request.json");
+    run(&[
+        "credentials",
+        "--request",
+        &request,
+        "--dir",
+        ca.to_str().unwrap(),
+    ]);
+    let keys: Vec<String> = trustees
+        .iter()
+        .enumerate()
+        .map(|(i, t)| {
+            let index = i.to_string();
+            let out = run(&[
+                "trustee-keygen",
+                "--request",
+                &request,
+                "--index",
+                &index,
+                "--dir",
+                t.to_str().unwrap(),
+            ]);
+            out.split_whitespace().last().unwrap().to_string()
+        })
+        .collect();
+    run(&[
+        "make-election",
+        "--template",
+        &template,
+        "--public-credentials",
+        &path(&ca, "public-credentials.json"),
+        "--trustee-keys",
+        &path(&trustees[0], "trustee-public-key.json"),
+        &path(&trustees[1], "trustee-public-key.json"),
+        "--dir",
+        admin_dir,
+    ]);
+    let election = path(&admin, "election.json");
+    let expected = name.replace("default", "RISTRETTO-25519");
+    let contents = std::fs::read_to_string(&election).unwrap();
+    assert!(contents.contains(&format!(r#""group":"{}""#, expected)));
+
+    // Voters 0 and 2 vote for the first and second answer, voter 1 abstains.
+    let mut ballots = Vec::new();
+    for (i, choices) in [(0, "[[true, false, false]]"), (2, "[[false, true, false]]")] {
+        let choices_path = voters.join(format!("choices-{}.json", i));
+        std::fs::write(&choices_path, choices).unwrap();
+        let ballot = path(&voters, &format!("ballot-{}.json", i));
+        run(&[
+            "vote",
+            "--credential",
+            &path(&ca, &format!("credential-{}.json", i)),
+            "--election",
+            &election,
+            "--choices",
+            choices_path.to_str().unwrap(),
+            "--out",
+            &ballot,
+        ]);
+        run(&[
+            "verify-ballot",
+            "--election",
+            &election,
+            "--ballot",
+            &ballot,
+        ]);
+        ballots.push(ballot);
+    }
+    run(&[
+        "aggregate",
+        "--ballots",
+        &ballots[0],
+        &ballots[1],
+        "--dir",
+        admin_dir,
+    ]);
+    // Resuming a participant at the wrong step fails.
+    assert!(!belenios(&["aggregate", "--dir", admin_dir])
+        .status
+        .success());
+
+    let tally = path(&admin, "encrypted-tally.json");
+    for (trustee, key) in trustees.iter().zip(keys.iter()) {
+        let dir = trustee.to_str().unwrap();
+        run(&[
+            "partial-decrypt",
+            "--tally",
+            &tally,
+            "--key",
+            key,
+            "--dir",
+            dir,
+        ]);
+    }
+    let out = run(&[
+        "finalize",
+        "--partial-decryptions",
+        &path(&trustees[0], "partial-decryption.json"),
+        &path(&trustees[1], "partial-decryption.json"),
+        "--dir",
+        admin_dir,
+    ]);
+    assert!(out.contains("Question 1: [1, 3, 0]"), "{}", out);
+    let head = out
+        .split_whitespace()
+        .skip_while(|w| *w != "head")
+        .nth(1)
+        .unwrap()
+        .to_string();
+
+    let board = path(&admin, "bulletin-board.bin");
+    run(&["verify-election", "--board", &board, "--head", &head]);
+    let mut other_head = head.clone().into_bytes();
+    other_head[0] = if other_head[0] == b'0' { b'1' } else { b'0' };
+    let other_head = String::from_utf8(other_head).unwrap();
+    let output = belenios(&["verify-election", "--board", &board, "--head", &other_head]);
+    assert!(!output.status.success());
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_cli_election() {
+    run_election(None);
+}
+
+#[test]
+fn test_cli_election_belenios_2048() {
+    run_election(Some("BELENIOS-2048"));
+}
+
+#[test]
+fn test_cli_unknown_group() {
+    let dir = std::env::temp_dir().join(format!("belenios-cli-group-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let template = dir.join("template.json");
+    let contents = r#"{"group": "P-256", "voters": [1], "election": {"version": 1, "name": "",
+        "description": "", "administrator": "", "credential_authority": "", "questions": []}}"#;
+    std::fs::write(&template, contents).unwrap();
+    let output = belenios(&[
+        "setup",
+        "--template",
+        template.to_str().unwrap(),
+        "--dir",
+        dir.to_str().unwrap(),
+    ]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("unsupported group P-256"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
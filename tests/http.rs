@@ -0,0 +1,158 @@
+//! Runs a whole election against the HTTP service of the voting server, on localhost.
+#![cfg(feature = "http")]
+#![allow(non_snake_case)]
+
+use belenios::datatypes::credentials::Password;
+use belenios::datatypes::questions::QuestionBuilder;
+use belenios::http::{Client, ClientError, HttpServer};
+use belenios::participants::credential_authority::CredentialAuthority;
+use belenios::participants::messages::*;
+use belenios::participants::participant_template::*;
+use belenios::participants::server_admin::ServerAdmin;
+use belenios::participants::trustee::{self, Trustee};
+use belenios::participants::voter::{self, Voter};
+use belenios::participants::voting_server::{self, VotingServer};
+use belenios::primitives::rng::SecureRandom;
+use belenios::verify::verify_election;
+use belenios::ProtocolError;
+use ring::rand::SystemRandom;
+use std::sync::{Arc, Mutex};
+
+/// Sets up an election up to the voting phase, returning the voting server along with the
+/// registered voters and the trustees.
+fn setup_election(
+    rng: Arc<Mutex<dyn SecureRandom>>,
+    weights: Vec<u128>,
+    num_trustees: usize,
+) -> (
+    VotingServer<voting_server::E11>,
+    Vec<Voter<voter::E5>>,
+    Vec<Trustee<trustee::E9>>,
+) {
+    let voters = E1MBuilder::default().voters(weights).build().unwrap();
+    let (_, message_SA) = ServerAdmin::new(rng.clone()).process_message(voters.clone());
+    let (voting_server, message_VS) = VotingServer::new(rng.clone()).process_message(voters);
+    let uuid = message_VS.uuid().clone();
+    let (credential_authority, credentials) =
+        CredentialAuthority::new(rng.clone()).process_message(E3M::from((message_VS, message_SA)));
+    let credentials: Vec<E4Mi> = credentials.into();
+    let voters = credentials
+        .into_iter()
+        .map(|c| Voter::new(rng.clone()).process_message(c).0)
+        .collect();
+    let (_, message) = credential_authority.process_message(EmptyMessage);
+    let (voting_server, message) = voting_server.process_message(message);
+    message.check.unwrap();
+    let (trustees, trustee_keys): (Vec<_>, Vec<_>) = (0..num_trustees)
+        .map(|index| {
+            let context = E8MiBuilder::default()
+                .uuid(uuid.clone())
+                .index(index)
+                .build()
+                .unwrap();
+            Trustee::new(rng.clone()).process_message(context)
+        })
+        .unzip();
+    let (voting_server, message) = voting_server.process_message(E9M::from(trustee_keys));
+    message.check.unwrap();
+    let question = QuestionBuilder::default()
+        .question("Q1")
+        .answers(vec!["A", "B", "C"])
+        .build()
+        .unwrap();
+    let election = E10MBuilder::default()
+        .questions(vec![question])
+        .version(1)
+        .description("Test election".to_string())
+        .name("Test".to_string())
+        .administrator("Admin".to_string())
+        .credential_authority("CA".to_string())
+        .build()
+        .unwrap();
+    let (voting_server, _) = voting_server.process_message(election);
+    (voting_server, voters, trustees)
+}
+
+#[test]
+fn test_http_election() {
+    let rng: Arc<Mutex<dyn SecureRandom>> = Arc::new(Mutex::new(SystemRandom::new()));
+    let admin_key = Password::gen(rng.clone());
+    let (voting_server, voters, trustees) = setup_election(rng.clone(), vec![1, 2, 3], 2);
+    let server = HttpServer::spawn(
+        "127.0.0.1:0",
+        voting_server.snapshot(&admin_key),
+        admin_key.clone(),
+    )
+    .unwrap();
+    let client = Client::new(&server.url());
+    let admin = Client::new(&server.url()).with_admin_key(admin_key);
+
+    // Each voter fetches the election from the server, and submits their ballot.
+    let election = client.election().unwrap();
+    let choices = [
+        vec![true, false, false],
+        vec![false, true, false],
+        vec![false, true, false],
+    ];
+    let ballots: Vec<V3Mi> = voters
+        .into_iter()
+        .zip(choices)
+        .map(|(voter, choices)| {
+            let (voter, _) = voter.process_message(election.clone());
+            let (_, ballot) = voter.process_message(V2Mi {
+                choices: vec![choices],
+            });
+            ballot
+        })
+        .collect();
+    for ballot in ballots.iter().take(2) {
+        client.submit_ballot(ballot).unwrap();
+    }
+
+    // A ballot without its signature is rejected, and does not enter the ballot box.
+    let mut unsigned = serde_json::to_value(&ballots[2]).unwrap();
+    unsigned["vote"]["signature"] = serde_json::Value::Null;
+    let unsigned: V3Mi = serde_json::from_value(unsigned).unwrap();
+    assert!(matches!(
+        client.submit_ballot(&unsigned),
+        Err(ClientError::Protocol(ProtocolError::BallotSignatureError))
+    ));
+    assert_eq!(client.ballots().unwrap().len(), 2);
+
+    // Only the administrator closes the election, after which ballots are refused.
+    assert!(matches!(client.close(), Err(ClientError::Status(401, _))));
+    admin.close().unwrap();
+    assert!(matches!(
+        client.submit_ballot(&ballots[2]),
+        Err(ClientError::Status(409, _))
+    ));
+
+    let encrypted_tally = client.encrypted_tally().unwrap();
+    let partial_decryptions: Vec<T3Mi> = trustees
+        .into_iter()
+        .map(|trustee| trustee.process_message(encrypted_tally.clone()).1)
+        .collect();
+    // A partial decryption which is not the trustee's own is rejected on submission.
+    assert!(matches!(
+        client.submit_partial_decryption(1, &partial_decryptions[0]),
+        Err(ClientError::Protocol(ProtocolError::PartialDecryptionProofFailedError(i))) if i == vec![1]
+    ));
+    for (i, partial_decryption) in partial_decryptions.iter().enumerate() {
+        client
+            .submit_partial_decryption(i, partial_decryption)
+            .unwrap();
+    }
+    // Nor can an accepted partial decryption be replaced.
+    assert!(matches!(
+        client.submit_partial_decryption(0, &partial_decryptions[1]),
+        Err(ClientError::Status(409, _))
+    ));
+    let result = admin.tally().unwrap();
+    assert_eq!(result.result.unwrap(), vec![vec![1, 2, 0]]);
+    assert_eq!(client.result().unwrap().num_tallied, 2);
+
+    // Anybody may check the whole election from its bulletin board.
+    let entries = client.bulletin_board().unwrap();
+    let report = verify_election(&entries);
+    assert!(report.is_valid(), "{:?}", report.failures);
+}
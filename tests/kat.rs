@@ -0,0 +1,151 @@
+//! Known-answer tests: a whole election run with deterministic RNGs, whose messages must match
+//! the vectors of `tests/vectors/kat.json`.
+//!
+//! Any change to the randomness drawn, to the proofs or to the encoding of messages changes the
+//! vectors. When such a change is intended, regenerate them with
+//! `BELENIOS_UPDATE_KAT=1 cargo test --test kat`, and commit them along with the change.
+#![cfg(feature = "deterministic-rng")]
+#![allow(non_snake_case)]
+
+use belenios::datatypes::questions::QuestionBuilder;
+use belenios::participants::credential_authority::CredentialAuthority;
+use belenios::participants::messages::*;
+use belenios::participants::participant_template::*;
+use belenios::participants::server_admin::ServerAdmin;
+use belenios::participants::trustee::Trustee;
+use belenios::participants::voter::Voter;
+use belenios::participants::voting_server::VotingServer;
+use belenios::primitives::rng::{DeterministicRandom, SecureRandom};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+const VECTORS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/vectors/kat.json");
+
+/// The RNG of a participant, seeded with its name so that the randomness of each participant
+/// does not depend on that of the others.
+fn rng(name: &str) -> Arc<Mutex<dyn SecureRandom>> {
+    let mut seed = [0; 32];
+    seed[..name.len()].copy_from_slice(name.as_bytes());
+    Arc::new(Mutex::new(DeterministicRandom::new(seed)))
+}
+
+fn to_json<T: Serialize>(messages: &[T]) -> Value {
+    serde_json::to_value(messages).unwrap()
+}
+
+/// Runs an election with 3 voters and 2 trustees, returning its messages.
+fn run_election() -> Value {
+    let voters = E1MBuilder::default().voters(vec![1, 2, 3]).build().unwrap();
+    let (_, message_SA) = ServerAdmin::new(rng("server admin")).process_message(voters.clone());
+    let voting_server = VotingServer::new(rng("voting server"));
+    let (voting_server, message_VS) = voting_server.process_message(voters);
+    let uuid = message_VS.uuid().clone();
+    let credential_authority = CredentialAuthority::new(rng("credential authority"));
+    let (credential_authority, credentials) =
+        credential_authority.process_message(E3M::from((message_VS, message_SA)));
+    let credentials: Vec<E4Mi> = credentials.into();
+    let (_, L) = credential_authority.process_message(EmptyMessage);
+    let public_credentials = serde_json::to_value(&L).unwrap();
+    let (voting_server, message) = voting_server.process_message(L);
+    message.check.unwrap();
+
+    let (trustees, trustee_keys): (Vec<_>, Vec<_>) = (0..2)
+        .map(|index| {
+            let context = E8MiBuilder::default()
+                .uuid(uuid.clone())
+                .index(index)
+                .build()
+                .unwrap();
+            Trustee::new(rng(&format!("trustee {}", index))).process_message(context)
+        })
+        .unzip();
+    let trustee_keys_json = to_json(&trustee_keys);
+    let (voting_server, message) = voting_server.process_message(E9M::from(trustee_keys));
+    message.check.unwrap();
+
+    let question = QuestionBuilder::default()
+        .question("Q1")
+        .answers(vec!["A", "B", "C"])
+        .build()
+        .unwrap();
+    let election = E10MBuilder::default()
+        .questions(vec![question])
+        .version(1)
+        .description("Known-answer tests".to_string())
+        .name("KAT".to_string())
+        .administrator("Admin".to_string())
+        .credential_authority("CA".to_string())
+        .build()
+        .unwrap();
+    let (voting_server, election) = voting_server.process_message(election);
+    let choices = [
+        vec![true, false, false],
+        vec![false, true, false],
+        vec![false, false, true],
+    ];
+    let ballots: Vec<V3Mi> = credentials
+        .iter()
+        .zip(choices)
+        .enumerate()
+        .map(|(i, (credential, choices))| {
+            let voter = Voter::new(rng(&format!("voter {}", i)));
+            let (voter, _) = voter.process_message(credential.clone());
+            let (voter, _) = voter.process_message(election.clone());
+            let (_, ballot) = voter.process_message(V2Mi {
+                choices: vec![choices],
+            });
+            ballot
+        })
+        .collect();
+    let (mut voting_server, _) = voting_server.process_message(EmptyMessage);
+    for ballot in ballots.iter() {
+        let (server, message) = voting_server.process_message(ballot.clone());
+        message.check.unwrap();
+        voting_server = server;
+    }
+
+    let (voting_server, encrypted_tally) = voting_server.process_message(EmptyMessage);
+    let partial_decryptions: Vec<T3Mi> = trustees
+        .into_iter()
+        .map(|trustee| trustee.process_message(encrypted_tally.clone()).1)
+        .collect();
+    let partial_decryptions_json = to_json(&partial_decryptions);
+    let (voting_server, message) = voting_server.process_message(T3M::from(partial_decryptions));
+    message.check.unwrap();
+    let (_, result) = voting_server.process_message(EmptyMessage);
+    assert_eq!(result.result.as_ref().unwrap(), &vec![vec![1, 2, 3]]);
+
+    json!({
+        "uuid": uuid,
+        "credentials": to_json(&credentials),
+        "public_credentials": public_credentials,
+        "trustee_keys": trustee_keys_json,
+        "ballots": to_json(&ballots),
+        "encrypted_tally": serde_json::to_value(&encrypted_tally).unwrap(),
+        "partial_decryptions": partial_decryptions_json,
+        "result": serde_json::to_value(&result).unwrap(),
+    })
+}
+
+#[test]
+fn test_known_answers() {
+    let vectors = run_election();
+    assert_eq!(vectors, run_election(), "the election is not reproducible");
+    if std::env::var_os("BELENIOS_UPDATE_KAT").is_some() {
+        // One vector per line, which keeps the diffs of the file readable.
+        let lines: Vec<String> = vectors
+            .as_object()
+            .unwrap()
+            .iter()
+            .map(|(name, vector)| format!("  {:?}: {}", name, vector))
+            .collect();
+        std::fs::write(VECTORS, format!("{{\n{}\n}}\n", lines.join(",\n"))).unwrap();
+        return;
+    }
+    let expected: Value = serde_json::from_str(&std::fs::read_to_string(VECTORS).unwrap()).unwrap();
+    for (name, expected) in expected.as_object().unwrap() {
+        assert_eq!(&vectors[name], expected, "the {} changed", name);
+    }
+    assert_eq!(vectors, expected);
+}